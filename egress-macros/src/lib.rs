@@ -0,0 +1,105 @@
+//! Procedural macros for `egress`. Use them through the `egress` crate's `macros` feature
+//! rather than depending on this crate directly.
+
+use {
+    proc_macro::TokenStream,
+    proc_macro2::Span,
+    quote::quote,
+    syn::{parse_macro_input, spanned::Spanned, FnArg, Ident, ItemFn, ReturnType, Type},
+};
+
+/// What the test function asks to be passed.
+enum Argument {
+    Egress,
+    Artifact,
+}
+
+/// Turn a function taking a `&mut Egress` or a `&mut Artifact` into a test which opens a
+/// context for it and closes it again, asserting that nothing regressed. See the `egress`
+/// crate's documentation for details.
+#[proc_macro_attribute]
+pub fn egress_test(attr: TokenStream, item: TokenStream) -> TokenStream {
+    if !attr.is_empty() {
+        return syn::Error::new(Span::call_site(), "`#[egress_test]` takes no arguments")
+            .to_compile_error()
+            .into();
+    }
+
+    let function = parse_macro_input!(item as ItemFn);
+    match expand(function) {
+        Ok(tokens) => tokens.into(),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
+fn expand(mut function: ItemFn) -> syn::Result<proc_macro2::TokenStream> {
+    let argument = argument(&function)?;
+    if let ReturnType::Type(_, ty) = &function.sig.output {
+        return Err(syn::Error::new(
+            ty.span(),
+            "`#[egress_test]` functions can't return anything",
+        ));
+    }
+
+    let name = function.sig.ident.clone();
+    let attrs = std::mem::take(&mut function.attrs);
+    let vis = std::mem::replace(&mut function.vis, syn::Visibility::Inherited);
+    let body = Ident::new("__egress_test_body", name.span());
+    function.sig.ident = body.clone();
+
+    let call = match argument {
+        Argument::Egress => quote!(#body(&mut egress)),
+        Argument::Artifact => quote!(#body(egress.artifact(stringify!(#name)))),
+    };
+
+    Ok(quote! {
+        #[test]
+        #(#attrs)*
+        #vis fn #name() {
+            #function
+
+            let mut egress = ::egress::Egress::open(
+                env!("CARGO_MANIFEST_DIR"),
+                format!("{}/{}", module_path!().replace("::", "/"), stringify!(#name)),
+            )
+            .expect("failed to open Egress context");
+            #call;
+            egress
+                .close_and_assert_unregressed()
+                .expect("failed to close Egress context");
+        }
+    })
+}
+
+fn argument(function: &ItemFn) -> syn::Result<Argument> {
+    let inputs = &function.sig.inputs;
+    let error = || {
+        syn::Error::new(
+            if inputs.is_empty() {
+                function.sig.span()
+            } else {
+                inputs.span()
+            },
+            "`#[egress_test]` functions take a single `&mut Egress` or `&mut Artifact` argument",
+        )
+    };
+
+    let ty = match (inputs.len(), inputs.first()) {
+        (1, Some(FnArg::Typed(pat_type))) => &*pat_type.ty,
+        _ => return Err(error()),
+    };
+    let elem = match ty {
+        Type::Reference(reference) if reference.mutability.is_some() => &*reference.elem,
+        _ => return Err(error()),
+    };
+    let ident = match elem {
+        Type::Path(path) => path.path.segments.last().map(|segment| &segment.ident),
+        _ => None,
+    };
+
+    match ident {
+        Some(ident) if ident == "Egress" => Ok(Argument::Egress),
+        Some(ident) if ident == "Artifact" => Ok(Argument::Artifact),
+        _ => Err(error()),
+    }
+}
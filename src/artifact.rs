@@ -2,33 +2,176 @@
 //! previously produced "reference" artifacts.
 
 use ::{
-    serde::{Deserialize, Serialize},
+    prettydiff::basic::DiffOp,
+    serde::{
+        de::{MapAccess, Visitor},
+        ser::SerializeMap,
+        Deserialize, Deserializer, Serialize, Serializer,
+    },
     serde_json::Value,
     std::{
-        collections::BTreeMap,
+        collections::{BTreeMap, HashSet},
         fmt::{self},
+        io::{self, Read, Write},
+        path::Path,
+        sync::Arc,
+        time::{Duration, SystemTime, UNIX_EPOCH},
     },
 };
 
-use crate::ErrorKind;
+use crate::{diff, metadata, nonfinite, sidecar, ErrorKind, Format};
+
+/// The key the hash of a hash-only artifact is stored under in its reference.
+pub(crate) const HASH_KEY: &str = "$sha256";
+
+/// How the absolute and relative tolerances combine when both are set.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ToleranceMode {
+    /// Both `|a - b| <= atol` and `|a - b| <= rtol * |b|` must hold.
+    #[default]
+    Separate,
+    /// The single predicate `|a - b| <= atol + rtol * |b|`, as used by numpy's `isclose`.
+    Combined,
+}
+
+/// The rules numbers are compared with.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub(crate) struct Tolerance {
+    pub atol: Option<f64>,
+    pub rtol: Option<f64>,
+    pub mode: ToleranceMode,
+    /// Whether an integer and a float are always different.
+    pub strict_number_types: bool,
+    /// Whether `atol` applies to two integers as well, rather than only to floats.
+    pub integer_atol: bool,
+    /// How many representable floats apart two floats may be and still match, regardless of
+    /// `atol` and `rtol`.
+    pub ulps: Option<u32>,
+    /// Whether floats were produced as `f32`s, so that they also match if they're within
+    /// `SINGLE_PRECISION_ULPS` of each other as `f32`s.
+    pub single_precision: bool,
+}
+
+/// How many representable `f32`s apart two floats in a single precision subtree may be.
+const SINGLE_PRECISION_ULPS: u64 = 4;
+
+impl Tolerance {
+    fn exact(self) -> Self {
+        Tolerance {
+            atol: None,
+            rtol: None,
+            ulps: None,
+            single_precision: false,
+            ..self
+        }
+    }
+
+    fn compare_integer(self, a: i128, b: i128) -> bool {
+        a == b
+            || (self.integer_atol
+                && self
+                    .atol
+                    .is_some_and(|atol| (a - b).unsigned_abs() as f64 <= atol))
+    }
+
+    fn compare_float(self, a: f64, b: f64) -> bool {
+        if self
+            .ulps
+            .is_some_and(|ulps| ulps_apart(a, b) <= u64::from(ulps))
+        {
+            return true;
+        }
+        if self.single_precision && ulps_apart_f32(a as f32, b as f32) <= SINGLE_PRECISION_ULPS {
+            return true;
+        }
+
+        let diff = (a - b).abs();
+        match (self.atol, self.rtol, self.mode) {
+            (None, None, _) => a == b,
+            (None, Some(rtol), _) => diff <= rtol * b.abs(),
+            (Some(atol), None, _) => diff <= atol,
+            (Some(atol), Some(rtol), ToleranceMode::Separate) => {
+                (diff <= rtol * b.abs()) && (diff <= atol)
+            }
+            (Some(atol), Some(rtol), ToleranceMode::Combined) => diff <= atol + rtol * b.abs(),
+        }
+    }
 
-fn compare_float(a: f64, b: f64, atol: Option<f64>, rtol: Option<f64>) -> bool {
-    match (atol, rtol) {
-        (None, None) => a == b,
-        (None, Some(rtol)) => (a - b) <= rtol * b.abs(),
-        (Some(atol), None) => (a - b).abs() <= atol,
-        (Some(atol), Some(rtol)) => ((a - b) <= rtol * b.abs()) && ((a - b).abs() <= atol),
+    /// Compare two strings holding numbers, like `"123456789012345678"` or `"1.0000001"`, as
+    /// the numbers they hold. Strings which don't both parse as numbers must be equal.
+    fn compare_numeric_strings(self, a: &str, b: &str) -> bool {
+        if let (Ok(a), Ok(b)) = (a.parse::<i128>(), b.parse::<i128>()) {
+            return self.compare_integer(a, b);
+        }
+        if self.strict_number_types && (a.parse::<i128>().is_ok() || b.parse::<i128>().is_ok()) {
+            return a == b;
+        }
+        match (a.parse::<f64>(), b.parse::<f64>()) {
+            (Ok(a), Ok(b)) => self.compare_float(a, b),
+            _ => a == b,
+        }
     }
 }
 
+/// The number of representable floats between `a` and `b`, or `u64::MAX` if either is NaN.
+/// `0.0` and `-0.0` are no floats apart.
+fn ulps_apart(a: f64, b: f64) -> u64 {
+    if a.is_nan() || b.is_nan() {
+        return u64::MAX;
+    }
+
+    // Map the bits onto integers which are ordered like the floats are, with both zeros at 0.
+    let ordered = |f: f64| {
+        let bits = f.to_bits() as i64;
+        if bits < 0 {
+            i64::MIN - bits
+        } else {
+            bits
+        }
+    };
+    (i128::from(ordered(a)) - i128::from(ordered(b))).unsigned_abs() as u64
+}
+
+/// Like `ulps_apart`, but counting the `f32`s between `a` and `b`.
+fn ulps_apart_f32(a: f32, b: f32) -> u64 {
+    if a.is_nan() || b.is_nan() {
+        return u64::MAX;
+    }
+
+    let ordered = |f: f32| {
+        let bits = f.to_bits() as i32;
+        if bits < 0 {
+            i32::MIN - bits
+        } else {
+            bits
+        }
+    };
+    (i64::from(ordered(a)) - i64::from(ordered(b))).unsigned_abs()
+}
+
 fn diff_json(
     mismatches: &mut Vec<Mismatch>,
+    settings: &Settings,
     prefix: String,
     value: &Value,
     reference: &Value,
-    atol: Option<f64>,
-    rtol: Option<f64>,
+    tolerance: Tolerance,
 ) {
+    if settings.is_ignored(&prefix) {
+        return;
+    }
+
+    if let Some(comparator) = settings.comparator(&prefix) {
+        let (value, reference) = (Entry::Json(value.clone()), Entry::Json(reference.clone()));
+        if !(comparator.0)(&value, &reference) {
+            mismatches.push(Mismatch::NotEq(prefix, value, reference));
+        }
+        return;
+    }
+
+    let tolerance = settings.tolerance_at(&prefix, tolerance);
+
     use Value::*;
     match (value, reference) {
         (Object(map), Object(reference_map)) => {
@@ -37,7 +180,7 @@ fn diff_json(
                     Some(it) => it,
                     None => {
                         mismatches.push(Mismatch::NotInReference(
-                            format!("{}.{}", prefix, k),
+                            key_path(&prefix, k),
                             Entry::Json(v.clone()),
                         ));
 
@@ -47,57 +190,92 @@ fn diff_json(
 
                 diff_json(
                     &mut *mismatches,
-                    format!("{}.{}", prefix, k),
+                    settings,
+                    key_path(&prefix, k),
                     v,
                     v_ref,
-                    atol,
-                    rtol,
+                    tolerance,
                 );
             }
 
             for (k, v_ref) in reference_map.iter() {
                 if !map.contains_key(k) {
                     mismatches.push(Mismatch::NotProduced(
-                        format!("{}.{}", prefix, k),
+                        key_path(&prefix, k),
                         Entry::Json(v_ref.clone()),
                     ));
                 }
             }
         }
         (Array(array), Array(array_ref)) => {
-            if array.len() != array_ref.len() {
+            if settings.is_truncated_stream(&prefix) {
+                diff_json_truncated(mismatches, settings, &prefix, array, array_ref, tolerance);
+                return;
+            }
 
+            if let Some(field) = settings.record_key(&prefix) {
+                if diff_json_records(
+                    mismatches, settings, &prefix, field, array, array_ref, tolerance,
+                ) {
+                    return;
+                }
+            }
+
+            if array.len() != array_ref.len() {
                 mismatches.push(Mismatch::LengthMismatch(
-                    format!("{}.len()", prefix),
+                    prefix.clone(),
                     array.len(),
                     array_ref.len(),
                     Entry::Json(array.clone().into()),
                     Entry::Json(array_ref.clone().into()),
                 ));
+            }
+
+            if settings.is_unordered(&prefix) {
+                diff_json_multiset(mismatches, &prefix, array, array_ref);
+            } else if array.len() != array_ref.len() {
+                diff_json_array(mismatches, settings, &prefix, array, array_ref, tolerance);
             } else {
                 for (i, (elem, elem_ref)) in array.iter().zip(array_ref.iter()).enumerate() {
                     diff_json(
                         &mut *mismatches,
-                        format!("{}[{}]", prefix, i),
+                        settings,
+                        index_path(&prefix, i),
                         elem,
                         elem_ref,
-                        atol,
-                        rtol,
+                        tolerance,
                     );
                 }
             }
         }
         (Number(a), Number(b)) => {
-            if let (Some(a), Some(b)) = (a.as_i64(), b.as_i64()) {
-                if a != b {
+            if tolerance.strict_number_types && a.is_f64() != b.is_f64() {
+                mismatches.push(Mismatch::NotEq(
+                    prefix,
+                    Entry::Json(a.clone().into()),
+                    Entry::Json(b.clone().into()),
+                ));
+            } else if let (Some(a), Some(b)) = (a.as_i64(), b.as_i64()) {
+                if !tolerance.compare_integer(a.into(), b.into()) {
+                    mismatches.push(Mismatch::NotEq(
+                        prefix,
+                        Entry::Json(a.into()),
+                        Entry::Json(b.into()),
+                    ));
+                }
+            } else if let (Some(a), Some(b)) = (a.as_u64(), b.as_u64()) {
+                // Too big for an `i64`, but an `f64` would lose precision.
+                if !tolerance.compare_integer(a.into(), b.into()) {
                     mismatches.push(Mismatch::NotEq(
                         prefix,
                         Entry::Json(a.into()),
                         Entry::Json(b.into()),
                     ));
                 }
-            } else if let (Some(a), Some(b)) = (a.as_f64(), b.as_f64()) {
-                if !compare_float(a, b, atol, rtol) {
+            } else if let (true, Some(a), Some(b)) =
+                (a.is_f64() || b.is_f64(), a.as_f64(), b.as_f64())
+            {
+                if !tolerance.compare_float(a, b) {
                     mismatches.push(Mismatch::NotEq(
                         prefix,
                         Entry::Json(a.into()),
@@ -114,6 +292,15 @@ fn diff_json(
                 }
             }
         }
+        (String(a), String(b)) if settings.is_numeric_string(&prefix) => {
+            if !tolerance.compare_numeric_strings(a, b) {
+                mismatches.push(Mismatch::NotEq(
+                    prefix,
+                    Entry::Json(a.clone().into()),
+                    Entry::Json(b.clone().into()),
+                ));
+            }
+        }
         (other, other_ref) => {
             if other != other_ref {
                 mismatches.push(Mismatch::NotEq(
@@ -126,6 +313,301 @@ fn diff_json(
     }
 }
 
+/// Diff an array which `insert_stream` cut short at its first mismatching element against the
+/// full reference, pairwise. Its length says nothing, so no `LengthMismatch` is reported.
+fn diff_json_truncated(
+    mismatches: &mut Vec<Mismatch>,
+    settings: &Settings,
+    prefix: &str,
+    array: &[Value],
+    array_ref: &[Value],
+    tolerance: Tolerance,
+) {
+    for (i, elem) in array.iter().enumerate() {
+        let path = index_path(prefix, i);
+        match array_ref.get(i) {
+            Some(elem_ref) => diff_json(mismatches, settings, path, elem, elem_ref, tolerance),
+            None => mismatches.push(Mismatch::NotInReference(path, Entry::Json(elem.clone()))),
+        }
+    }
+}
+
+/// Diff two arrays of records, objects which are told apart by the value of their `field`,
+/// matching up the records with the same key no matter where they are in the arrays. Records
+/// are reported at `prefix[field=key]`, e.g. `runs[id=7].duration`. Returns `false` without
+/// reporting anything if some element isn't an object with `field`, or if two records in the
+/// same array share a key.
+fn diff_json_records(
+    mismatches: &mut Vec<Mismatch>,
+    settings: &Settings,
+    prefix: &str,
+    field: &str,
+    array: &[Value],
+    array_ref: &[Value],
+    tolerance: Tolerance,
+) -> bool {
+    let key = |elem: &Value| {
+        elem.as_object()?.get(field).map(|key| match key {
+            Value::String(s) => s.clone(),
+            other => other.to_string(),
+        })
+    };
+    let records = |array: &[Value]| -> Option<BTreeMap<String, usize>> {
+        let mut records = BTreeMap::new();
+        for (i, elem) in array.iter().enumerate() {
+            if records.insert(key(elem)?, i).is_some() {
+                return None;
+            }
+        }
+        Some(records)
+    };
+    let (records, records_ref) = match (records(array), records(array_ref)) {
+        (Some(records), Some(records_ref)) => (records, records_ref),
+        _ => return false,
+    };
+
+    for (k, &i) in &records {
+        let path = index_path(prefix, format_args!("{}={}", field, k));
+        match records_ref.get(k) {
+            Some(&i_ref) => diff_json(
+                &mut *mismatches,
+                settings,
+                path,
+                &array[i],
+                &array_ref[i_ref],
+                tolerance,
+            ),
+            None => mismatches.push(Mismatch::NotInReference(
+                path,
+                Entry::Json(array[i].clone()),
+            )),
+        }
+    }
+    for (k, &i_ref) in &records_ref {
+        if !records.contains_key(k) {
+            mismatches.push(Mismatch::NotProduced(
+                index_path(prefix, format_args!("{}={}", field, k)),
+                Entry::Json(array_ref[i_ref].clone()),
+            ));
+        }
+    }
+
+    true
+}
+
+/// Diff two arrays of different lengths element by element. The elements are aligned along
+/// their longest common subsequence, so that inserting or removing a single element is
+/// reported as just that rather than as a change to every element after it. Elements only in
+/// the new array are reported at their index in the new array, and elements only in the
+/// reference at their index in the reference.
+fn diff_json_array(
+    mismatches: &mut Vec<Mismatch>,
+    settings: &Settings,
+    prefix: &str,
+    array: &[Value],
+    array_ref: &[Value],
+    tolerance: Tolerance,
+) {
+    let (mut i, mut i_ref) = (0, 0);
+    for op in prettydiff::basic::diff(array_ref, array) {
+        let (removed, inserted): (&[Value], &[Value]) = match op {
+            DiffOp::Equal(elems) => {
+                i += elems.len();
+                i_ref += elems.len();
+                continue;
+            }
+            DiffOp::Remove(elems) => (elems, &[]),
+            DiffOp::Insert(elems) => (&[], elems),
+            DiffOp::Replace(elems_ref, elems) => (elems_ref, elems),
+        };
+
+        // Changed elements are compared pairwise; whatever is left over on either side was
+        // added or removed.
+        let paired = removed.len().min(inserted.len());
+        for (elem, elem_ref) in inserted.iter().zip(removed) {
+            diff_json(
+                &mut *mismatches,
+                settings,
+                index_path(prefix, i),
+                elem,
+                elem_ref,
+                tolerance,
+            );
+            i += 1;
+            i_ref += 1;
+        }
+        for elem_ref in &removed[paired..] {
+            mismatches.push(Mismatch::NotProduced(
+                index_path(prefix, i_ref),
+                Entry::Json(elem_ref.clone()),
+            ));
+            i_ref += 1;
+        }
+        for elem in &inserted[paired..] {
+            mismatches.push(Mismatch::NotInReference(
+                index_path(prefix, i),
+                Entry::Json(elem.clone()),
+            ));
+            i += 1;
+        }
+    }
+}
+
+/// Diff two arrays as multisets, ignoring the order of their elements. Elements are matched up
+/// by exact equality, and those without a counterpart on the other side are reported at their
+/// index in their own array.
+fn diff_json_multiset(
+    mismatches: &mut Vec<Mismatch>,
+    prefix: &str,
+    array: &[Value],
+    array_ref: &[Value],
+) {
+    let mut unmatched_ref = array_ref.iter().enumerate().collect::<Vec<_>>();
+    for (i, elem) in array.iter().enumerate() {
+        match unmatched_ref
+            .iter()
+            .position(|(_, elem_ref)| *elem_ref == elem)
+        {
+            Some(matched) => {
+                unmatched_ref.remove(matched);
+            }
+            None => mismatches.push(Mismatch::NotInReference(
+                index_path(prefix, i),
+                Entry::Json(elem.clone()),
+            )),
+        }
+    }
+
+    for (i_ref, elem_ref) in unmatched_ref {
+        mismatches.push(Mismatch::NotProduced(
+            index_path(prefix, i_ref),
+            Entry::Json(elem_ref.clone()),
+        ));
+    }
+}
+
+fn redact_entry(settings: &Settings, path: String, entry: &mut Entry) {
+    if let Some(replacement) = settings.redaction(&path) {
+        *entry = replacement.clone();
+        return;
+    }
+
+    match entry {
+        Entry::Json(value) => redact_json(settings, path, value),
+        Entry::Artifact(art) => {
+            art.apply_redactions();
+            for (k, entry) in art.entries.iter_mut() {
+                redact_entry(settings, key_path(&path, k), entry);
+            }
+        }
+        Entry::Str(_) | Entry::Bytes(_) | Entry::Sidecar { .. } | Entry::Null => {}
+    }
+}
+
+fn redact_json(settings: &Settings, path: String, value: &mut Value) {
+    if let Some(replacement) = settings.redaction(&path) {
+        *value = match replacement {
+            Entry::Str(s) => Value::String(s.clone()),
+            Entry::Json(json) => json.clone(),
+            Entry::Bytes(bytes) => bytes.clone().into(),
+            Entry::Null => Value::Null,
+            Entry::Artifact(art) => {
+                serde_json::to_value(art).expect("artifacts always serialize to JSON")
+            }
+            sidecar @ Entry::Sidecar { .. } => {
+                serde_json::to_value(sidecar).expect("entries always serialize to JSON")
+            }
+        };
+        return;
+    }
+
+    match value {
+        Value::Object(map) => {
+            for (k, v) in map.iter_mut() {
+                redact_json(settings, key_path(&path, k), v);
+            }
+        }
+        Value::Array(array) => {
+            for (i, elem) in array.iter_mut().enumerate() {
+                redact_json(settings, index_path(&path, i), elem);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn merge_new_json_keys(value: &mut Value, produced: &Value) -> bool {
+    let mut added = false;
+    match (value, produced) {
+        (Value::Object(map), Value::Object(produced_map)) => {
+            for (k, produced_v) in produced_map {
+                match map.get_mut(k) {
+                    Some(v) => added |= merge_new_json_keys(v, produced_v),
+                    None => {
+                        map.insert(k.clone(), produced_v.clone());
+                        added = true;
+                    }
+                }
+            }
+        }
+        (Value::Array(array), Value::Array(produced_array)) => {
+            for (v, produced_v) in array.iter_mut().zip(produced_array) {
+                added |= merge_new_json_keys(v, produced_v);
+            }
+        }
+        _ => {}
+    }
+    added
+}
+
+fn is_escaped_control(c: char) -> bool {
+    c.is_control() && c != '\n' && c != '\t'
+}
+
+fn round_json(value: &mut Value, digits: u32) {
+    match value {
+        Value::Number(n) if n.is_f64() => {
+            let f = n.as_f64().expect("checked is_f64");
+            let rounded = format!("{:.*e}", digits.max(1) as usize - 1, f);
+            if let Some(rounded) = rounded.parse().ok().and_then(serde_json::Number::from_f64) {
+                *n = rounded;
+            }
+        }
+        Value::Object(map) => map.values_mut().for_each(|v| round_json(v, digits)),
+        Value::Array(array) => array.iter_mut().for_each(|v| round_json(v, digits)),
+        _ => {}
+    }
+}
+
+fn positive_zero_json(value: &mut Value) {
+    match value {
+        Value::Number(n) if n.as_f64() == Some(0.0) && n.is_f64() => {
+            *n = serde_json::Number::from_f64(0.0).expect("zero is finite");
+        }
+        Value::Object(map) => map.values_mut().for_each(positive_zero_json),
+        Value::Array(array) => array.iter_mut().for_each(positive_zero_json),
+        _ => {}
+    }
+}
+
+/// Rebuild every object in `value` with its keys in sorted order. `serde_json` objects keep
+/// their keys sorted anyway, unless something in the dependency graph turns on its
+/// `preserve_order` feature, in which case they keep insertion order instead.
+fn sort_json_keys(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            let mut entries = std::mem::take(map).into_iter().collect::<Vec<_>>();
+            entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+            for (_, v) in entries.iter_mut() {
+                sort_json_keys(v);
+            }
+            map.extend(entries);
+        }
+        Value::Array(array) => array.iter_mut().for_each(sort_json_keys),
+        _ => {}
+    }
+}
+
 /// Artifacts are maps from string keys to `Entry` objects. Entries in an
 /// artifact can be strings, JSON values, byte buffers, or - because
 /// artifacts are tree structured - another `Artifact`.
@@ -137,28 +619,617 @@ pub enum Entry {
     /// A JSON entry. The `Value` type comes from the `serde_json` crate.
     Json(Value),
 
-    /// A raw byte entry.
-    Bytes(Vec<u8>),
+    /// A raw byte entry. These are stored as base64 strings.
+    Bytes(#[serde(with = "crate::bytes")] Vec<u8>),
 
     /// An artifact entry.
     Artifact(Artifact),
+
+    /// An entry which was explicitly produced without a value, like a `None`. Unlike a
+    /// missing entry, a `Null` entry in the reference has to be produced again.
+    Null,
+
+    /// An entry too big to store inline, which was written to a sidecar file next to the
+    /// reference instead. These are only ever produced by Egress itself, when the
+    /// `inline_threshold_bytes` config option is set.
+    Sidecar {
+        /// The sidecar file's name, relative to the directory the reference is in.
+        path: String,
+        /// The SHA-256 hash of the sidecar file's contents, in hex.
+        hash: String,
+    },
+}
+
+impl Entry {
+    /// Serialize a value into a JSON entry, the way `Artifact::insert_serialize` does.
+    pub fn try_from_serialize<T: Serialize + ?Sized>(value: &T) -> Result<Entry, ErrorKind> {
+        Ok(Entry::Json(nonfinite::to_value(value)?))
+    }
+}
+
+impl From<String> for Entry {
+    fn from(s: String) -> Self {
+        Entry::Str(s)
+    }
+}
+
+impl From<&str> for Entry {
+    fn from(s: &str) -> Self {
+        Entry::Str(s.to_owned())
+    }
+}
+
+impl From<Value> for Entry {
+    fn from(value: Value) -> Self {
+        Entry::Json(value)
+    }
+}
+
+impl From<Vec<u8>> for Entry {
+    fn from(bytes: Vec<u8>) -> Self {
+        Entry::Bytes(bytes)
+    }
 }
 
 /// An `Artifact` is the main object that Egress uses to handle and compare
 /// data produced from your tests. It's basically just a map from string keys
 /// to `Entry`s.
-#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
-#[serde(transparent)]
+#[derive(Debug, Clone, Default)]
 pub struct Artifact {
     entries: BTreeMap<String, Entry>,
+    /// The keys of `entries` in the order they were inserted or read in, which is only used
+    /// for writing them out in that order if `Settings::preserve_order` is set.
+    order: Vec<String>,
+    /// Boxed, as otherwise every `Entry` would be as big as the settings.
+    settings: Box<Settings>,
+}
+
+/// Serialized as a map of its entries, sorted by key unless the artifact preserves insertion
+/// order.
+impl Serialize for Artifact {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if !self.settings.preserve_order {
+            return self.entries.serialize(serializer);
+        }
+
+        let mut map = serializer.serialize_map(Some(self.entries.len()))?;
+        let mut written = HashSet::new();
+        let ordered = self
+            .order
+            .iter()
+            .filter_map(|k| self.entries.get_key_value(k));
+        for (k, entry) in ordered.chain(&self.entries) {
+            if written.insert(k) {
+                map.serialize_entry(k, entry)?;
+            }
+        }
+        map.end()
+    }
+}
+
+/// Deserialized from a map of its entries, remembering the order they were read in.
+impl<'de> Deserialize<'de> for Artifact {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct ArtifactVisitor;
+
+        impl<'de> Visitor<'de> for ArtifactVisitor {
+            type Value = Artifact;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a map of artifact entries")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Artifact, A::Error> {
+                let mut artifact = Artifact::new();
+                while let Some((k, entry)) = map.next_entry::<String, Entry>()? {
+                    artifact.order.push(k.clone());
+                    artifact.entries.insert(k, entry);
+                }
+                Ok(artifact)
+            }
+        }
+
+        deserializer.deserialize_map(ArtifactVisitor)
+    }
+}
+
+impl PartialEq for Artifact {
+    fn eq(&self, other: &Self) -> bool {
+        self.entries == other.entries
+    }
+}
+
+/// Comparison settings for an `Artifact`. Key paths are relative to the artifact they're
+/// registered on, and these are never persisted.
+#[derive(Debug, Clone, Default)]
+struct Settings {
+    exact_subtrees: Vec<String>,
+    single_precision: Vec<String>,
+    numeric_strings: Vec<String>,
+    ignored: Vec<String>,
+    unordered: Vec<String>,
+    keyed: Vec<(String, String)>,
+    redactions: Vec<(String, Entry)>,
+    atol: Option<Option<f64>>,
+    rtol: Option<Option<f64>>,
+    subset: bool,
+    comparators: Vec<(String, Comparator)>,
+    tolerances: Vec<(String, Option<f64>, Option<f64>)>,
+    hash_only: bool,
+    preserve_order: bool,
+    streams: Option<Box<Streams>>,
+    /// While comparing a nested artifact, the settings of the artifact it's nested in, along
+    /// with its key there, so that key paths registered on the outer artifact which reach into
+    /// this one apply too.
+    outer: Option<Box<(String, Settings)>>,
+}
+
+/// The state of `insert_stream` for an artifact made by an `Egress` context.
+#[derive(Debug, Clone)]
+struct Streams {
+    context: StreamContext,
+    /// Entries which were cut short at their first mismatch.
+    truncated: Vec<String>,
+}
+
+/// What `Artifact::insert_stream` needs to compare items against the reference as they're
+/// produced, attached to the artifacts made by an `Egress` context.
+#[derive(Clone)]
+pub(crate) struct StreamContext {
+    /// Reads the reference on disk, if there is one.
+    pub load_reference: Arc<LoadReferenceFn>,
+    pub tolerance: Tolerance,
+    pub round_floats_to_digits: Option<u32>,
+    /// Whether the reference is going to be rewritten anyway, so every item is needed.
+    pub consume_all: bool,
+}
+
+pub(crate) type LoadReferenceFn = dyn Fn() -> Result<Option<Artifact>, ErrorKind> + Send + Sync;
+
+impl fmt::Debug for StreamContext {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("StreamContext")
+    }
+}
+
+/// A custom equality check registered with `Artifact::set_comparator`.
+#[derive(Clone)]
+struct Comparator(Arc<ComparatorFn>);
+
+type ComparatorFn = dyn Fn(&Entry, &Entry) -> bool + Send + Sync;
+
+impl fmt::Debug for Comparator {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("Comparator")
+    }
+}
+
+impl Settings {
+    /// The tolerance to compare this artifact with, given the one it would otherwise inherit.
+    fn tolerance(&self, inherited: Tolerance) -> Tolerance {
+        Tolerance {
+            atol: self.atol.unwrap_or(inherited.atol),
+            rtol: self.rtol.unwrap_or(inherited.rtol),
+            ..inherited
+        }
+    }
+
+    /// These settings for comparing the artifact nested under `key` in an artifact compared
+    /// with `outer`.
+    fn within(&self, key: &str, outer: &Settings) -> Settings {
+        Settings {
+            outer: Some(Box::new((key.to_owned(), outer.clone()))),
+            ..self.clone()
+        }
+    }
+
+    /// These settings and those of every artifact this one is nested in, innermost first, each
+    /// along with `path` as seen from it and the key path of this artifact as seen from it.
+    fn scopes(&self, path: &str) -> Vec<(&Settings, String, String)> {
+        let mut scopes = vec![(self, path.to_owned(), String::new())];
+        let mut settings = self;
+        while let Some((key, outer)) = settings.outer.as_deref() {
+            let (_, path, scope) = &scopes[scopes.len() - 1];
+            let scope = if scope.is_empty() {
+                key.clone()
+            } else {
+                key_path(key, scope)
+            };
+            let path = key_path(key, path);
+            scopes.push((outer, path, scope));
+            settings = outer;
+        }
+        scopes
+    }
+
+    /// The tolerance to compare numbers at or beneath `path` with: exact inside an exact
+    /// subtree, otherwise the one set for the closest enclosing key path with `set_tolerance`,
+    /// if any.
+    fn tolerance_at(&self, path: &str, inherited: Tolerance) -> Tolerance {
+        if self.is_exact(path) {
+            return inherited.exact();
+        }
+
+        let inherited = Tolerance {
+            single_precision: inherited.single_precision || self.is_single_precision(path),
+            ..inherited
+        };
+        // Key paths of outer artifacts which cover all of this one are already in `inherited`,
+        // where this artifact's own `set_atol` and `set_rtol` take precedence over them.
+        let tolerance = self
+            .scopes(path)
+            .into_iter()
+            .find_map(|(settings, path, scope)| {
+                settings
+                    .tolerances
+                    .iter()
+                    .rev()
+                    .find(|(subtree, ..)| {
+                        is_under(&path, subtree) && (scope.is_empty() || !is_under(&scope, subtree))
+                    })
+                    .map(|&(_, atol, rtol)| (atol, rtol))
+            });
+        match tolerance {
+            Some((atol, rtol)) => Tolerance {
+                atol,
+                rtol,
+                ..inherited
+            },
+            None => inherited,
+        }
+    }
+
+    /// Whether `path` is under any of the key paths `list` picks out of some scope's settings.
+    fn is_under_any(&self, path: &str, list: fn(&Settings) -> &Vec<String>) -> bool {
+        self.scopes(path).into_iter().any(|(settings, path, _)| {
+            list(settings)
+                .iter()
+                .any(|subtree| is_under(&path, subtree))
+        })
+    }
+
+    fn is_exact(&self, path: &str) -> bool {
+        self.is_under_any(path, |settings| &settings.exact_subtrees)
+    }
+
+    fn is_single_precision(&self, path: &str) -> bool {
+        self.is_under_any(path, |settings| &settings.single_precision)
+    }
+
+    fn is_numeric_string(&self, path: &str) -> bool {
+        self.is_under_any(path, |settings| &settings.numeric_strings)
+    }
+
+    fn is_ignored(&self, path: &str) -> bool {
+        self.is_under_any(path, |settings| &settings.ignored)
+    }
+
+    /// The field records in the array at `path` are keyed by, if it was set with `key_by`.
+    fn record_key(&self, path: &str) -> Option<&str> {
+        self.scopes(path)
+            .into_iter()
+            .find_map(|(settings, path, _)| {
+                settings
+                    .keyed
+                    .iter()
+                    .rev()
+                    .find(|(key_path, _)| matches_path(key_path, &path))
+            })
+            .map(|(_, field)| field.as_str())
+    }
+
+    fn is_truncated_stream(&self, path: &str) -> bool {
+        self.streams
+            .as_ref()
+            .is_some_and(|streams| streams.truncated.iter().any(|name| name == path))
+    }
+
+    fn is_unordered(&self, path: &str) -> bool {
+        self.scopes(path).into_iter().any(|(settings, path, _)| {
+            settings
+                .unordered
+                .iter()
+                .any(|key_path| matches_path(key_path, &path))
+        })
+    }
+
+    fn comparator(&self, path: &str) -> Option<&Comparator> {
+        self.scopes(path)
+            .into_iter()
+            .find_map(|(settings, path, _)| {
+                settings
+                    .comparators
+                    .iter()
+                    .find(|(key_path, _)| matches_path(key_path, &path))
+            })
+            .map(|(_, comparator)| comparator)
+    }
+
+    fn redaction(&self, path: &str) -> Option<&Entry> {
+        self.redactions
+            .iter()
+            .find(|(key_path, _)| matches_path(key_path, path))
+            .map(|(_, replacement)| replacement)
+    }
+}
+
+/// One key or array index of a key path, e.g. `fruits` or `[1]`.
+#[derive(Clone, Copy)]
+struct Segment<'a> {
+    text: &'a str,
+    index: bool,
+}
+
+/// Split a key path like `test.fruits[1]` into its segments: `test`, `fruits` and `[1]`.
+fn segments(path: &str) -> Vec<Segment<'_>> {
+    let mut segments = Vec::new();
+    let mut rest = path;
+    while !rest.is_empty() {
+        if let Some(index) = rest.strip_prefix('[') {
+            let end = index.find(']').unwrap_or(index.len());
+            segments.push(Segment {
+                text: &index[..end],
+                index: true,
+            });
+            rest = index.get(end + 1..).unwrap_or_default();
+        } else {
+            let key = rest.strip_prefix('.').unwrap_or(rest);
+            let end = key.find(['.', '[']).unwrap_or(key.len());
+            segments.push(Segment {
+                text: &key[..end],
+                index: false,
+            });
+            rest = &key[end..];
+        }
+    }
+    segments
+}
+
+/// Whether `text` matches `pattern`, where a `*` in `pattern` stands for any run of characters.
+fn glob(pattern: &str, text: &str) -> bool {
+    let mut pieces = pattern.split('*');
+    let first = pieces.next().unwrap_or_default();
+    let mut rest = match text.strip_prefix(first) {
+        Some(rest) => rest,
+        None => return false,
+    };
+
+    let pieces = pieces.collect::<Vec<_>>();
+    let (last, middle) = match pieces.split_last() {
+        Some(split) => split,
+        None => return rest.is_empty(),
+    };
+    for piece in middle {
+        match rest.find(piece) {
+            Some(i) => rest = &rest[i + piece.len()..],
+            None => return false,
+        }
+    }
+    rest.ends_with(last)
+}
+
+/// Match path segments against pattern segments. A `**` segment matches any number of
+/// segments, including none; any other segment matches one key or index of the same kind, with
+/// `*` standing for any run of characters. If `prefix` is set, `pattern` only has to match the
+/// start of `path`.
+fn match_segments(pattern: &[Segment], path: &[Segment], prefix: bool) -> bool {
+    match (pattern.split_first(), path.split_first()) {
+        (None, _) => prefix || path.is_empty(),
+        (Some((first, rest)), _) if first.text == "**" => {
+            (0..=path.len()).any(|skip| match_segments(rest, &path[skip..], prefix))
+        }
+        (Some(_), None) => false,
+        (Some((first, rest)), Some((segment, path))) => {
+            first.index == segment.index
+                && glob(first.text, segment.text)
+                && match_segments(rest, path, prefix)
+        }
+    }
+}
+
+/// Whether `path` is exactly the one described by `pattern`. A `*` in `pattern` stands for any
+/// single key or index, or part of one, and a `**` for any number of keys and indices, so
+/// `results.*.elapsed_ms` covers `results.a.elapsed_ms` and `results.**.elapsed_ms` also
+/// covers `results.a.b[0].elapsed_ms`.
+fn matches_path(pattern: &str, path: &str) -> bool {
+    match_segments(&segments(pattern), &segments(path), false)
 }
 
+/// Whether `path` is `subtree` itself or lies somewhere beneath it, with wildcards in `subtree`
+/// as for `matches_path`.
+fn is_under(path: &str, subtree: &str) -> bool {
+    match_segments(&segments(subtree), &segments(path), true)
+}
+
+/// The key path of the entry or object field `key` inside whatever is at `prefix`, e.g.
+/// `test.fruits` for `fruits` inside `test`. An empty prefix is the root of an artifact, so
+/// the key path is just `key`.
+pub(crate) fn key_path(prefix: &str, key: &str) -> String {
+    if prefix.is_empty() {
+        key.to_owned()
+    } else {
+        format!("{}.{}", prefix, key)
+    }
+}
+
+/// The key path of the array element at `index` inside the array at `prefix`, e.g.
+/// `test.fruits[1]`, or `test.runs[id=7]` for a record keyed with `key_by`.
+pub(crate) fn index_path(prefix: &str, index: impl fmt::Display) -> String {
+    format!("{}[{}]", prefix, index)
+}
+
+/// A difference found between a newly produced artifact and its reference. The first field of
+/// every variant is the key path of the offending entry, e.g. `test.fruits[1]`: the artifact's
+/// name followed by `.key` for every object key or nested artifact entry and `[i]` for every
+/// array index along the way. The components of a nested artifact name are always separated by
+/// `/`, e.g. `group/case1.fruits[1]`, whatever the platform.
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[allow(clippy::enum_variant_names)]
 pub enum Mismatch {
+    /// The entry exists in both, but the new value (first) differs from the reference (second).
     NotEq(String, Entry, Entry),
+    /// The entry was newly produced, but doesn't exist in the reference.
     NotInReference(String, Entry),
+    /// The entry exists in the reference, but wasn't produced this time.
     NotProduced(String, Entry),
+    /// An array changed length. Holds the new length, the reference's length, and then the
+    /// new and reference arrays themselves. This is a summary: the elements which were added,
+    /// removed or changed are reported as mismatches of their own.
     LengthMismatch(String, usize, usize, Entry, Entry),
+    /// There's no reference for the artifact at all, and the context isn't allowed to create
+    /// one. The path is the artifact's name.
+    MissingReference(String),
+}
+
+/// The kind of a `Mismatch`, without its key path or values.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[allow(clippy::enum_variant_names)]
+pub enum MismatchKind {
+    /// See `Mismatch::NotEq`.
+    NotEq,
+    /// See `Mismatch::NotInReference`.
+    NotInReference,
+    /// See `Mismatch::NotProduced`.
+    NotProduced,
+    /// See `Mismatch::LengthMismatch`.
+    LengthMismatch,
+    /// See `Mismatch::MissingReference`.
+    MissingReference,
+}
+
+/// A description of the mismatch, followed by a diff of the new value against the reference
+/// where there is one. Colored if colors are enabled.
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.describe(f, diff::colorize())
+    }
+}
+
+/// Displays a mismatch like its `Display` impl, but never with colors.
+pub(crate) struct Plain<'a>(pub &'a Mismatch);
+
+impl fmt::Display for Plain<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.0.describe(f, false)
+    }
+}
+
+impl Mismatch {
+    fn describe(&self, f: &mut fmt::Formatter, color: bool) -> fmt::Result {
+        match self {
+            Mismatch::NotEq(k, Entry::Str(new_value), Entry::Str(reference)) => {
+                writeln!(
+                    f,
+                    "entry `{}` not the same as the reference value",
+                    diff::highlight_key_path(k, color)
+                )?;
+                write!(
+                    f,
+                    "Diff:\n{}",
+                    diff::render_lines(reference, new_value, color)
+                )
+            }
+            Mismatch::NotEq(k, Entry::Bytes(new_value), Entry::Bytes(reference)) => {
+                writeln!(
+                    f,
+                    "entry `{}` not the same as the reference value",
+                    diff::highlight_key_path(k, color)
+                )?;
+                write!(
+                    f,
+                    "Diff:\n{}",
+                    diff::render_bytes(reference, new_value, color)
+                )
+            }
+            Mismatch::NotEq(k, new_value, reference) => {
+                writeln!(
+                    f,
+                    "entry `{}` not the same as the reference value",
+                    diff::highlight_key_path(k, color)
+                )?;
+                let old_s = serde_json::to_string(reference).map_err(|_| fmt::Error)?;
+                let new_s = serde_json::to_string(new_value).map_err(|_| fmt::Error)?;
+                write!(f, "Diff:\n {}", diff::render_inline(&old_s, &new_s, color))
+            }
+            Mismatch::NotInReference(k, new_value) => write!(
+                f,
+                "entry `{}` does not exist in the reference: {}",
+                diff::highlight_key_path(k, color),
+                diff::preview(new_value, false, color)
+            ),
+            Mismatch::NotProduced(k, reference) => write!(
+                f,
+                "entry `{}` exists in the reference but was not found here: {}",
+                diff::highlight_key_path(k, color),
+                diff::preview(reference, true, color)
+            ),
+            Mismatch::LengthMismatch(k, len, len_ref, new, reference) => {
+                writeln!(
+                    f,
+                    "array length for `{}` was {} but is now {}",
+                    diff::highlight_key_path(k, color),
+                    len_ref,
+                    len
+                )?;
+                let old_s = serde_json::to_string(reference).map_err(|_| fmt::Error)?;
+                let new_s = serde_json::to_string(new).map_err(|_| fmt::Error)?;
+                write!(f, "Diff:\n {}", diff::render_inline(&old_s, &new_s, color))
+            }
+            Mismatch::MissingReference(k) => write!(
+                f,
+                "no reference exists for artifact `{}`",
+                diff::highlight_key_path(k, color)
+            ),
+        }
+    }
+
+    /// Whether this is a key which is only in the new artifact, as opposed to an extra array
+    /// element or a difference in the value of a key both sides share.
+    pub(crate) fn is_new_key(&self) -> bool {
+        match self {
+            Mismatch::NotInReference(path, _) => !path.ends_with(']'),
+            _ => false,
+        }
+    }
+
+    /// The key path of the offending entry, whichever kind of mismatch this is.
+    pub fn path(&self) -> &str {
+        match self {
+            Mismatch::NotEq(path, ..)
+            | Mismatch::NotInReference(path, ..)
+            | Mismatch::NotProduced(path, ..)
+            | Mismatch::LengthMismatch(path, ..)
+            | Mismatch::MissingReference(path) => path,
+        }
+    }
+
+    /// What kind of mismatch this is, for telling them apart without matching on every
+    /// variant's fields.
+    pub fn kind(&self) -> MismatchKind {
+        match self {
+            Mismatch::NotEq(..) => MismatchKind::NotEq,
+            Mismatch::NotInReference(..) => MismatchKind::NotInReference,
+            Mismatch::NotProduced(..) => MismatchKind::NotProduced,
+            Mismatch::LengthMismatch(..) => MismatchKind::LengthMismatch,
+            Mismatch::MissingReference(..) => MismatchKind::MissingReference,
+        }
+    }
+
+    fn path_mut(&mut self) -> &mut String {
+        match self {
+            Mismatch::NotEq(path, ..)
+            | Mismatch::NotInReference(path, ..)
+            | Mismatch::NotProduced(path, ..)
+            | Mismatch::LengthMismatch(path, ..)
+            | Mismatch::MissingReference(path) => path,
+        }
+    }
+
+    fn prefixed(mut self, prefix: &str) -> Self {
+        let path = self.path_mut();
+        *path = key_path(prefix, path);
+        self
+    }
 }
 
 impl Artifact {
@@ -169,17 +1240,81 @@ impl Artifact {
         Self::default()
     }
 
+    /// Read an artifact from a reference file, in the format its extension belongs to, e.g.
+    /// to inspect a reference outside of a test. Sidecar entries are left as they are rather
+    /// than replaced with the contents of their sidecar files.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Artifact, ErrorKind> {
+        let path = path.as_ref();
+        let format = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .and_then(Format::from_extension)
+            .ok_or_else(|| ErrorKind::UnknownFormat(path.to_owned()))?;
+        format.deserialize(&crate::store::read_file(path)?)
+    }
+
     /// Insert an `Entry` into the artifact, with a given string name. The other
     /// `insert_*` methods are just convenient wrappers around this one.
+    ///
+    /// Panics if there's already an entry with the same name; see `try_insert` for a version
+    /// which doesn't.
     pub fn insert(&mut self, name: &str, entry: Entry) {
-        if self.entries.insert(name.to_string(), entry).is_some() {
-            panic!(
-                "Duplicate entries under the same name (`{}`) are not allowed!",
-                name
-            );
+        if let Err(err) = self.try_insert(name, entry) {
+            panic!("{}", err);
+        }
+    }
+
+    /// Insert an `Entry` into the artifact, with a given string name, returning
+    /// `ErrorKind::DuplicateEntry` and leaving the artifact untouched if the name is taken.
+    pub fn try_insert(&mut self, name: &str, entry: Entry) -> Result<(), ErrorKind> {
+        use std::collections::btree_map::Entry::*;
+        match self.entries.entry(name.to_owned()) {
+            Occupied(_) => Err(ErrorKind::DuplicateEntry(name.to_owned())),
+            Vacant(vacant) => {
+                vacant.insert(entry);
+                self.order.push(name.to_owned());
+                Ok(())
+            }
         }
     }
 
+    /// Remove the entry with the given name, returning it if there was one. The name can then
+    /// be inserted again.
+    pub fn remove(&mut self, name: &str) -> Option<Entry> {
+        self.order.retain(|k| k != name);
+        self.entries.remove(name)
+    }
+
+    /// Move every entry of `other` into this artifact, e.g. to fold in the artifacts produced by
+    /// several helper functions. Returns `ErrorKind::DuplicateEntry`, leaving this artifact
+    /// untouched, if any name is taken. The comparison settings of `other`, like ignored key
+    /// paths, are not carried over.
+    pub fn merge(&mut self, other: Artifact) -> Result<(), ErrorKind> {
+        if let Some(name) = other.entries.keys().find(|k| self.entries.contains_key(*k)) {
+            return Err(ErrorKind::DuplicateEntry(name.clone()));
+        }
+
+        let Artifact {
+            mut entries, order, ..
+        } = other;
+        for name in order {
+            if let Some(entry) = entries.remove(&name) {
+                self.try_insert(&name, entry)?;
+            }
+        }
+        for (name, entry) in entries {
+            self.try_insert(&name, entry)?;
+        }
+        Ok(())
+    }
+
+    /// Remove every entry from the artifact. Comparison settings like ignored key paths and
+    /// tolerances are kept.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.order.clear();
+    }
+
     /// Convert a value to a string via the `fmt::Debug` formatter and then insert
     /// that into the `Artifact` with the given string key.
     pub fn insert_debug<T: fmt::Debug>(&mut self, name: &str, value: &T) {
@@ -192,70 +1327,635 @@ impl Artifact {
         self.insert(name, Entry::Str(value.to_string()));
     }
 
+    /// Convert a value to a string via the alternate `fmt::Display` form, `{:#}`, and then
+    /// insert that into the `Artifact` with the given string key.
+    pub fn insert_display_alternate<T: fmt::Display>(&mut self, name: &str, value: &T) {
+        self.insert(name, Entry::Str(format!("{:#}", value)));
+    }
+
+    /// Run `f`, capturing everything it writes to stdout, and insert that into the `Artifact`
+    /// with the given string key. Trailing newlines are stripped and `\r\n` line endings are
+    /// turned into `\n`, so that the same output always makes the same entry.
+    ///
+    /// Stdout is redirected at the file descriptor level for the whole process while `f` runs,
+    /// and restored afterwards even if `f` panics. Note that the test harness intercepts
+    /// `print!` and `println!` before they reach the file descriptor, unless tests are run with
+    /// `--nocapture` or without the harness; anything written through `std::io::stdout()` is
+    /// captured either way.
+    pub fn insert_captured<F: FnOnce()>(&mut self, name: &str, f: F) -> Result<(), ErrorKind> {
+        io::stdout().flush()?;
+        let mut redirect = gag::BufferRedirect::stdout()?;
+        f();
+        io::stdout().flush()?;
+
+        let mut captured = String::new();
+        redirect.read_to_string(&mut captured)?;
+        drop(redirect);
+
+        let captured = captured.replace("\r\n", "\n");
+        self.insert(name, Entry::Str(captured.trim_end_matches('\n').to_owned()));
+        Ok(())
+    }
+
+    /// Copy a byte slice into the `Artifact` with the given string key. Unlike the string
+    /// inserters, the bytes don't have to be valid UTF-8.
+    pub fn insert_bytes(&mut self, name: &str, bytes: &[u8]) {
+        self.insert(name, Entry::Bytes(bytes.to_vec()));
+    }
+
     /// Convert a value to a JSON value via `serde_json` and then insert that into
     /// the `Artifact` with the given string key.
     ///
     /// Egress uses `serde` to do this, so if you want to be able to have nicely formatted
     /// diffs between your types, you'll want them to derive `serde::{Serialize}`.
+    ///
+    /// JSON can't represent NaN or infinite floats, so these are stored as the strings
+    /// `"NaN"`, `"Infinity"` and `"-Infinity"`. That way a NaN which stays a NaN compares
+    /// equal to its reference, rather than turning into a `null`.
     pub fn insert_serialize<T: Serialize>(
         &mut self,
         name: &str,
         value: &T,
     ) -> Result<(), ErrorKind> {
-        self.insert_json(name, serde_json::to_value(value)?);
+        self.insert_json(name, nonfinite::to_value(value)?);
         Ok(())
     }
 
+    /// Convert a value to a JSON value with `f` and insert that into the `Artifact` with the
+    /// given string key, for types which don't implement `Serialize` or which should be stored
+    /// in a different shape than their `Serialize` impl gives. Errors from `f` are passed on.
+    pub fn insert_serialize_with<T, F>(
+        &mut self,
+        name: &str,
+        value: &T,
+        f: F,
+    ) -> Result<(), ErrorKind>
+    where
+        T: ?Sized,
+        F: FnOnce(&T) -> Result<Value, ErrorKind>,
+    {
+        self.insert_json(name, f(value)?);
+        Ok(())
+    }
+
+    /// Insert a `Duration` into the `Artifact` with the given string key, as a float number of
+    /// nanoseconds. Unlike the struct `serde` would make of it, this is compared with the usual
+    /// float tolerances, with `atol` in nanoseconds.
+    pub fn insert_duration(&mut self, name: &str, duration: Duration) {
+        self.insert_json(name, (duration.as_nanos() as f64).into());
+    }
+
+    /// Insert a `SystemTime` into the `Artifact` with the given string key, as a float number of
+    /// milliseconds since the Unix epoch, negative for times before it. This is compared with
+    /// the usual float tolerances, with `atol` in milliseconds.
+    pub fn insert_system_time(&mut self, name: &str, time: SystemTime) {
+        let millis = match time.duration_since(UNIX_EPOCH) {
+            Ok(since) => since.as_nanos() as f64 / 1e6,
+            Err(before) => -(before.duration().as_nanos() as f64) / 1e6,
+        };
+        self.insert_json(name, millis.into());
+    }
+
+    /// Serialize every item of a collection into its own entry, keyed `prefix[0]`,
+    /// `prefix[1]` and so on, as with `insert_serialize`. Fails if any item fails to serialize,
+    /// or if one of the keys is already taken.
+    pub fn insert_serialize_all<T, I>(&mut self, prefix: &str, items: I) -> Result<(), ErrorKind>
+    where
+        T: Serialize,
+        I: IntoIterator<Item = T>,
+    {
+        for (i, item) in items.into_iter().enumerate() {
+            let value = nonfinite::to_value(&item)?;
+            self.try_insert(&index_path(prefix, i), Entry::Json(value))?;
+        }
+        Ok(())
+    }
+
+    /// Serialize the items of a possibly very long sequence one at a time, as with
+    /// `insert_serialize`, and insert them as a JSON array with the given string key.
+    ///
+    /// If this artifact was made by an `Egress` context and its reference in `artifact_subdir`
+    /// already has an array under this key, each item is compared against the reference as soon
+    /// as it's produced, and the iterator isn't consumed any further once an item doesn't match.
+    /// A sequence which regresses early then costs neither the time nor the memory of producing
+    /// the rest of it, and only that first mismatching item is reported. An item beyond the end
+    /// of the reference counts as a mismatch too.
+    ///
+    /// Since the new array is incomplete, blessing the report leaves its reference alone; rerun
+    /// with `EGRESS_UPDATE` or `EGRESS_RECORD` set, which consume the whole sequence, to accept
+    /// the change. Items are compared with the tolerances the context had when the artifact was
+    /// made. Arrays which are ignored, unordered, keyed with `key_by` or compared with a custom
+    /// comparator, and the entries of hash-only artifacts, are collected in full and compared on
+    /// `close` as usual.
+    pub fn insert_stream<T, I>(&mut self, name: &str, items: I) -> Result<(), ErrorKind>
+    where
+        T: Serialize,
+        I: IntoIterator<Item = T>,
+    {
+        if self.entries.contains_key(name) {
+            return Err(ErrorKind::DuplicateEntry(name.to_owned()));
+        }
+
+        let mut items = items.into_iter().fuse();
+        let (reference, context) = match (self.stream_reference(name)?, &self.settings.streams) {
+            (Some(reference), Some(streams)) => (reference, &streams.context),
+            _ => {
+                let values = items
+                    .map(|item| nonfinite::to_value(&item))
+                    .collect::<Result<Vec<_>, _>>()?;
+                return self.try_insert(name, Entry::Json(Value::Array(values)));
+            }
+        };
+
+        let tolerance = self.settings.tolerance(context.tolerance);
+        let digits = context.round_floats_to_digits;
+        let normalize = |path: &str, value: &mut Value| {
+            redact_json(&self.settings, path.to_owned(), value);
+            if let Some(digits) = digits {
+                round_json(value, digits);
+            }
+            positive_zero_json(value);
+        };
+
+        let mut values = Vec::new();
+        let mut truncated = false;
+        for mut value_ref in reference {
+            let item = match items.next() {
+                Some(item) => item,
+                None => break,
+            };
+
+            let path = index_path(name, values.len());
+            let mut value = nonfinite::to_value(&item)?;
+            normalize(&path, &mut value);
+            normalize(&path, &mut value_ref);
+
+            let mut found = Vec::new();
+            diff_json(
+                &mut found,
+                &self.settings,
+                path,
+                &value,
+                &value_ref,
+                tolerance,
+            );
+            values.push(value);
+            // New keys are only a regression if the context says so, which `close` decides.
+            if !found.iter().all(Mismatch::is_new_key) {
+                truncated = true;
+                break;
+            }
+        }
+        if !truncated {
+            if let Some(item) = items.next() {
+                values.push(nonfinite::to_value(&item)?);
+                truncated = true;
+            }
+        }
+
+        if let (true, Some(streams)) = (truncated, &mut self.settings.streams) {
+            streams.truncated.push(name.to_owned());
+        }
+        self.try_insert(name, Entry::Json(Value::Array(values)))
+    }
+
+    /// The reference array `insert_stream` should compare the items of `name` against as they
+    /// come, if it should at all.
+    fn stream_reference(&self, name: &str) -> Result<Option<Vec<Value>>, ErrorKind> {
+        let settings = &self.settings;
+        let context = match &settings.streams {
+            Some(streams) if !streams.context.consume_all => &streams.context,
+            _ => return Ok(None),
+        };
+        if settings.hash_only
+            || settings.is_ignored(name)
+            || settings.is_unordered(name)
+            || settings.record_key(name).is_some()
+            || settings.comparator(name).is_some()
+        {
+            return Ok(None);
+        }
+
+        let reference = (context.load_reference)()?;
+        Ok(
+            match reference.and_then(|mut reference| reference.entries.remove(name)) {
+                Some(Entry::Json(Value::Array(values))) => Some(values),
+                _ => None,
+            },
+        )
+    }
+
+    /// Insert an `Entry::Null` into the `Artifact` with the given string key, recording that the
+    /// entry was produced but had no value. This is reported as `NotEq` if the reference holds
+    /// a value instead, and as `NotProduced` if the entry is missing next time.
+    pub fn insert_null(&mut self, name: &str) {
+        self.insert(name, Entry::Null);
+    }
+
     /// Insert a JSON `Value` into the `Artifact` with the given string key.
     pub fn insert_json(&mut self, name: &str, json_value: Value) {
         self.insert(name, Entry::Json(json_value));
     }
 
-    fn compare_against_reference(
+    /// Compare every number at or beneath `key_path` exactly, no matter how loose the
+    /// tolerances configured on the `Egress` context are. Use this for values like hashes,
+    /// IDs and versions which must never be allowed to drift.
+    ///
+    /// Key paths are written the same way they show up in mismatches, relative to this
+    /// artifact: `key`, `key.field`, `key.list[0]`, or `key.nested_key` for entries of a
+    /// nested `Artifact`. An exact subtree always takes precedence over any tolerance.
+    pub fn exact_subtree(&mut self, key_path: &str) {
+        self.settings.exact_subtrees.push(key_path.to_owned());
+    }
+
+    /// Mark every float at or beneath `key_path` as produced as an `f32`, so that it also
+    /// matches its reference if the two are within a few representable `f32`s of each other,
+    /// on top of the usual tolerances. This absorbs the much larger rounding error of single
+    /// precision, including the difference between an `f32` and the `f64` computed the same
+    /// way. Exact subtrees are still compared exactly.
+    pub fn single_precision(&mut self, key_path: &str) {
+        self.settings.single_precision.push(key_path.to_owned());
+    }
+
+    /// Compare strings at or beneath `key_path` which hold numbers, like `"123456789012345678"`
+    /// or `"1.0000001"`, numerically with the usual tolerances, rather than exactly. This is for
+    /// serializers which write big integers and high-precision decimals as strings so as not to
+    /// lose precision. Strings which don't parse as numbers are still compared exactly.
+    ///
+    /// Key paths are written as for `exact_subtree`, and apply to string entries as well as to
+    /// strings inside JSON entries.
+    pub fn numeric_strings(&mut self, key_path: &str) {
+        self.settings.numeric_strings.push(key_path.to_owned());
+    }
+
+    /// Skip everything at or beneath `key_path` when comparing against the reference, so that
+    /// volatile values like timestamps, durations and random IDs never cause a regression.
+    /// Ignored values are still stored in the reference.
+    ///
+    /// Key paths are written as for `exact_subtree`. Any key or index may also be replaced by
+    /// a `*` wildcard, e.g. `metadata.*.timestamp` or `runs[*].duration`, and a `**` matches any
+    /// number of keys and indices, e.g. `results.**.elapsed_ms`.
+    pub fn ignore(&mut self, key_path: &str) {
+        self.settings.ignored.push(key_path.to_owned());
+    }
+
+    /// Treat the array at `key_path` as a set, or rather a multiset: reordering its elements
+    /// isn't a regression, but elements which are only in the new array or only in the
+    /// reference are still reported. Elements are matched up by exact equality, so tolerances
+    /// don't apply to them.
+    ///
+    /// Key paths are written as for `ignore`, wildcards included.
+    pub fn unordered(&mut self, key_path: &str) {
+        self.settings.unordered.push(key_path.to_owned());
+    }
+
+    /// Treat the array at `key_path` as a collection of records told apart by their `field`,
+    /// like an `id`, rather than by their position: records are matched up by the value of
+    /// `field`, so reordering them isn't a regression, and a change to a record is reported
+    /// under its key, e.g. `runs[id=7].duration`. Records only in the new array or only in the
+    /// reference are reported as `NotInReference` and `NotProduced`, rather than as a change
+    /// of length.
+    ///
+    /// If some element isn't an object with `field`, or two records in the array share a key,
+    /// the array is compared by position as usual. Key paths are written as for `ignore`,
+    /// wildcards included, and a `*` also matches a record's `[field=key]`.
+    pub fn key_by(&mut self, key_path: &str, field: &str) {
+        self.settings
+            .keyed
+            .push((key_path.to_owned(), field.to_owned()));
+    }
+
+    /// Replace whatever ends up at `key_path` with `replacement` before the artifact is stored
+    /// or compared, so that a volatile value like a random ID or a temporary path is recorded
+    /// as a stable placeholder rather than skipped entirely.
+    ///
+    /// Key paths are written as for `ignore`, wildcards included. Inside a JSON entry, a `Str`
+    /// replacement becomes a JSON string and a `Json` replacement is spliced in as is.
+    pub fn redact(&mut self, key_path: &str, replacement: Entry) {
+        self.settings
+            .redactions
+            .push((key_path.to_owned(), replacement));
+    }
+
+    /// Only require the reference to be a subset of this artifact: keys which are new in this
+    /// artifact, whether entries, fields of JSON objects or entries of nested artifacts, are not
+    /// reported as `NotInReference`. Keys missing from this artifact are still reported as
+    /// `NotProduced`, and arrays must still match element by element.
+    pub fn subset_match(&mut self) {
+        self.settings.subset = true;
+    }
+
+    /// Store only a SHA-256 hash of this artifact's serialized contents in its reference, for
+    /// artifacts too big to be worth reviewing or diffing, like a serialized model. Comparing is
+    /// then all or nothing: any change at all is reported as a single `NotEq` of the hashes.
+    ///
+    /// This only applies to the artifacts a testing context is closed with, not nested ones.
+    pub fn store_hash_only(&mut self) {
+        self.settings.hash_only = true;
+    }
+
+    /// Decide whether the value at `key_path` matches its reference with `comparator` instead
+    /// of the built-in comparison, for values which need a domain-specific notion of equality.
+    /// The comparator is given the new value and then the reference, and a mismatch is reported
+    /// if it returns `false`. Values inside JSON entries are passed as `Entry::Json`.
+    ///
+    /// Key paths are written as for `ignore`, wildcards included. Comparators only live on the
+    /// in-memory artifact and are never persisted.
+    pub fn set_comparator<F>(&mut self, key_path: &str, comparator: F)
+    where
+        F: Fn(&Entry, &Entry) -> bool + Send + Sync + 'static,
+    {
+        self.settings
+            .comparators
+            .push((key_path.to_owned(), Comparator(Arc::new(comparator))));
+    }
+
+    /// Compare numbers at or beneath `key_path` with their own absolute and relative tolerance,
+    /// leaving every other number in the artifact to be compared as before. This is for a
+    /// single value which legitimately drifts, without loosening the comparison of everything
+    /// else. `None` disables the respective check.
+    ///
+    /// Key paths are written as for `ignore`, wildcards included. Where several key paths
+    /// match, the one set last wins, and an exact subtree takes precedence over all of them.
+    pub fn set_tolerance(&mut self, key_path: &str, atol: Option<f64>, rtol: Option<f64>) {
+        self.settings
+            .tolerances
+            .push((key_path.to_owned(), atol, rtol));
+    }
+
+    /// Override the absolute tolerance used for this artifact, instead of inheriting it from
+    /// the `Egress` context or the enclosing artifact. `None` disables the absolute check.
+    pub fn set_atol(&mut self, atol: Option<f64>) {
+        self.settings.atol = Some(atol);
+    }
+
+    /// Override the relative tolerance used for this artifact, instead of inheriting it from
+    /// the `Egress` context or the enclosing artifact. `None` disables the relative check.
+    ///
+    /// Setting both `atol` and `rtol` to `None` makes every number in the artifact compare
+    /// exactly.
+    pub fn set_rtol(&mut self, rtol: Option<f64>) {
+        self.settings.rtol = Some(rtol);
+    }
+
+    /// Validate this artifact against a JSON Schema, independently of any reference. The
+    /// artifact is validated as a JSON object mapping each key to its value: string entries
+    /// become JSON strings, JSON entries are used as is, null entries become JSON nulls and
+    /// nested artifacts become nested objects. Byte entries are left out. Requires the `schema`
+    /// feature.
+    ///
+    /// On failure, returns a description of every violation, prefixed with its key path, e.g.
+    /// `result.count: "two" is not of type "integer"`.
+    #[cfg(feature = "schema")]
+    pub fn validate_schema(&self, schema: &Value) -> Result<(), Vec<String>> {
+        crate::schema::validate(self, schema)
+    }
+
+    /// Compare this artifact against a reference in memory, exactly as `Egress::close` would
+    /// compare it against the one on disk with the given tolerances. Key paths in the
+    /// mismatches are relative to this artifact, e.g. `fruits[1]` or `nested.fruit`.
+    ///
+    /// Tolerances set on the artifact itself with `set_atol` and `set_rtol` take precedence.
+    pub fn diff(
         &self,
-        prefix: String,
         reference: &Artifact,
         atol: Option<f64>,
         rtol: Option<f64>,
+    ) -> Vec<Mismatch> {
+        let tolerance = Tolerance {
+            atol,
+            rtol,
+            ..Tolerance::default()
+        };
+        self.compare_against_reference(
+            &self.settings,
+            reference,
+            self.settings.tolerance(tolerance),
+        )
+    }
+
+    pub(crate) fn entries(&self) -> &BTreeMap<String, Entry> {
+        &self.entries
+    }
+
+    pub(crate) fn entries_mut(&mut self) -> &mut BTreeMap<String, Entry> {
+        &mut self.entries
+    }
+
+    /// Apply the redactions registered on this artifact and any nested ones.
+    pub(crate) fn apply_redactions(&mut self) {
+        for (k, entry) in self.entries.iter_mut() {
+            redact_entry(&self.settings, k.clone(), entry);
+        }
+    }
+
+    pub(crate) fn set_stream_context(&mut self, context: StreamContext) {
+        self.settings.streams = Some(Box::new(Streams {
+            context,
+            truncated: Vec::new(),
+        }));
+    }
+
+    /// Whether `insert_stream` cut any of this artifact's entries short, so that it mustn't be
+    /// written out as a reference.
+    pub(crate) fn has_truncated_streams(&self) -> bool {
+        self.settings
+            .streams
+            .as_ref()
+            .is_some_and(|streams| !streams.truncated.is_empty())
+    }
+
+    pub(crate) fn is_hash_only(&self) -> bool {
+        self.settings.hash_only
+    }
+
+    /// Write this artifact's entries out in the order they were inserted, or read in, rather
+    /// than sorted by key. Nested artifacts are still sorted.
+    pub(crate) fn set_preserve_order(&mut self, preserve_order: bool) {
+        self.settings.preserve_order = preserve_order;
+    }
+
+    /// The hash-only form of this artifact: a single string entry under `HASH_KEY`, holding the
+    /// hash of its serialized entries. An artifact already in this form is returned as is.
+    pub(crate) fn hashed(&self) -> Artifact {
+        if self.entries.len() == 1 && self.entries.contains_key(HASH_KEY) {
+            return self.clone();
+        }
+
+        let serialized = serde_json::to_vec(&self.entries).expect("entries always serialize");
+        let mut hashed = Artifact::new();
+        hashed
+            .entries
+            .insert(HASH_KEY.to_owned(), Entry::Str(sidecar::hash(&serialized)));
+        hashed
+    }
+
+    /// Replace the control characters in the string entries of this artifact and any nested
+    /// ones, other than newlines and tabs, with visible escapes like `\u{1b}`.
+    pub(crate) fn escape_control_chars(&mut self) {
+        for entry in self.entries.values_mut() {
+            match entry {
+                Entry::Str(s) if s.chars().any(is_escaped_control) => {
+                    *s = s
+                        .chars()
+                        .map(|c| match c {
+                            c if is_escaped_control(c) => format!("\\u{{{:x}}}", c as u32),
+                            c => c.to_string(),
+                        })
+                        .collect();
+                }
+                Entry::Artifact(art) => art.escape_control_chars(),
+                _ => {}
+            }
+        }
+    }
+
+    /// Copy every key of `produced` which this artifact doesn't have yet into it, including
+    /// entries of nested artifacts and fields of JSON objects, leaving the keys it already has
+    /// untouched. Returns whether anything was added.
+    pub(crate) fn merge_new_keys(&mut self, produced: &Artifact) -> bool {
+        let mut added = false;
+        for (k, entry) in produced.entries.iter() {
+            if k == metadata::KEY {
+                continue;
+            }
+
+            match (self.entries.get_mut(k), entry) {
+                (None, _) => {
+                    self.entries.insert(k.clone(), entry.clone());
+                    self.order.push(k.clone());
+                    added = true;
+                }
+                (Some(Entry::Artifact(art)), Entry::Artifact(produced_art)) => {
+                    added |= art.merge_new_keys(produced_art);
+                }
+                (Some(Entry::Json(value)), Entry::Json(produced_value)) => {
+                    added |= merge_new_json_keys(value, produced_value);
+                }
+                _ => {}
+            }
+        }
+        added
+    }
+
+    /// Round every float in the JSON entries of this artifact and any nested ones to `digits`
+    /// significant digits. Integers are left alone.
+    pub(crate) fn round_floats(&mut self, digits: u32) {
+        for entry in self.entries.values_mut() {
+            match entry {
+                Entry::Json(value) => round_json(value, digits),
+                Entry::Artifact(art) => art.round_floats(digits),
+                _ => {}
+            }
+        }
+    }
+
+    /// Replace every `-0.0` in the JSON entries of this artifact and any nested ones with `0.0`.
+    /// The two compare equal anyway, but serialize differently.
+    pub(crate) fn positive_zeros(&mut self) {
+        for entry in self.entries.values_mut() {
+            match entry {
+                Entry::Json(value) => positive_zero_json(value),
+                Entry::Artifact(art) => art.positive_zeros(),
+                _ => {}
+            }
+        }
+    }
+
+    /// Sort the keys of every object in the JSON entries of this artifact and any nested ones,
+    /// so that references are written the same way whichever features `serde_json` was built
+    /// with.
+    pub(crate) fn sort_json_keys(&mut self) {
+        for entry in self.entries.values_mut() {
+            match entry {
+                Entry::Json(value) => sort_json_keys(value),
+                Entry::Artifact(art) => art.sort_json_keys(),
+                _ => {}
+            }
+        }
+    }
+
+    /// Copy the comparison settings of `other`, and of its nested artifacts, onto this one.
+    /// Used after rebuilding an artifact from its serialized form.
+    #[cfg(feature = "jcs")]
+    pub(crate) fn copy_settings_from(&mut self, other: &Artifact) {
+        self.settings = other.settings.clone();
+        for (k, entry) in self.entries.iter_mut() {
+            if let (Entry::Artifact(art), Some(Entry::Artifact(other_art))) =
+                (entry, other.entries.get(k))
+            {
+                art.copy_settings_from(other_art);
+            }
+        }
+    }
+
+    /// Compare against a reference with `settings`, which are this artifact's own unless it's
+    /// nested in another, producing mismatches whose paths are relative to this artifact.
+    /// `tolerance` must already account for this artifact's own overrides.
+    fn compare_against_reference(
+        &self,
+        settings: &Settings,
+        reference: &Artifact,
+        tolerance: Tolerance,
     ) -> Vec<Mismatch> {
         let mut mismatches = Vec::new();
 
         for (k, v) in self.entries.iter() {
+            if settings.is_ignored(k) || k == metadata::KEY {
+                continue;
+            }
+
             let v_ref = match reference.entries.get(k) {
                 Some(it) => it,
                 None => {
-                    mismatches.push(Mismatch::NotInReference(
-                        format!("{}::{}", prefix, k),
-                        v.clone(),
-                    ));
+                    mismatches.push(Mismatch::NotInReference(k.clone(), v.clone()));
                     continue;
                 }
             };
 
+            if let Some(comparator) = settings.comparator(k) {
+                if !(comparator.0)(v, v_ref) {
+                    mismatches.push(Mismatch::NotEq(k.clone(), v.clone(), v_ref.clone()));
+                }
+                continue;
+            }
+
             use Entry::*;
             match (v, v_ref) {
                 (Artifact(art), Artifact(art_ref)) => {
-                    mismatches.extend(art.compare_against_reference(
-                        format!("{}::{}", prefix, k),
-                        art_ref,
-                        atol,
-                        rtol,
-                    ));
+                    let tolerance = if settings.is_exact(k) {
+                        tolerance.exact()
+                    } else {
+                        art.settings.tolerance(settings.tolerance_at(k, tolerance))
+                    };
+
+                    let nested = art.settings.within(k, settings);
+                    mismatches.extend(
+                        art.compare_against_reference(&nested, art_ref, tolerance)
+                            .into_iter()
+                            .map(|mismatch| mismatch.prefixed(k)),
+                    );
+                }
+                (Str(s), Str(s_ref)) if settings.is_numeric_string(k) => {
+                    let tolerance = settings.tolerance_at(k, tolerance);
+                    if !tolerance.compare_numeric_strings(s, s_ref) {
+                        mismatches.push(Mismatch::NotEq(k.clone(), v.clone(), v_ref.clone()));
+                    }
                 }
                 (Json(json), Json(json_ref)) => {
                     diff_json(
                         &mut mismatches,
-                        format!("{}::{}", prefix, k),
+                        settings,
+                        k.clone(),
                         json,
                         json_ref,
-                        atol,
-                        rtol,
+                        tolerance,
                     );
                 }
                 (other, other_ref) => {
                     if other != other_ref {
                         mismatches.push(Mismatch::NotEq(
-                            format!("{}::{}", prefix, k),
+                            k.clone(),
                             other.clone(),
                             other_ref.clone(),
                         ));
@@ -265,14 +1965,17 @@ impl Artifact {
         }
 
         for (k_ref, v_ref) in reference.entries.iter() {
-            if !self.entries.contains_key(k_ref) {
-                mismatches.push(Mismatch::NotProduced(
-                    format!("{}::{}", prefix, k_ref),
-                    v_ref.clone(),
-                ));
+            if !self.entries.contains_key(k_ref) && k_ref != metadata::KEY {
+                mismatches.push(Mismatch::NotProduced(k_ref.clone(), v_ref.clone()));
             }
         }
 
+        // Keys missing on one side, and paths reaching into nested artifacts, aren't filtered
+        // while diffing.
+        mismatches.retain(|mismatch| !settings.is_ignored(mismatch.path()));
+        if settings.subset {
+            mismatches.retain(|mismatch| !mismatch.is_new_key());
+        }
         mismatches
     }
 
@@ -280,9 +1983,15 @@ impl Artifact {
         &self,
         prefix: String,
         reference: &Artifact,
-        atol: Option<f64>,
-        rtol: Option<f64>,
+        tolerance: Tolerance,
     ) -> Vec<Mismatch> {
-        self.compare_against_reference(prefix, reference, atol, rtol)
+        self.compare_against_reference(
+            &self.settings,
+            reference,
+            self.settings.tolerance(tolerance),
+        )
+        .into_iter()
+        .map(|mismatch| mismatch.prefixed(&prefix))
+        .collect()
     }
 }
@@ -0,0 +1,135 @@
+//! A command line tool for inspecting and tidying up Egress references.
+
+use {
+    egress::{Artifact, Egress, ErrorKind},
+    std::{path::PathBuf, process},
+};
+
+const USAGE: &str = "\
+usage: egress [--config-dir DIR] <command>
+
+commands:
+    list [SUBDIR]                         list the references in an artifact subdirectory
+    show FILE                             pretty-print a reference
+    diff REFERENCE NEW [--atol X] [--rtol X]
+                                          compare two references the way tests do, with the
+                                          settings in `Egress.toml`
+    prune SUBDIR NAME...                  delete the references in an artifact subdirectory
+                                          which don't belong to any of the named artifacts
+    prune-sidecars [SUBDIR]               delete sidecar files no reference refers to, and
+                                          temporary files left by interrupted writes
+
+`--config-dir` is the directory containing `Egress.toml`, and defaults to the current one. It's
+never created: `list`, `diff`, `prune` and `prune-sidecars` fail if it's missing. `--atol` and
+`--rtol` override the tolerances it sets.";
+
+fn main() {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    match run(args) {
+        Ok(code) => process::exit(code),
+        Err(Error::Usage(message)) => {
+            eprintln!("error: {}\n\n{}", message, USAGE);
+            process::exit(2);
+        }
+        Err(Error::Egress(err)) => {
+            eprintln!("error: {}", err);
+            process::exit(2);
+        }
+    }
+}
+
+enum Error {
+    Usage(String),
+    Egress(ErrorKind),
+}
+
+impl From<ErrorKind> for Error {
+    fn from(err: ErrorKind) -> Self {
+        Error::Egress(err)
+    }
+}
+
+/// Run a command, returning the exit code: `1` if `diff` found mismatches, `0` otherwise.
+fn run(args: Vec<String>) -> Result<i32, Error> {
+    let mut config_dir = PathBuf::from(".");
+    let mut atol = None;
+    let mut rtol = None;
+    let mut positional = Vec::new();
+
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        let mut value = |flag: &str| {
+            args.next()
+                .ok_or_else(|| Error::Usage(format!("`{}` needs a value", flag)))
+        };
+        match arg.as_str() {
+            "--config-dir" => config_dir = PathBuf::from(value(&arg)?),
+            "--atol" => atol = Some(parse_float(&arg, &value(&arg)?)?),
+            "--rtol" => rtol = Some(parse_float(&arg, &value(&arg)?)?),
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return Ok(0);
+            }
+            flag if flag.starts_with("--") => {
+                return Err(Error::Usage(format!("unknown flag `{}`", flag)))
+            }
+            _ => positional.push(arg),
+        }
+    }
+
+    let positional = positional.iter().map(String::as_str).collect::<Vec<_>>();
+    match positional.as_slice() {
+        ["list", subdir @ ..] if subdir.len() <= 1 => {
+            let egress = Egress::open_existing(&config_dir, subdir.first().unwrap_or(&""))?;
+            for name in egress.existing_artifacts()? {
+                println!("{}", name.display());
+            }
+            Ok(0)
+        }
+        ["show", file] => {
+            let artifact = Artifact::load(file)?;
+            let json = serde_json::to_string_pretty(&artifact).map_err(ErrorKind::from)?;
+            println!("{}", json);
+            Ok(0)
+        }
+        ["diff", reference, new] => {
+            let mut egress = Egress::open_existing(&config_dir, "")?;
+            if let Some(atol) = atol {
+                egress.atol = Some(atol);
+            }
+            if let Some(rtol) = rtol {
+                egress.rtol = Some(rtol);
+            }
+            let mismatches = egress.diff_files(reference, new)?;
+            for mismatch in &mismatches {
+                println!("MISMATCH: {}", mismatch);
+            }
+            Ok(if mismatches.is_empty() { 0 } else { 1 })
+        }
+        ["prune", subdir, names @ ..] if !names.is_empty() => {
+            let egress = Egress::open_existing(&config_dir, subdir)?;
+            for path in egress.prune_references(names)? {
+                println!("deleted {}", path.display());
+            }
+            Ok(0)
+        }
+        ["prune-sidecars", subdir @ ..] if subdir.len() <= 1 => {
+            let egress = Egress::open_existing(&config_dir, subdir.first().unwrap_or(&""))?;
+            for path in egress.prune_sidecars()? {
+                println!("deleted {}", path.display());
+            }
+            Ok(0)
+        }
+        [] => Err(Error::Usage("no command given".to_owned())),
+        [command, ..] => Err(Error::Usage(format!(
+            "unknown command or wrong arguments for `{}`",
+            command
+        ))),
+    }
+}
+
+fn parse_float(flag: &str, value: &str) -> Result<f64, Error> {
+    value
+        .parse()
+        .map_err(|_| Error::Usage(format!("`{}` needs a number, not `{}`", flag, value)))
+}
@@ -0,0 +1,70 @@
+//! Building `Egress` contexts from configuration given in code rather than in `Egress.toml`.
+
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use crate::{ArtifactStore, Egress, EgressConfig, ErrorKind, FsStore};
+
+/// A builder for an `Egress` context, which never touches an `Egress.toml`. Anything not set
+/// here takes the same default as in a freshly created `Egress.toml`.
+#[derive(Debug, Clone)]
+pub struct EgressBuilder {
+    config: EgressConfig,
+    artifact_subdir: PathBuf,
+    store: Arc<dyn ArtifactStore>,
+}
+
+impl EgressBuilder {
+    pub(crate) fn new() -> Self {
+        EgressBuilder {
+            config: EgressConfig::new(),
+            artifact_subdir: PathBuf::new(),
+            store: Arc::new(FsStore),
+        }
+    }
+
+    /// Set the directory artifacts are written to. Unlike the `artifact_dir` in an
+    /// `Egress.toml`, this is used as is, rather than relative to a config directory. Defaults
+    /// to `egress/artifacts/`.
+    pub fn artifact_dir<P: AsRef<Path>>(mut self, artifact_dir: P) -> Self {
+        self.config.artifact_dir = artifact_dir.as_ref().to_owned();
+        self
+    }
+
+    /// Set the subdirectory of `artifact_dir` this context places its artifacts in.
+    pub fn artifact_subdir<P: AsRef<Path>>(mut self, artifact_subdir: P) -> Self {
+        self.artifact_subdir = artifact_subdir.as_ref().to_owned();
+        self
+    }
+
+    /// Set the absolute tolerance (absolute(a - b) <= atol)
+    pub fn atol(mut self, atol: Option<f64>) -> Self {
+        self.config.atol = atol;
+        self
+    }
+
+    /// Set the relative tolerance (absolute(a - b) <= rtol * absolute(b))
+    pub fn rtol(mut self, rtol: Option<f64>) -> Self {
+        self.config.rtol = rtol;
+        self
+    }
+
+    /// Set where references are read from and written to, e.g. a `MemoryStore` for tests
+    /// which shouldn't touch the disk. Defaults to the filesystem.
+    pub fn store<S: ArtifactStore + 'static>(mut self, store: S) -> Self {
+        self.store = Arc::new(store);
+        self
+    }
+
+    /// Create the `Egress` context. A negative or non-finite `atol` or `rtol` is an
+    /// `ErrorKind::InvalidConfig`, just like in an `Egress.toml`.
+    pub fn build(self) -> Result<Egress, ErrorKind> {
+        self.config.validate()?;
+        let artifact_subdir = self.config.artifact_dir.join(&self.artifact_subdir);
+        let mut egress = Egress::from_config(self.config, artifact_subdir);
+        egress.store = self.store;
+        Ok(egress)
+    }
+}
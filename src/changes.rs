@@ -0,0 +1,144 @@
+//! Summaries of how references changed between two versions of an artifact directory.
+
+use ::std::{
+    collections::{BTreeSet, HashSet},
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    artifact::{Mismatch, Tolerance},
+    sidecar, Artifact, ErrorKind, Format, FsStore,
+};
+
+/// Diff every reference file found under two artifact directories - for example, the
+/// committed version of `egress/artifacts` and the one in your working tree - treating the
+/// files under `old_dir` as the reference and the files under `new_dir` as newly produced.
+///
+/// Returns the mismatches for each file which changed, keyed by its path relative to the
+/// directories and sorted by that path. Files which didn't change are left out. A file which
+/// only exists under `new_dir` reports all of its entries as not being in the reference, and
+/// a file which only exists under `old_dir` reports all of its entries as not produced.
+/// Sidecar files aren't listed on their own: a changed sidecar shows up as a change to the
+/// entry it stands for.
+pub fn describe_changes<P, Q>(
+    old_dir: P,
+    new_dir: Q,
+) -> Result<Vec<(PathBuf, Vec<Mismatch>)>, ErrorKind>
+where
+    P: AsRef<Path>,
+    Q: AsRef<Path>,
+{
+    let (old_dir, new_dir) = (old_dir.as_ref(), new_dir.as_ref());
+
+    let mut paths = BTreeSet::new();
+    collect_references(old_dir, Path::new(""), &mut paths)?;
+    collect_references(new_dir, Path::new(""), &mut paths)?;
+
+    let mut changes = Vec::new();
+    for path in reference_paths(&[old_dir, new_dir], paths)? {
+        let old = read_reference(&old_dir.join(&path))?.unwrap_or_default();
+        let new = read_reference(&new_dir.join(&path))?.unwrap_or_default();
+
+        let name = crate::artifact_key(&path.with_extension(""));
+        let mismatches = new.report_mismatches(name, &old, Tolerance::default());
+        if !mismatches.is_empty() {
+            changes.push((path, mismatches));
+        }
+    }
+
+    Ok(changes)
+}
+
+fn collect_references(
+    root: &Path,
+    subdir: &Path,
+    paths: &mut BTreeSet<PathBuf>,
+) -> Result<(), ErrorKind> {
+    let dir = root.join(subdir);
+    if !dir.is_dir() {
+        return Ok(());
+    }
+
+    for dir_entry in fs::read_dir(dir)? {
+        let dir_entry = dir_entry?;
+        let path = subdir.join(dir_entry.file_name());
+
+        if dir_entry.file_type()?.is_dir() {
+            collect_references(root, &path, paths)?;
+        } else if path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .and_then(Format::from_extension)
+            .is_some()
+        {
+            paths.insert(path);
+        }
+    }
+
+    Ok(())
+}
+
+/// The `paths` which are references rather than sidecars. Like `Egress`'s own listing, a file
+/// named like a sidecar is only taken to be a reference if it can be read as one and no
+/// reference in either of `dirs` refers to it.
+fn reference_paths(dirs: &[&Path], paths: BTreeSet<PathBuf>) -> Result<Vec<PathBuf>, ErrorKind> {
+    let (ambiguous, mut references): (Vec<_>, Vec<_>) = paths.into_iter().partition(|path| {
+        path.file_name()
+            .and_then(|name| name.to_str())
+            .is_some_and(sidecar::is_sidecar_name)
+    });
+    if ambiguous.is_empty() {
+        return Ok(references);
+    }
+
+    let mut readable = HashSet::new();
+    let mut sidecars = HashSet::new();
+    for path in references.iter().chain(&ambiguous) {
+        for dir in dirs {
+            if let Ok(Some(reference)) = deserialize_reference(&dir.join(path)) {
+                let parent = path.parent().unwrap_or_else(|| Path::new(""));
+                sidecars.extend(
+                    sidecar::names(&reference)
+                        .into_iter()
+                        .map(|name| parent.join(name)),
+                );
+                readable.insert(path.clone());
+            }
+        }
+    }
+
+    references.extend(
+        ambiguous
+            .into_iter()
+            .filter(|path| readable.contains(path) && !sidecars.contains(path)),
+    );
+    references.sort();
+    Ok(references)
+}
+
+/// Read the reference at `path`, with its sidecars in place of the entries they stand for.
+fn read_reference(path: &Path) -> Result<Option<Artifact>, ErrorKind> {
+    let mut reference = match deserialize_reference(path)? {
+        Some(reference) => reference,
+        None => return Ok(None),
+    };
+
+    let empty = Artifact::new();
+    let dir = path.parent().unwrap_or_else(|| Path::new(""));
+    sidecar::resolve(&FsStore, &mut reference, &empty, &empty, dir)?;
+    Ok(Some(reference))
+}
+
+fn deserialize_reference(path: &Path) -> Result<Option<Artifact>, ErrorKind> {
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    let format = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .and_then(Format::from_extension)
+        .expect("only files with a known extension are collected");
+    Ok(Some(format.deserialize(&fs::read(path)?)?))
+}
@@ -0,0 +1,522 @@
+//! Human-readable diffs for mismatched entries.
+//!
+//! Values from the reference are shown in green and newly produced values in red. Colors are
+//! only used with the `color` feature enabled, when stderr is a terminal and the `NO_COLOR`
+//! environment variable isn't set; otherwise the same text is produced without any escape
+//! codes, with inline changes marked as `[-reference-]{+new+}`.
+
+use prettydiff::basic::{diff, DiffOp};
+
+use crate::Entry;
+
+#[cfg(feature = "color")]
+mod paint {
+    use owo_colors::OwoColorize;
+
+    pub fn reference(s: &str) -> String {
+        s.green().to_string()
+    }
+
+    pub fn reference_changed(s: &str) -> String {
+        s.black().on_green().to_string()
+    }
+
+    pub fn new(s: &str) -> String {
+        s.red().to_string()
+    }
+
+    pub fn new_changed(s: &str) -> String {
+        s.black().on_red().to_string()
+    }
+
+    pub fn key_path(s: &str) -> String {
+        s.bold().to_string()
+    }
+}
+
+#[cfg(not(feature = "color"))]
+mod paint {
+    pub fn reference(s: &str) -> String {
+        s.to_owned()
+    }
+
+    pub fn reference_changed(s: &str) -> String {
+        s.to_owned()
+    }
+
+    pub fn new(s: &str) -> String {
+        s.to_owned()
+    }
+
+    pub fn new_changed(s: &str) -> String {
+        s.to_owned()
+    }
+
+    pub fn key_path(s: &str) -> String {
+        s.to_owned()
+    }
+}
+
+/// Whether mismatch output should be colored.
+#[cfg(feature = "color")]
+pub(crate) fn colorize() -> bool {
+    use std::io::IsTerminal;
+
+    let no_color = std::env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty());
+    !no_color && std::io::stderr().is_terminal()
+}
+
+/// Whether mismatch output should be colored.
+#[cfg(not(feature = "color"))]
+pub(crate) fn colorize() -> bool {
+    false
+}
+
+/// Format a mismatch's key path for display, in bold if `color` is set.
+pub(crate) fn highlight_key_path(path: &str, color: bool) -> String {
+    if color {
+        paint::key_path(path)
+    } else {
+        path.to_owned()
+    }
+}
+
+/// How many characters of a missing or new value to show in a mismatch.
+const PREVIEW_CHARS: usize = 80;
+
+/// Render a short, single-line preview of an entry which is only in the reference, if
+/// `in_reference` is set, or only in the new artifact otherwise. Previews longer than
+/// `PREVIEW_CHARS` are cut off with a `...`.
+pub(crate) fn preview(entry: &Entry, in_reference: bool, color: bool) -> String {
+    let full = match entry {
+        Entry::Str(s) => serde_json::to_string(s),
+        Entry::Json(value) => serde_json::to_string(value),
+        Entry::Bytes(bytes) => Ok(format!("<{} bytes>", bytes.len())),
+        Entry::Artifact(artifact) => serde_json::to_string(artifact),
+        Entry::Null => Ok("null".to_owned()),
+        Entry::Sidecar { path, .. } => Ok(format!("<sidecar `{}`>", path)),
+    }
+    .unwrap_or_default();
+
+    let preview = match full.char_indices().nth(PREVIEW_CHARS) {
+        Some((end, _)) => format!("{}...", &full[..end]),
+        None => full,
+    };
+
+    match (color, in_reference) {
+        (false, _) => preview,
+        (true, true) => paint::reference(&preview),
+        (true, false) => paint::new(&preview),
+    }
+}
+
+fn chars(s: &str) -> Vec<&str> {
+    s.split("").filter(|c| !c.is_empty()).collect()
+}
+
+/// Render a character-by-character diff of two single-line strings, such as serialized JSON.
+pub(crate) fn render_inline(old: &str, new: &str, color: bool) -> String {
+    let (old_chars, new_chars) = (chars(old), chars(new));
+
+    let mut out = String::new();
+    for op in diff(&old_chars, &new_chars) {
+        let (removed, inserted) = match op {
+            DiffOp::Equal(s) => {
+                out += &s.concat();
+                continue;
+            }
+            DiffOp::Remove(s) => (s.concat(), String::new()),
+            DiffOp::Insert(s) => (String::new(), s.concat()),
+            DiffOp::Replace(a, b) => (a.concat(), b.concat()),
+        };
+
+        if color {
+            out += &paint::reference(&removed);
+            out += &paint::new(&inserted);
+        } else {
+            if !removed.is_empty() {
+                out += &format!("[-{}-]", removed);
+            }
+            if !inserted.is_empty() {
+                out += &format!("{{+{}+}}", inserted);
+            }
+        }
+    }
+
+    out
+}
+
+/// How many unchanged lines to show around each change in a line diff.
+const CONTEXT_LINES: usize = 3;
+
+#[derive(Clone, Copy, PartialEq)]
+enum Tag {
+    Equal,
+    Removed,
+    Inserted,
+}
+
+/// Render a line-by-line diff of two strings, marking lines only in the reference with `-`
+/// and lines only in the new value with `+`. Long runs of unchanged lines are left out, so a
+/// one-line change in a long debug dump shows up as just that line and a few around it.
+///
+/// When colors are enabled, changed lines are also highlighted character by character, so a
+/// one-character change in a long line is easy to spot.
+pub(crate) fn render_lines(old: &str, new: &str, color: bool) -> String {
+    let old_lines = old.lines().collect::<Vec<_>>();
+    let new_lines = new.lines().collect::<Vec<_>>();
+
+    let removed = |line: &str| {
+        let line = format!("-{}", line);
+        if color {
+            paint::reference(&line)
+        } else {
+            line
+        }
+    };
+    let inserted = |line: &str| {
+        let line = format!("+{}", line);
+        if color {
+            paint::new(&line)
+        } else {
+            line
+        }
+    };
+
+    let mut lines = Vec::new();
+    for op in diff(&old_lines, &new_lines) {
+        match op {
+            DiffOp::Equal(equal) => {
+                lines.extend(equal.iter().map(|line| (Tag::Equal, format!(" {}", line))))
+            }
+            DiffOp::Remove(old) => {
+                lines.extend(old.iter().map(|line| (Tag::Removed, removed(line))))
+            }
+            DiffOp::Insert(new) => {
+                lines.extend(new.iter().map(|line| (Tag::Inserted, inserted(line))))
+            }
+            DiffOp::Replace(old, new) => {
+                let rendered = if color {
+                    highlight_replace(old, new)
+                } else {
+                    old.iter()
+                        .map(|line| removed(line))
+                        .chain(new.iter().map(|line| inserted(line)))
+                        .collect()
+                };
+                let tags = std::iter::repeat_n(Tag::Removed, old.len())
+                    .chain(std::iter::repeat_n(Tag::Inserted, new.len()));
+                lines.extend(tags.zip(rendered));
+            }
+        }
+    }
+
+    hunks(lines)
+}
+
+/// Cut a line diff down to the changed lines and `CONTEXT_LINES` lines of context around them.
+/// If any lines are left out, every group of lines which is kept gets a unified diff style
+/// `@@ -l,s +l,s @@` header giving its position in the reference and in the new value.
+fn hunks(lines: Vec<(Tag, String)>) -> String {
+    let changed = lines
+        .iter()
+        .enumerate()
+        .filter(|(_, (tag, _))| *tag != Tag::Equal)
+        .map(|(i, _)| i)
+        .collect::<Vec<_>>();
+    let keep = (0..lines.len())
+        .map(|i| {
+            changed
+                .iter()
+                .any(|&c| c.saturating_sub(CONTEXT_LINES) <= i && i <= c + CONTEXT_LINES)
+        })
+        .collect::<Vec<_>>();
+
+    if !keep.contains(&false) {
+        return lines
+            .into_iter()
+            .map(|(_, line)| line)
+            .collect::<Vec<_>>()
+            .join("\n");
+    }
+
+    let mut out = Vec::new();
+    let (mut old_line, mut new_line) = (1, 1);
+    let mut i = 0;
+    while i < lines.len() {
+        if !keep[i] {
+            old_line += 1;
+            new_line += 1;
+            i += 1;
+            continue;
+        }
+
+        let end = (i..lines.len()).find(|&j| !keep[j]).unwrap_or(lines.len());
+        let hunk = &lines[i..end];
+        let old_len = hunk.iter().filter(|(tag, _)| *tag != Tag::Inserted).count();
+        let new_len = hunk.iter().filter(|(tag, _)| *tag != Tag::Removed).count();
+        out.push(format!(
+            "@@ -{},{} +{},{} @@",
+            old_line, old_len, new_line, new_len
+        ));
+        out.extend(hunk.iter().map(|(_, line)| line.clone()));
+
+        old_line += old_len;
+        new_line += new_len;
+        i = end;
+    }
+
+    out.join("\n")
+}
+
+fn highlight_replace(removed: &[&str], inserted: &[&str]) -> Vec<String> {
+    let mut removed_out = Vec::new();
+    let mut inserted_out = Vec::new();
+
+    // Pair changed lines up positionally; whatever is left over on either side is a plain
+    // removal or insertion.
+    for (old, new) in removed.iter().zip(inserted) {
+        let mut old_line = paint::reference("-");
+        let mut new_line = paint::new("+");
+        for op in diff(&chars(old), &chars(new)) {
+            match op {
+                DiffOp::Equal(s) => {
+                    old_line += &paint::reference(&s.concat());
+                    new_line += &paint::new(&s.concat());
+                }
+                DiffOp::Remove(s) => old_line += &paint::reference_changed(&s.concat()),
+                DiffOp::Insert(s) => new_line += &paint::new_changed(&s.concat()),
+                DiffOp::Replace(a, b) => {
+                    old_line += &paint::reference_changed(&a.concat());
+                    new_line += &paint::new_changed(&b.concat());
+                }
+            }
+        }
+
+        removed_out.push(old_line);
+        inserted_out.push(new_line);
+    }
+
+    let paired = removed.len().min(inserted.len());
+    removed_out.extend(
+        removed[paired..]
+            .iter()
+            .map(|line| paint::reference(&format!("-{}", line))),
+    );
+    inserted_out.extend(
+        inserted[paired..]
+            .iter()
+            .map(|line| paint::new(&format!("+{}", line))),
+    );
+
+    removed_out.extend(inserted_out);
+    removed_out
+}
+
+const HEXDUMP_ROW: usize = 16;
+const HEXDUMP_CONTEXT_ROWS: usize = 1;
+
+/// Render a hexdump of the rows around the first byte at which two buffers differ, marking the
+/// reference's rows with `-` and the new value's with `+`.
+pub(crate) fn render_bytes(old: &[u8], new: &[u8], color: bool) -> String {
+    let first = old
+        .iter()
+        .zip(new)
+        .position(|(a, b)| a != b)
+        .unwrap_or_else(|| old.len().min(new.len()));
+    let first_row = first / HEXDUMP_ROW;
+    let start = first_row.saturating_sub(HEXDUMP_CONTEXT_ROWS) * HEXDUMP_ROW;
+    let end = ((first_row + HEXDUMP_CONTEXT_ROWS + 1) * HEXDUMP_ROW).min(old.len().max(new.len()));
+
+    let mut out = vec![format!(
+        "first difference at offset {:#x} (the reference is {} bytes, the new value is {})",
+        first,
+        old.len(),
+        new.len()
+    )];
+    for row_start in (start..end).step_by(HEXDUMP_ROW) {
+        let row = |bytes: &[u8]| {
+            let row_end = (row_start + HEXDUMP_ROW).min(bytes.len());
+            bytes[row_start.min(row_end)..row_end].to_vec()
+        };
+        let (old_row, new_row) = (row(old), row(new));
+        let changed = (0..HEXDUMP_ROW)
+            .map(|i| old_row.get(i) != new_row.get(i))
+            .collect::<Vec<_>>();
+
+        if !changed.contains(&true) {
+            out.push(format!(
+                " {}",
+                hexdump_row(row_start, &old_row, &changed, None)
+            ));
+        } else if color {
+            let removed = hexdump_row(
+                row_start,
+                &old_row,
+                &changed,
+                Some(paint::reference_changed),
+            );
+            let inserted = hexdump_row(row_start, &new_row, &changed, Some(paint::new_changed));
+            out.push(paint::reference("-") + &removed);
+            out.push(paint::new("+") + &inserted);
+        } else {
+            out.push(format!(
+                "-{}",
+                hexdump_row(row_start, &old_row, &changed, None)
+            ));
+            out.push(format!(
+                "+{}",
+                hexdump_row(row_start, &new_row, &changed, None)
+            ));
+
+            // Without colors, point out the changed bytes underneath.
+            let carets = changed
+                .iter()
+                .map(|&changed| if changed { "^^" } else { "  " })
+                .collect::<Vec<_>>()
+                .join(" ");
+            out.push(format!(" {:8}  {}", "", carets.trim_end()));
+        }
+    }
+
+    out.join("\n")
+}
+
+/// Format one row of a hexdump as an offset, the bytes in hex and then the printable ones as
+/// ASCII. Changed bytes are painted with `highlight`, if given.
+fn hexdump_row(
+    offset: usize,
+    bytes: &[u8],
+    changed: &[bool],
+    highlight: Option<fn(&str) -> String>,
+) -> String {
+    let paint = |i: usize, s: String| match highlight {
+        Some(highlight) if changed[i] => highlight(&s),
+        _ => s,
+    };
+
+    let hex = (0..HEXDUMP_ROW)
+        .map(|i| match bytes.get(i) {
+            Some(byte) => paint(i, format!("{:02x}", byte)),
+            None => "  ".to_owned(),
+        })
+        .collect::<Vec<_>>()
+        .join(" ");
+    let ascii = bytes
+        .iter()
+        .enumerate()
+        .map(|(i, &byte)| {
+            let c = if byte.is_ascii_graphic() || byte == b' ' {
+                byte as char
+            } else {
+                '.'
+            };
+            paint(i, c.to_string())
+        })
+        .collect::<String>();
+
+    format!("{:08x}  {}  |{}|", offset, hex, ascii)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn line_diff() {
+        let old = "one\ntwo\nthree";
+        let new = "one\ntoo\nthree\nfour";
+
+        assert_eq!(
+            render_lines(old, new, false),
+            " one\n-two\n+too\n three\n+four"
+        );
+    }
+
+    #[test]
+    fn line_diff_context() {
+        let old = (1..=20).map(|i| format!("line {}", i)).collect::<Vec<_>>();
+        let mut new = old.clone();
+        new[9] = "changed".to_owned();
+        new.remove(17);
+
+        assert_eq!(
+            render_lines(&old.join("\n"), &new.join("\n"), false),
+            [
+                "@@ -7,7 +7,7 @@",
+                " line 7",
+                " line 8",
+                " line 9",
+                "-line 10",
+                "+changed",
+                " line 11",
+                " line 12",
+                " line 13",
+                "@@ -15,6 +15,5 @@",
+                " line 15",
+                " line 16",
+                " line 17",
+                "-line 18",
+                " line 19",
+                " line 20",
+            ]
+            .join("\n")
+        );
+    }
+
+    #[test]
+    fn inline_diff() {
+        assert_eq!(
+            render_inline(r#"{"Json":2}"#, r#"{"Json":3}"#, false),
+            r#"{"Json":[-2-]{+3+}}"#
+        );
+    }
+
+    #[test]
+    fn hexdump_diff() {
+        let old = (0..64).collect::<Vec<u8>>();
+        let mut new = old.clone();
+        new[0x23] = b'A';
+
+        assert_eq!(
+            render_bytes(&old, &new, false),
+            [
+                "first difference at offset 0x23 (the reference is 64 bytes, the new value is 64)",
+                " 00000010  10 11 12 13 14 15 16 17 18 19 1a 1b 1c 1d 1e 1f  |................|",
+                "-00000020  20 21 22 23 24 25 26 27 28 29 2a 2b 2c 2d 2e 2f  | !\"#$%&'()*+,-./|",
+                "+00000020  20 21 22 41 24 25 26 27 28 29 2a 2b 2c 2d 2e 2f  | !\"A$%&'()*+,-./|",
+                "                    ^^",
+                " 00000030  30 31 32 33 34 35 36 37 38 39 3a 3b 3c 3d 3e 3f  |0123456789:;<=>?|",
+            ]
+            .join("\n")
+        );
+    }
+
+    #[test]
+    fn hexdump_diff_truncated() {
+        let old = b"hello world".to_vec();
+        let new = b"hello".to_vec();
+
+        assert_eq!(
+            render_bytes(&old, &new, false),
+            [
+                "first difference at offset 0x5 (the reference is 11 bytes, the new value is 5)",
+                "-00000000  68 65 6c 6c 6f 20 77 6f 72 6c 64                 |hello world|",
+                "+00000000  68 65 6c 6c 6f                                   |hello|",
+                "                          ^^ ^^ ^^ ^^ ^^ ^^",
+            ]
+            .join("\n")
+        );
+    }
+
+    #[cfg(feature = "color")]
+    #[test]
+    fn intra_line_highlight() {
+        let rendered = render_lines("the quick fox", "the quack fox", true);
+
+        assert!(rendered.contains(&paint::reference_changed("i")));
+        assert!(rendered.contains(&paint::new_changed("a")));
+        assert!(rendered.contains(&paint::reference("the qu")));
+        assert!(rendered.contains(&paint::new("ck fox")));
+    }
+}
@@ -1,23 +1,127 @@
-use failure::Fail;
+use thiserror::Error;
 
 /// Possible failure modes for creating and closing `Egress` contexts.
-#[derive(Debug, Fail)]
+#[derive(Debug, Error)]
 pub enum ErrorKind {
     /// Wrapper for `std::io::Error` from file operations.
-    #[fail(display = "I/O error: {}", _0)]
-    IoError(#[cause] std::io::Error),
+    #[error("I/O error: {}", _0)]
+    IoError(#[source] std::io::Error),
+
+    /// A reference, a sidecar or a directory to hold them couldn't be created or written.
+    #[error("I/O error on artifact file `{}`: {}", path.display(), source)]
+    ArtifactIo {
+        /// The file or directory which couldn't be created or written.
+        path: std::path::PathBuf,
+        /// The underlying error.
+        #[source]
+        source: std::io::Error,
+    },
 
     /// Wrapper for `toml` serialization errors for config data.
-    #[fail(display = "error while serializing config data to TOML: {}", _0)]
-    TomlSerError(#[cause] toml::ser::Error),
+    #[error("error while serializing config data to TOML: {}", _0)]
+    TomlSerError(#[source] toml::ser::Error),
 
     /// Wrapper for `toml` deserialization errors for config data.
-    #[fail(display = "error while deserializing config data from TOML: {}", _0)]
-    TomlDeError(#[cause] toml::de::Error),
+    #[error("error while deserializing config data from TOML: {}", _0)]
+    TomlDeError(#[source] toml::de::Error),
 
     /// Wrapper for errors caused by serializing/deserializing artifacts from JSON.
-    #[fail(display = "error while (de)serializing artifact from JSON: {}", _0)]
-    JsonError(#[cause] serde_json::error::Error),
+    #[error("error while (de)serializing artifact from JSON: {}", _0)]
+    JsonError(#[source] serde_json::error::Error),
+
+    /// Wrapper for errors caused by serializing/deserializing artifacts from YAML.
+    #[cfg(feature = "yaml")]
+    #[error("error while (de)serializing artifact from YAML: {}", _0)]
+    YamlError(#[source] serde_yaml::Error),
+
+    /// Wrapper for errors caused by serializing artifacts to CBOR.
+    #[cfg(feature = "cbor")]
+    #[error("error while serializing artifact to CBOR: {}", _0)]
+    CborSerError(#[source] ciborium::ser::Error<std::io::Error>),
+
+    /// Wrapper for errors caused by deserializing artifacts from CBOR.
+    #[cfg(feature = "cbor")]
+    #[error("error while deserializing artifact from CBOR: {}", _0)]
+    CborDeError(#[source] ciborium::de::Error<std::io::Error>),
+
+    /// Wrapper for errors caused by serializing artifacts to TOML.
+    #[error("error while serializing artifact to TOML: {}", _0)]
+    TomlArtifactSerError(#[source] toml::ser::Error),
+
+    /// Wrapper for errors caused by deserializing artifacts from TOML.
+    #[error("error while deserializing artifact from TOML: {}", _0)]
+    TomlArtifactDeError(#[source] toml::de::Error),
+
+    /// An artifact stored as TOML held a null, which TOML can't represent, at this key path.
+    #[error(
+        "TOML can't represent the null at `{}`; use another format for this artifact",
+        _0
+    )]
+    TomlNull(String),
+
+    /// Wrapper for errors caused by serializing artifacts to RON.
+    #[cfg(feature = "ron")]
+    #[error("error while serializing artifact to RON: {}", _0)]
+    RonSerError(#[source] ron::Error),
+
+    /// Wrapper for errors caused by deserializing artifacts from RON.
+    #[cfg(feature = "ron")]
+    #[error("error while deserializing artifact from RON: {}", _0)]
+    RonDeError(#[source] ron::de::SpannedError),
+
+    /// An entry was inserted into an artifact under a name which is already taken.
+    #[error("duplicate entries under the same name (`{}`) are not allowed", _0)]
+    DuplicateEntry(String),
+
+    /// An artifact name wasn't a relative path, or tried to escape the artifact directory with
+    /// `..`.
+    #[error(
+        "artifact name `{}` must be a relative path without `..`",
+        _0.display()
+    )]
+    InvalidArtifactName(std::path::PathBuf),
+
+    /// An artifact was created under a name which is already taken in the same context.
+    #[error("only one artifact allowed with the name `{}`", _0.display())]
+    DuplicateArtifact(std::path::PathBuf),
+
+    /// A context was opened with an artifact subdirectory which another live context in the
+    /// same process is already using. Subdirectories nested inside one another are fine.
+    #[error(
+        "artifact subdirectory `{}` is already in use by another live context",
+        _0.display()
+    )]
+    ArtifactSubdirInUse(std::path::PathBuf),
+
+    /// A numeric environment variable, like `EGRESS_ATOL`, was set to something which isn't a
+    /// number. Holds the variable's name and value.
+    #[error("environment variable `{}` must be a number, not `{}`", _0, _1)]
+    InvalidEnvVar(String, String),
+
+    /// A config value was out of range, like a negative or non-finite tolerance. Holds the
+    /// name of the offending field, or of the environment variable overriding it, and a
+    /// description of what it must be instead.
+    #[error("invalid config: `{}` {}", _0, _1)]
+    InvalidConfig(String, String),
+
+    /// A context was opened with `Egress::open_existing`, but there was no config file at this
+    /// path.
+    #[error("no config file at `{}`", _0.display())]
+    MissingConfig(std::path::PathBuf),
+
+    /// An artifact was about to be written as a reference while holding a stream which
+    /// `Artifact::insert_stream` cut short at its first mismatch, e.g. because capture mode was
+    /// switched on only after the artifact was made. Holds the artifact's name.
+    #[error(
+        "artifact `{}` holds a stream cut short at its first mismatch, which can't be written \
+         as a reference; switch on capture or update mode before making the artifact",
+        _0
+    )]
+    TruncatedStream(String),
+
+    /// A file's extension didn't match the extension of any artifact format.
+    #[error("no artifact format uses the extension of `{}`", _0.display())]
+    UnknownFormat(std::path::PathBuf),
 }
 
 impl From<std::io::Error> for ErrorKind {
@@ -43,3 +147,38 @@ impl From<serde_json::error::Error> for ErrorKind {
         ErrorKind::JsonError(err)
     }
 }
+
+#[cfg(feature = "yaml")]
+impl From<serde_yaml::Error> for ErrorKind {
+    fn from(err: serde_yaml::Error) -> Self {
+        ErrorKind::YamlError(err)
+    }
+}
+
+#[cfg(feature = "cbor")]
+impl From<ciborium::ser::Error<std::io::Error>> for ErrorKind {
+    fn from(err: ciborium::ser::Error<std::io::Error>) -> Self {
+        ErrorKind::CborSerError(err)
+    }
+}
+
+#[cfg(feature = "cbor")]
+impl From<ciborium::de::Error<std::io::Error>> for ErrorKind {
+    fn from(err: ciborium::de::Error<std::io::Error>) -> Self {
+        ErrorKind::CborDeError(err)
+    }
+}
+
+#[cfg(feature = "ron")]
+impl From<ron::Error> for ErrorKind {
+    fn from(err: ron::Error) -> Self {
+        ErrorKind::RonSerError(err)
+    }
+}
+
+#[cfg(feature = "ron")]
+impl From<ron::de::SpannedError> for ErrorKind {
+    fn from(err: ron::de::SpannedError) -> Self {
+        ErrorKind::RonDeError(err)
+    }
+}
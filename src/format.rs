@@ -0,0 +1,146 @@
+//! The file formats artifacts can be stored in.
+
+use ::{
+    serde::{Deserialize, Serialize},
+    serde_json::Value,
+};
+
+use crate::{
+    artifact::{index_path, key_path},
+    Artifact, Entry, ErrorKind,
+};
+
+/// The file format reference artifacts are stored in. The format only affects how artifacts
+/// look on disk; they're compared in exactly the same way regardless.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Format {
+    /// JSON, stored in `.json` files. Pretty-printed unless `json_pretty` is turned off.
+    #[default]
+    Json,
+    /// YAML, stored in `.yaml` files. Requires the `yaml` feature.
+    #[cfg(feature = "yaml")]
+    Yaml,
+    /// CBOR, a compact binary format, stored in `.cbor` files. Byte entries are stored as raw
+    /// byte strings rather than base64. Requires the `cbor` feature.
+    #[cfg(feature = "cbor")]
+    Cbor,
+    /// TOML, stored in `.toml` files, for references reviewers edit by hand. TOML has no null,
+    /// so writing a JSON entry containing one fails with `ErrorKind::TomlNull`.
+    Toml,
+    /// RON, stored in `.ron` files. Requires the `ron` feature.
+    #[cfg(feature = "ron")]
+    Ron,
+}
+
+impl Format {
+    const ALL: &'static [Format] = &[
+        Format::Json,
+        #[cfg(feature = "yaml")]
+        Format::Yaml,
+        #[cfg(feature = "cbor")]
+        Format::Cbor,
+        Format::Toml,
+        #[cfg(feature = "ron")]
+        Format::Ron,
+    ];
+
+    /// The file extension used for artifacts in this format.
+    pub fn extension(self) -> &'static str {
+        match self {
+            Format::Json => "json",
+            #[cfg(feature = "yaml")]
+            Format::Yaml => "yaml",
+            #[cfg(feature = "cbor")]
+            Format::Cbor => "cbor",
+            Format::Toml => "toml",
+            #[cfg(feature = "ron")]
+            Format::Ron => "ron",
+        }
+    }
+
+    /// Find the format which uses a given file extension, if any.
+    pub(crate) fn from_extension(extension: &str) -> Option<Format> {
+        Format::ALL
+            .iter()
+            .copied()
+            .find(|format| format.extension() == extension)
+    }
+
+    pub(crate) fn serialize(self, artifact: &Artifact) -> Result<Vec<u8>, ErrorKind> {
+        match self {
+            Format::Json => Ok(serde_json::to_vec_pretty(artifact)?),
+            #[cfg(feature = "yaml")]
+            Format::Yaml => Ok(serde_yaml::to_string(artifact)?.into_bytes()),
+            #[cfg(feature = "cbor")]
+            Format::Cbor => {
+                let mut bytes = Vec::new();
+                ciborium::ser::into_writer(artifact, &mut bytes)?;
+                Ok(bytes)
+            }
+            Format::Toml => {
+                if let Some(path) = find_null(artifact, "") {
+                    return Err(ErrorKind::TomlNull(path));
+                }
+                let value = toml::Value::try_from(serde_json::to_value(artifact)?)
+                    .map_err(ErrorKind::TomlArtifactSerError)?;
+                let toml =
+                    toml::to_string_pretty(&value).map_err(ErrorKind::TomlArtifactSerError)?;
+                Ok(toml.into_bytes())
+            }
+            #[cfg(feature = "ron")]
+            Format::Ron => {
+                let value = serde_json::to_value(artifact)?;
+                let ron = ron::ser::to_string_pretty(&value, ron::ser::PrettyConfig::default())?;
+                Ok(ron.into_bytes())
+            }
+        }
+    }
+
+    pub(crate) fn deserialize(self, bytes: &[u8]) -> Result<Artifact, ErrorKind> {
+        match self {
+            Format::Json => Ok(serde_json::from_slice(bytes)?),
+            #[cfg(feature = "yaml")]
+            Format::Yaml => Ok(serde_yaml::from_slice(bytes)?),
+            #[cfg(feature = "cbor")]
+            Format::Cbor => Ok(ciborium::de::from_reader(bytes)?),
+            Format::Toml => {
+                let value: Value =
+                    toml::from_slice(bytes).map_err(ErrorKind::TomlArtifactDeError)?;
+                Ok(serde_json::from_value(value)?)
+            }
+            #[cfg(feature = "ron")]
+            Format::Ron => {
+                let value: Value = ron::de::from_bytes(bytes)?;
+                Ok(serde_json::from_value(value)?)
+            }
+        }
+    }
+}
+
+/// The key path of the first null in the JSON entries of `artifact` or its nested artifacts,
+/// if there is one.
+fn find_null(artifact: &Artifact, prefix: &str) -> Option<String> {
+    artifact.entries().iter().find_map(|(k, entry)| {
+        let path = key_path(prefix, k);
+        match entry {
+            Entry::Json(value) => find_null_in_value(value, path),
+            Entry::Artifact(nested) => find_null(nested, &path),
+            _ => None,
+        }
+    })
+}
+
+fn find_null_in_value(value: &Value, path: String) -> Option<String> {
+    match value {
+        Value::Null => Some(path),
+        Value::Array(array) => array
+            .iter()
+            .enumerate()
+            .find_map(|(i, elem)| find_null_in_value(elem, index_path(&path, i))),
+        Value::Object(map) => map
+            .iter()
+            .find_map(|(k, v)| find_null_in_value(v, key_path(&path, k))),
+        Value::Bool(_) | Value::Number(_) | Value::String(_) => None,
+    }
+}
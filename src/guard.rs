@@ -0,0 +1,61 @@
+//! A guard which closes an `Egress` context when it's dropped, so that forgetting to assert on
+//! a context can't let a regression slip through.
+
+use std::ops::{Deref, DerefMut};
+
+use crate::Egress;
+
+/// An `Egress` context which is closed when the guard is dropped, panicking if any artifact
+/// regressed or the context couldn't be closed. Returned by `Egress::open_guarded`, and usable
+/// wherever an `Egress` is through `Deref`.
+///
+/// Nothing is checked if the guard is dropped while the thread is already panicking, e.g.
+/// because an assertion earlier in the test failed, so that the original panic isn't masked.
+#[must_use = "the context is closed and checked as soon as the guard is dropped"]
+#[derive(Debug)]
+pub struct EgressGuard {
+    egress: Option<Egress>,
+}
+
+impl EgressGuard {
+    pub(crate) fn new(egress: Egress) -> Self {
+        EgressGuard {
+            egress: Some(egress),
+        }
+    }
+}
+
+impl Deref for EgressGuard {
+    type Target = Egress;
+
+    fn deref(&self) -> &Egress {
+        self.egress
+            .as_ref()
+            .expect("the context is only taken on drop")
+    }
+}
+
+impl DerefMut for EgressGuard {
+    fn deref_mut(&mut self) -> &mut Egress {
+        self.egress
+            .as_mut()
+            .expect("the context is only taken on drop")
+    }
+}
+
+impl Drop for EgressGuard {
+    fn drop(&mut self) {
+        let egress = match self.egress.take() {
+            Some(egress) => egress,
+            None => return,
+        };
+        if std::thread::panicking() {
+            return;
+        }
+
+        match egress.close() {
+            Ok(report) => report.assert_unregressed(),
+            Err(err) => panic!("failed to close Egress context: {}", err),
+        }
+    }
+}
@@ -0,0 +1,219 @@
+//! JSON canonicalization as specified by [RFC 8785] (the JSON Canonicalization Scheme, or JCS).
+//!
+//! Canonical JSON is byte-stable: object keys are sorted by their UTF-16 code units, numbers
+//! are written the way ECMAScript's `Number.prototype.toString` would write them, and strings
+//! only escape what they absolutely have to. This makes references reproducible by other
+//! languages' JCS implementations.
+//!
+//! [RFC 8785]: https://www.rfc-editor.org/rfc/rfc8785
+
+use ::{serde_json::Value, std::fmt::Write};
+
+use crate::{Artifact, ErrorKind};
+
+/// Serialize a JSON `Value` into its RFC 8785 canonical form.
+pub fn canonicalize(value: &Value) -> String {
+    let mut out = String::new();
+    write_value(&mut out, value);
+    out
+}
+
+/// Round-trip an artifact through its canonical form, normalizing numbers so that e.g. `1`
+/// and `1.0` are indistinguishable. The artifact's comparison settings are kept.
+pub(crate) fn canonicalize_artifact(artifact: &Artifact) -> Result<Artifact, ErrorKind> {
+    let value = serde_json::to_value(artifact)?;
+    let mut canonical: Artifact = serde_json::from_str(&canonicalize(&value))?;
+    canonical.copy_settings_from(artifact);
+    Ok(canonical)
+}
+
+fn write_value(out: &mut String, value: &Value) {
+    match value {
+        Value::Null => out.push_str("null"),
+        Value::Bool(b) => out.push_str(if *b { "true" } else { "false" }),
+        Value::Number(n) => {
+            // JCS treats every number as an IEEE 754 double, integers included.
+            let f = n
+                .as_f64()
+                .expect("serde_json numbers are always representable as f64");
+            out.push_str(&format_number(f));
+        }
+        Value::String(s) => write_string(out, s),
+        Value::Array(array) => {
+            out.push('[');
+            for (i, elem) in array.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_value(out, elem);
+            }
+            out.push(']');
+        }
+        Value::Object(map) => {
+            let mut entries = map.iter().collect::<Vec<_>>();
+            entries.sort_by(|(a, _), (b, _)| a.encode_utf16().cmp(b.encode_utf16()));
+
+            out.push('{');
+            for (i, (k, v)) in entries.into_iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_string(out, k);
+                out.push(':');
+                write_value(out, v);
+            }
+            out.push('}');
+        }
+    }
+}
+
+fn write_string(out: &mut String, s: &str) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\u{8}' => out.push_str("\\b"),
+            '\t' => out.push_str("\\t"),
+            '\n' => out.push_str("\\n"),
+            '\u{c}' => out.push_str("\\f"),
+            '\r' => out.push_str("\\r"),
+            c if (c as u32) < 0x20 => write!(out, "\\u{:04x}", c as u32).unwrap(),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+/// Format a finite `f64` following the ECMAScript `Number::toString` algorithm.
+fn format_number(f: f64) -> String {
+    // Also catches negative zero, which is serialized as plain `0`.
+    if f == 0.0 {
+        return "0".to_owned();
+    }
+
+    // `serde_json` writes the shortest digit string that round-trips, breaking ties toward an
+    // even last digit, which is exactly the `s`/`k`/`n` decomposition the ECMAScript algorithm
+    // is specified in terms of. It just doesn't lay out the digits the same way.
+    let shortest = serde_json::to_string(&f.abs()).unwrap();
+    let (mantissa, exponent) = match shortest.split_once(['e', 'E']) {
+        Some((mantissa, exponent)) => (mantissa, exponent.parse::<i32>().unwrap()),
+        None => (shortest.as_str(), 0),
+    };
+    let (int, frac) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+    let all_digits = format!("{}{}", int, frac);
+    let significant = all_digits.trim_start_matches('0');
+    let leading_zeros = (all_digits.len() - significant.len()) as i32;
+    let digits = significant.trim_end_matches('0');
+    let k = digits.len() as i32;
+    let n = int.len() as i32 + exponent - leading_zeros;
+
+    let mut out = String::new();
+    if f < 0.0 {
+        out.push('-');
+    }
+
+    if k <= n && n <= 21 {
+        out.push_str(digits);
+        out.push_str(&"0".repeat((n - k) as usize));
+    } else if 0 < n && n <= 21 {
+        out.push_str(&digits[..n as usize]);
+        out.push('.');
+        out.push_str(&digits[n as usize..]);
+    } else if -6 < n && n <= 0 {
+        out.push_str("0.");
+        out.push_str(&"0".repeat(-n as usize));
+        out.push_str(digits);
+    } else {
+        out.push_str(&digits[..1]);
+        if k > 1 {
+            out.push('.');
+            out.push_str(&digits[1..]);
+        }
+        write!(out, "e{}{}", if n > 0 { "+" } else { "-" }, (n - 1).abs()).unwrap();
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn numbers() {
+        // From RFC 8785, Appendix B.
+        let vectors: &[(u64, &str)] = &[
+            (0x0000000000000000, "0"),
+            (0x8000000000000000, "0"),
+            (0x0000000000000001, "5e-324"),
+            (0x8000000000000001, "-5e-324"),
+            (0x7fefffffffffffff, "1.7976931348623157e+308"),
+            (0xffefffffffffffff, "-1.7976931348623157e+308"),
+            (0x4340000000000000, "9007199254740992"),
+            (0xc340000000000000, "-9007199254740992"),
+            (0x4430000000000000, "295147905179352830000"),
+            (0x44b52d02c7e14af5, "9.999999999999997e+22"),
+            (0x44b52d02c7e14af6, "1e+23"),
+            (0x44b52d02c7e14af7, "1.0000000000000001e+23"),
+            (0x444b1ae4d6e2ef4e, "999999999999999700000"),
+            (0x444b1ae4d6e2ef4f, "999999999999999900000"),
+            (0x444b1ae4d6e2ef50, "1e+21"),
+            (0x3eb0c6f7a0b5ed8c, "9.999999999999997e-7"),
+            (0x3eb0c6f7a0b5ed8d, "0.000001"),
+            (0x41b3de4355555553, "333333333.3333332"),
+            (0x41b3de4355555554, "333333333.33333325"),
+            (0x41b3de4355555555, "333333333.3333333"),
+            (0x41b3de4355555556, "333333333.3333334"),
+            (0x41b3de4355555557, "333333333.33333343"),
+            (0xbecbf647612f3696, "-0.0000033333333333333333"),
+            (0x43143ff3c1cb0959, "1424953923781206.2"),
+        ];
+
+        for &(bits, expected) in vectors {
+            assert_eq!(format_number(f64::from_bits(bits)), expected, "{:#x}", bits);
+        }
+    }
+
+    #[test]
+    fn primitives() {
+        // From RFC 8785, section 3.2.2.
+        let input = r#"{
+            "numbers": [333333333.33333329, 1E30, 4.50, 2e-3, 0.000000000000000000000000001],
+            "string": "\u20ac$\u000F\u000aA'\u0042\u0022\u005c\\\"\/",
+            "literals": [null, true, false]
+        }"#;
+        let expected = r#"{"literals":[null,true,false],"numbers":[333333333.3333333,1e+30,4.5,0.002,1e-27],"string":"€$\u000f\nA'B\"\\\\\"/"}"#;
+
+        let value: Value = serde_json::from_str(input).unwrap();
+        assert_eq!(canonicalize(&value), expected);
+    }
+
+    #[test]
+    fn sorting() {
+        // From RFC 8785, section 3.2.3.
+        let input = r#"{
+            "\u20ac": "Euro Sign",
+            "\r": "Carriage Return",
+            "\ufb33": "Hebrew Letter Dalet With Dagesh",
+            "1": "One",
+            "\ud83d\ude00": "Emoji: Grinning Face",
+            "\u0080": "Control",
+            "\u00f6": "Latin Small Letter O With Diaeresis"
+        }"#;
+        let expected = concat!(
+            "{",
+            "\"\\r\":\"Carriage Return\",",
+            "\"1\":\"One\",",
+            "\"\u{80}\":\"Control\",",
+            "\"\u{f6}\":\"Latin Small Letter O With Diaeresis\",",
+            "\"\u{20ac}\":\"Euro Sign\",",
+            "\"\u{1f600}\":\"Emoji: Grinning Face\",",
+            "\"\u{fb33}\":\"Hebrew Letter Dalet With Dagesh\"",
+            "}",
+        );
+
+        let value: Value = serde_json::from_str(input).unwrap();
+        assert_eq!(canonicalize(&value), expected);
+    }
+}
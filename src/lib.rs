@@ -31,28 +31,92 @@
 //!
 //! To see the artifacts produced by this example, check `egress/artifacts/rust_out/basic_arithmetic.json`.
 //!
+//! ## Features
+//!
+//! - `color`: colors mismatch output when stderr is a terminal, unless `NO_COLOR` is set. This
+//!   includes highlighting the exact characters that changed within each changed line when
+//!   diffing string entries.
+//! - `yaml`: enables the `yaml` artifact format.
+//! - `cbor`: enables the `cbor` artifact format, a compact binary alternative to JSON for large
+//!   artifacts.
+//! - `ron`: enables the `ron` artifact format. The `toml` format is always available.
+//! - `gzip`: enables the `compress` config option, which writes references gzip-compressed.
+//! - `jcs`: enables the `jcs` config option, which writes references in [RFC 8785] canonical form
+//!   and canonicalizes both sides before comparing them.
+//! - `macros`: enables the `#[egress_test]` attribute, which opens a context for a test and
+//!   closes it again afterwards.
+//! - `schema`: enables `Artifact::validate_schema`, for checking artifacts against a JSON Schema.
+//!
+//! [RFC 8785]: https://www.rfc-editor.org/rfc/rfc8785
+//!
 
 #![deny(missing_docs)]
 
 use ::{
-    fs2::FileExt,
     serde::{Deserialize, Serialize},
     std::{
-        collections::HashMap,
-        fs::{self, File, OpenOptions},
-        io::{Read, Write},
-        path::PathBuf,
+        collections::{HashMap, HashSet},
+        fmt, fs,
+        iter::FromIterator,
+        path::{Component, PathBuf},
+        sync::Arc,
     },
 };
 
 mod artifact;
+mod builder;
+mod bytes;
+mod changes;
+mod diff;
 mod error;
+mod format;
+mod guard;
+#[cfg(feature = "jcs")]
+mod jcs;
+mod metadata;
+mod nonfinite;
+mod registry;
+#[cfg(feature = "schema")]
+mod schema;
+mod sidecar;
+mod store;
 
-use artifact::Mismatch;
+use artifact::{StreamContext, Tolerance};
+#[cfg(feature = "gzip")]
+use ::{
+    flate2::{read::GzDecoder, write::GzEncoder, Compression},
+    std::io::{Read, Write},
+};
 
-pub use artifact::{Artifact, Entry};
+pub use artifact::{Artifact, Entry, Mismatch, MismatchKind, ToleranceMode};
+pub use builder::EgressBuilder;
+pub use changes::describe_changes;
+/// Turn a function into a test which gets an Egress context opened for it, and closed again
+/// once the function returns, asserting that nothing regressed. The function takes either a
+/// `&mut Egress` or a `&mut Artifact`, and returns nothing:
+///
+/// ```ignore
+/// use egress::{egress_test, Artifact};
+///
+/// #[egress_test]
+/// fn arithmetic(artifact: &mut Artifact) {
+///     artifact.insert_serialize("1 + 1", &(1 + 1)).unwrap();
+/// }
+/// ```
+///
+/// The context's `artifact_subdir` is named after the module and the function, e.g.
+/// `tests/arithmetic`, and an `Artifact` argument is an artifact named after the function
+/// inside it. If the function panics, the context is dropped without writing anything.
+/// Requires the `macros` feature.
+#[cfg(feature = "macros")]
+pub use egress_macros::egress_test;
 pub use error::ErrorKind;
-use prettydiff::diff_chars;
+pub use format::Format;
+pub use guard::EgressGuard;
+#[cfg(feature = "jcs")]
+pub use jcs::canonicalize;
+pub use store::{ArtifactStore, FsStore, MemoryStore};
+
 #[doc(hidden)]
 pub use std::path::Path; // for macros
 
@@ -61,6 +125,54 @@ struct EgressConfig {
     artifact_dir: PathBuf,
     atol: Option<f64>,
     rtol: Option<f64>,
+    #[serde(default)]
+    tolerance_mode: ToleranceMode,
+    #[serde(default)]
+    strict_number_types: bool,
+    #[serde(default)]
+    integer_atol: bool,
+    #[serde(default)]
+    ulps: Option<u32>,
+    #[serde(default = "default_create_missing")]
+    create_missing: bool,
+    #[serde(default)]
+    inline_threshold_bytes: Option<usize>,
+    #[serde(default)]
+    format: Format,
+    #[serde(default = "default_json_pretty")]
+    json_pretty: bool,
+    #[serde(default)]
+    indent: Option<usize>,
+    #[serde(default)]
+    max_reported_mismatches: Option<usize>,
+    #[serde(default)]
+    metadata: bool,
+    #[serde(default)]
+    extension: Option<String>,
+    #[serde(default)]
+    round_floats_to_digits: Option<u32>,
+    #[serde(default = "default_fail_on_new_keys")]
+    fail_on_new_keys: bool,
+    #[serde(default)]
+    escape_control_chars: bool,
+    #[serde(default)]
+    backup_on_overwrite: bool,
+    #[serde(default)]
+    verbose: bool,
+    #[serde(default)]
+    write_on_close_against_dir: bool,
+    #[serde(default)]
+    report_path: Option<PathBuf>,
+    #[serde(default)]
+    dry_run: bool,
+    #[serde(default)]
+    preserve_order: bool,
+    #[cfg(feature = "jcs")]
+    #[serde(default)]
+    jcs: bool,
+    #[cfg(feature = "gzip")]
+    #[serde(default)]
+    compress: bool,
 }
 
 impl EgressConfig {
@@ -69,8 +181,51 @@ impl EgressConfig {
             artifact_dir: PathBuf::from("egress/artifacts/"),
             atol: Some(0.0),
             rtol: Some(0.0),
+            tolerance_mode: ToleranceMode::Separate,
+            strict_number_types: false,
+            integer_atol: false,
+            ulps: None,
+            create_missing: true,
+            inline_threshold_bytes: None,
+            format: Format::Json,
+            json_pretty: true,
+            indent: None,
+            max_reported_mismatches: None,
+            metadata: false,
+            extension: None,
+            round_floats_to_digits: None,
+            fail_on_new_keys: true,
+            escape_control_chars: false,
+            backup_on_overwrite: false,
+            verbose: false,
+            write_on_close_against_dir: false,
+            report_path: None,
+            dry_run: false,
+            preserve_order: false,
+            #[cfg(feature = "jcs")]
+            jcs: false,
+            #[cfg(feature = "gzip")]
+            compress: false,
         }
     }
+
+    /// Check the values which can be out of range, wherever they came from.
+    fn validate(&self) -> Result<(), ErrorKind> {
+        validate_tolerance("atol", self.atol)?;
+        validate_tolerance("rtol", self.rtol)
+    }
+}
+
+fn default_create_missing() -> bool {
+    true
+}
+
+fn default_json_pretty() -> bool {
+    true
+}
+
+fn default_fail_on_new_keys() -> bool {
+    true
 }
 
 /// Comparison report for newly generated artifacts versus the artifacts stored in
@@ -80,48 +235,337 @@ impl EgressConfig {
 #[serde(transparent)]
 pub struct Report {
     mismatches: Vec<Mismatch>,
+    #[serde(skip)]
+    regressed: Vec<Regressed>,
+    #[serde(skip)]
+    max_reported: Option<usize>,
+    #[serde(skip)]
+    summary: Summary,
+    #[serde(skip)]
+    would_create: Vec<String>,
+    #[serde(skip)]
+    orphans: Vec<String>,
+    /// The name of the artifact each mismatch belongs to, by index. Empty if the report was
+    /// deserialized.
+    #[serde(skip)]
+    artifact_names: Vec<String>,
+}
+
+/// How many artifacts a context was closed with, and what happened to them. Printed by
+/// `close` if the `verbose` config option or `EGRESS_VERBOSE=1` is set.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Summary {
+    /// The number of artifacts in the context.
+    pub artifacts: usize,
+    /// The number of artifacts written without being compared, because they had no reference
+    /// yet or were being recorded.
+    pub created: usize,
+    /// The number of artifacts compared against their reference, whether they matched or not.
+    /// This includes artifacts which didn't match and were rewritten in update mode, which
+    /// aren't counted as `created` or `regressed`.
+    pub compared: usize,
+    /// The number of artifacts which didn't match their reference or were missing one, and
+    /// haven't been updated.
+    pub regressed: usize,
+}
+
+impl fmt::Display for Summary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} artifacts, {} created, {} compared, {} regressed",
+            self.artifacts, self.created, self.compared, self.regressed
+        )
+    }
+}
+
+impl Summary {
+    fn merge(self, other: Summary) -> Summary {
+        Summary {
+            artifacts: self.artifacts + other.artifacts,
+            created: self.created + other.created,
+            compared: self.compared + other.compared,
+            regressed: self.regressed + other.regressed,
+        }
+    }
+}
+
+/// A newly produced artifact which didn't match its reference, kept around so that the
+/// reference can be overwritten by `Report::bless`.
+#[derive(Debug, Clone)]
+struct Regressed {
+    files: Vec<(PathBuf, Vec<u8>)>,
+    backup_on_overwrite: bool,
+    store: Arc<dyn ArtifactStore>,
+}
+
+/// Regressed artifacts are equal if they would write the same files, whichever store to.
+impl PartialEq for Regressed {
+    fn eq(&self, other: &Self) -> bool {
+        self.files == other.files && self.backup_on_overwrite == other.backup_on_overwrite
+    }
 }
 
 impl Report {
+    /// Whether no mismatches at all were found.
+    pub fn is_empty(&self) -> bool {
+        self.mismatches.is_empty()
+    }
+
+    /// The number of mismatches found.
+    pub fn len(&self) -> usize {
+        self.mismatches.len()
+    }
+
+    /// How many artifacts were created, compared and regressed.
+    pub fn summary(&self) -> Summary {
+        self.summary
+    }
+
+    /// The names of the artifacts without a reference which a dry run didn't create one for,
+    /// but otherwise would have. Always empty outside of dry runs; see `Egress::dry_run`.
+    pub fn would_create(&self) -> &[String] {
+        &self.would_create
+    }
+
+    /// The names of the references on disk which no artifact in the context was closed with,
+    /// e.g. because a test was renamed or removed, sorted. Only the references `close_and_prune`
+    /// would delete are considered. Always empty for `close_against` and `close_against_dir`.
+    pub fn orphans(&self) -> &[String] {
+        &self.orphans
+    }
+
+    /// All of the mismatches found, for inspecting or logging them without panicking.
+    pub fn mismatches(&self) -> &[Mismatch] {
+        &self.mismatches
+    }
+
+    /// The mismatches found, grouped by the name of the artifact they belong to.
+    pub fn by_artifact(&self) -> HashMap<String, Vec<&Mismatch>> {
+        let mut grouped = HashMap::new();
+        for (i, mismatch) in self.mismatches.iter().enumerate() {
+            grouped
+                .entry(self.artifact_name(i).to_owned())
+                .or_insert_with(Vec::new)
+                .push(mismatch);
+        }
+        grouped
+    }
+
+    /// The name of the artifact the `i`th mismatch belongs to. A deserialized report doesn't
+    /// know, so it's taken from the start of the mismatch's path instead.
+    fn artifact_name(&self, i: usize) -> &str {
+        match self.artifact_names.get(i) {
+            Some(name) => name,
+            None => {
+                let path = self.mismatches[i].path();
+                path.split(['.', '[']).next().unwrap_or(path)
+            }
+        }
+    }
+
+    fn all_artifact_names(&self) -> Vec<String> {
+        (0..self.mismatches.len())
+            .map(|i| self.artifact_name(i).to_owned())
+            .collect()
+    }
+
+    /// Check whether a mismatch was found at the given key path, e.g. `test.fruits[1]`.
+    pub fn has_mismatch(&self, path: &str) -> bool {
+        self.mismatches
+            .iter()
+            .any(|mismatch| mismatch.path() == path)
+    }
+
+    /// Panic if no mismatch was found at the given key path. This is the opposite of
+    /// `assert_unregressed`, for testing that a regression actually gets caught.
+    pub fn assert_mismatch(&self, path: &str) {
+        if !self.has_mismatch(path) {
+            let paths = self
+                .mismatches
+                .iter()
+                .map(Mismatch::path)
+                .collect::<Vec<_>>();
+            panic!(
+                "expected a mismatch at `{}`, but only found mismatches at {:?}",
+                path, paths
+            );
+        }
+    }
+
+    /// Accept the newly produced output as correct, overwriting the reference files of every
+    /// artifact which had mismatches. This is the programmatic equivalent of rerunning with
+    /// `EGRESS_UPDATE=1`.
+    pub fn bless(self) -> Result<(), ErrorKind> {
+        for regressed in self.regressed {
+            write_files(
+                &*regressed.store,
+                regressed.files,
+                regressed.backup_on_overwrite,
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// Render the report as a JUnit XML `<testsuite>`, for CI dashboards which pick up test
+    /// results in that format. Every mismatch becomes a failing `<testcase>` named after its key
+    /// path, with the diff as the failure's body; a report without mismatches is a suite with a
+    /// single passing test case.
+    pub fn to_junit_xml(&self, suite_name: &str) -> String {
+        let suite_name = xml_escape(suite_name);
+        let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+
+        if self.mismatches.is_empty() {
+            xml.push_str(&format!(
+                "<testsuite name=\"{0}\" tests=\"1\" failures=\"0\">\n  <testcase name=\"{0}\" classname=\"{0}\"/>\n</testsuite>\n",
+                suite_name
+            ));
+            return xml;
+        }
+
+        xml.push_str(&format!(
+            "<testsuite name=\"{0}\" tests=\"{1}\" failures=\"{1}\">\n",
+            suite_name,
+            self.mismatches.len()
+        ));
+        for mismatch in &self.mismatches {
+            let path = xml_escape(mismatch.path());
+            xml.push_str(&format!(
+                "  <testcase name=\"{0}\" classname=\"{1}\">\n    <failure message=\"mismatch at {0}\">{2}</failure>\n  </testcase>\n",
+                path,
+                suite_name,
+                xml_escape(&artifact::Plain(mismatch).to_string())
+            ));
+        }
+        xml.push_str("</testsuite>\n");
+        xml
+    }
+
+    /// Combine two reports into one, e.g. to fail once for all of the contexts in a harness.
+    /// Blessing the merged report blesses the artifacts of both.
+    pub fn merge(mut self, other: Report) -> Report {
+        self.artifact_names = self.all_artifact_names();
+        self.artifact_names.extend(other.all_artifact_names());
+        self.mismatches.extend(other.mismatches);
+        self.regressed.extend(other.regressed);
+        self.max_reported = self.max_reported.or(other.max_reported);
+        self.summary = self.summary.merge(other.summary);
+        self.would_create.extend(other.would_create);
+        self.orphans.extend(other.orphans);
+        self
+    }
+
+    /// Turn the report into an error if any mismatches were found, for harnesses which collect
+    /// failures rather than panicking on the first one.
+    // The report itself is the error, so that it can be inspected or blessed.
+    #[allow(clippy::result_large_err)]
+    pub fn into_result(self) -> Result<(), Report> {
+        if self.mismatches.is_empty() {
+            Ok(())
+        } else {
+            Err(self)
+        }
+    }
+
     /// If any mismatches were found, this function will iterate through and print info
-    /// about them to stdout, before panicking.
+    /// about them to stderr, before panicking.
     pub fn assert_unregressed(self) {
+        if let Err(report) = self.into_result() {
+            eprintln!("{}", report);
+            panic!("End found mismatches; panicking to fail the test.");
+        }
+    }
+
+    /// Like `assert_unregressed`, but also panic if there are any `orphans`: references which
+    /// no artifact was closed with, which usually means a test was renamed or removed and its
+    /// reference left behind.
+    pub fn assert_unregressed_strict(self) {
+        if self.orphans.is_empty() {
+            return self.assert_unregressed();
+        }
+
         if !self.mismatches.is_empty() {
-            for mismatch in self.mismatches {
-                match mismatch {
-                    Mismatch::NotEq(k, new_value, reference) => {
-                        eprintln!(
-                            "MISMATCH: entry `{}` not the same as the reference value",
-                            k
-                        );
-                        let old_s = serde_json::to_string(&reference).unwrap();
-                        let new_s = serde_json::to_string(&new_value).unwrap();
-                        eprintln!("Diff:\n {}", diff_chars(&old_s, &new_s,));
-                    }
-                    Mismatch::NotInReference(k, _) => {
-                        eprintln!("MISMATCH: entry `{}` does not exist in the reference", k)
-                    }
-                    Mismatch::NotProduced(k, _) => eprintln!(
-                        "MISMATCH: entry `{}` exists in the reference but was not found here",
-                        k
-                    ),
-                    Mismatch::LengthMismatch(k, len, len_ref, new, reference) => {
-                        eprintln!(
-                            "MISMATCH: array length for `{}` was {} but is now {}",
-                            k, len_ref, len
-                        );
-                        let old_s = serde_json::to_string(&reference).unwrap();
-                        let new_s = serde_json::to_string(&new).unwrap();
-                        eprintln!("Diff:\n {}", diff_chars(&old_s, &new_s,));
-                    }
-                }
+            eprintln!("{}", self);
+        }
+        for orphan in &self.orphans {
+            eprintln!(
+                "ORPHAN: reference for `{}` exists but no artifact was produced for it",
+                orphan
+            );
+        }
+        panic!("End found orphaned references; panicking to fail the test.");
+    }
+}
+
+/// Every mismatch on its own line, under a header naming the artifact it belongs to, in the
+/// same format `assert_unregressed` prints them in. If `max_reported_mismatches` is configured,
+/// only that many are shown, followed by a count of the rest.
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let shown = self
+            .max_reported
+            .unwrap_or(usize::MAX)
+            .min(self.mismatches.len());
+        for (i, mismatch) in self.mismatches[..shown].iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
             }
-            panic!("End found mismatches; panicking to fail the test.");
+            if i == 0 || self.artifact_name(i) != self.artifact_name(i - 1) {
+                writeln!(f, "In artifact `{}`:", self.artifact_name(i))?;
+            }
+            write!(f, "MISMATCH: {}", mismatch)?;
+        }
+        if shown < self.mismatches.len() {
+            if shown > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "... and {} more", self.mismatches.len() - shown)?;
         }
+        Ok(())
+    }
+}
+
+impl std::error::Error for Report {}
+
+/// Merge any number of reports into one, as with `Report::merge`.
+impl FromIterator<Report> for Report {
+    fn from_iter<I: IntoIterator<Item = Report>>(reports: I) -> Self {
+        let empty = Report {
+            mismatches: Vec::new(),
+            regressed: Vec::new(),
+            max_reported: None,
+            summary: Summary::default(),
+            would_create: Vec::new(),
+            orphans: Vec::new(),
+            artifact_names: Vec::new(),
+        };
+        reports.into_iter().fold(empty, Report::merge)
     }
 }
 
-/// A testing context. You can open as many as you want, but make sure their `artifact_subdir`s don't collide.
+/// Escape text for use in XML character data or attribute values.
+fn xml_escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            // Control characters other than tabs and newlines aren't allowed in XML 1.0 at all.
+            c if c.is_control() && c != '\t' && c != '\n' && c != '\r' => {
+                escaped.push_str(&format!("\\u{{{:04x}}}", c as u32))
+            }
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// A testing context. You can open as many as you want, as long as no two live at the same time
+/// with the same `artifact_subdir`, or with one inside the other.
 #[derive(Debug)]
 pub struct Egress {
     artifact_subdir: PathBuf,
@@ -130,6 +574,97 @@ pub struct Egress {
     pub atol: Option<f64>,
     /// Set the relative tolerance (absolute(a - b) <= rtol * absolute(b))
     pub rtol: Option<f64>,
+    /// Set how `atol` and `rtol` combine when both are set (defaults to requiring both)
+    pub tolerance_mode: ToleranceMode,
+    /// Report a mismatch when a number changes between an integer and a float, e.g. from `1`
+    /// to `1.0`, even if the two are numerically equal
+    pub strict_number_types: bool,
+    /// Apply `atol` to integers as well as floats, e.g. to let Unix timestamps drift by a
+    /// second. Integers are otherwise always compared exactly. `rtol` never applies to them
+    pub integer_atol: bool,
+    /// Let floats match if they're at most this many representable floats apart, whatever
+    /// `atol` and `rtol` say, which suits values spanning many orders of magnitude. A float
+    /// matches if it's within either `ulps` or `atol` and `rtol`. Defaults to unset
+    pub ulps: Option<u32>,
+    /// Write a new reference when an artifact doesn't have one yet, rather than reporting a
+    /// `Mismatch::MissingReference`. Always off if `EGRESS_CI=1` is set in the environment
+    pub create_missing: bool,
+    /// Store entries bigger than this many bytes in sidecar files next to the reference, which
+    /// only the entry's hash is kept in. Sidecars are named after the artifact and the entry,
+    /// e.g. `artifact.key.bin`. Defaults to storing every entry inline
+    pub inline_threshold_bytes: Option<usize>,
+    /// Set the file format references are written and read in
+    pub format: Format,
+    /// Pretty-print JSON references rather than writing them on a single line. References are
+    /// read the same way either way, so switching this doesn't cause any mismatches
+    pub json_pretty: bool,
+    /// The number of spaces pretty-printed JSON references are indented by (defaults to 2)
+    pub indent: Option<usize>,
+    /// Only print this many mismatches when displaying the `Report`, e.g. in
+    /// `assert_unregressed`, followed by a count of the rest. The `Report` itself always keeps
+    /// every mismatch. Defaults to printing all of them
+    pub max_reported_mismatches: Option<usize>,
+    /// Write a `$metadata` entry into every reference, recording the version of the crate under
+    /// test, the version of Egress and when the reference was generated. Metadata never causes
+    /// a mismatch
+    pub metadata: bool,
+    /// Set the file extension references are written with and looked up by, e.g. `snap.json`
+    /// to tell them apart from other JSON files. Defaults to the extension of the `format`
+    pub extension: Option<String>,
+    /// Round every float to this many significant digits before it's stored or compared, so
+    /// that tiny platform-dependent differences in the last few digits never show up in the
+    /// reference. Applies to the reference being compared against too. Defaults to no rounding
+    pub round_floats_to_digits: Option<u32>,
+    /// Report keys which are only in the newly produced artifact as `Mismatch::NotInReference`.
+    /// When off, `close` adds new keys to the reference instead, leaving the rest of it as it
+    /// was, and only changed and removed keys are reported. Extra array elements are always
+    /// reported. Defaults to on
+    pub fail_on_new_keys: bool,
+    /// Replace control characters in string entries, other than newlines and tabs, with
+    /// visible escapes like `\u{1b}` before they're stored or compared, so that e.g. ANSI
+    /// color codes in captured output don't garble diffs
+    pub escape_control_chars: bool,
+    /// Before overwriting a reference in update or record mode, or with `Report::bless`, copy
+    /// the previous one to `<name>.bak`, along with any sidecars being overwritten. Only the
+    /// most recent backup is kept
+    pub backup_on_overwrite: bool,
+    /// Print a summary line to stderr when closing, like `egress: 12 artifacts, 2 created,
+    /// 10 compared, 0 regressed`, even if nothing regressed. Also on if `EGRESS_VERBOSE=1` is
+    /// set in the environment
+    pub verbose: bool,
+    /// Write the newly produced artifacts to `artifact_subdir` in `close_against_dir`, as well as
+    /// comparing them against the references in the other directory
+    pub write_on_close_against_dir: bool,
+    /// Write the `Report` of every `close` to this file as JSON, e.g. for external review
+    /// tooling, overwriting it each time. A relative path in `Egress.toml` is relative to the
+    /// directory containing it. Defaults to not writing a report
+    pub report_path: Option<PathBuf>,
+    /// Compare against the existing references in `close`, but never write, create or delete
+    /// any file: missing references are listed in `Report::would_create` instead of being
+    /// written, update and record mode are ignored and nothing is pruned. Also on if
+    /// `EGRESS_DRY_RUN=1` is set in the environment, which additionally keeps `open` from
+    /// creating a missing `Egress.toml`
+    pub dry_run: bool,
+    /// Write the entries of every reference in the order they were inserted into the artifact,
+    /// rather than sorted by key, so that e.g. `step_2` comes before `step_10`. Only the
+    /// artifact's own entries are affected, not those of nested artifacts or the fields of JSON
+    /// objects, and comparing never depends on the order. TOML and RON references, and JCS
+    /// canonical ones, are always sorted
+    pub preserve_order: bool,
+    /// Write references in RFC 8785 canonical form, and canonicalize both the reference and the
+    /// newly produced artifact before comparing them.
+    #[cfg(feature = "jcs")]
+    pub jcs: bool,
+    /// Write references gzip-compressed, with `.gz` appended to their extension, e.g.
+    /// `artifact.json.gz`. Sidecar files are never compressed. Requires the `gzip` feature.
+    #[cfg(feature = "gzip")]
+    pub compress: bool,
+    capture: bool,
+    /// The absolute path `artifact_subdir` was claimed under in the registry of live contexts,
+    /// once it has been.
+    registered: Option<PathBuf>,
+    /// Where references and their sidecars are read from and written to.
+    store: Arc<dyn ArtifactStore>,
 }
 
 impl Egress {
@@ -139,99 +674,942 @@ impl Egress {
     ///
     /// If an `Egress.toml` file is not found, one will be initialized with the default values at the directory
     /// indicated by `config_dir`.
+    ///
+    /// The artifact directory is taken from the `EGRESS_ARTIFACT_DIR` environment variable if it's set,
+    /// then from `artifact_dir` in `Egress.toml`, and otherwise defaults to `egress/artifacts/`. Either
+    /// way, a relative path is relative to `config_dir`.
+    ///
+    /// Likewise, the `EGRESS_ATOL` and `EGRESS_RTOL` environment variables override `atol` and
+    /// `rtol` from `Egress.toml`, for loosening tolerances without editing it. Setting `atol` or
+    /// `rtol` on the returned context still overrides both. A value which isn't a number is an
+    /// `ErrorKind::InvalidEnvVar`, and a negative or non-finite one an `ErrorKind::InvalidConfig`.
     pub fn open<P, Q>(config_dir: P, artifact_subdir: Q) -> Result<Self, ErrorKind>
     where
         P: AsRef<Path>,
         Q: AsRef<Path>,
     {
-        let path = config_dir.as_ref().join("Egress.toml");
+        Self::open_with_config_file(config_dir, "Egress.toml", artifact_subdir)
+    }
 
-        if !path.exists() {
-            fs::create_dir_all(&config_dir)?;
-            let mut config_file = OpenOptions::new()
-                .write(true)
-                .create_new(true)
-                .open(&path)?;
+    /// Open a new `Egress` context like `open`, wrapped in a guard which closes it and asserts
+    /// that nothing regressed when it's dropped, so that the check can't be forgotten.
+    pub fn open_guarded<P, Q>(config_dir: P, artifact_subdir: Q) -> Result<EgressGuard, ErrorKind>
+    where
+        P: AsRef<Path>,
+        Q: AsRef<Path>,
+    {
+        Self::open(config_dir, artifact_subdir).map(EgressGuard::new)
+    }
 
-            config_file.lock_exclusive()?;
+    /// Open a new `Egress` context like `open`, but reading its config from `config_file_name`
+    /// inside `config_dir` rather than from `Egress.toml`, e.g. to share one config between the
+    /// crates in a workspace. The file is initialized with the default values if it doesn't exist.
+    pub fn open_with_config_file<P, F, Q>(
+        config_dir: P,
+        config_file_name: F,
+        artifact_subdir: Q,
+    ) -> Result<Self, ErrorKind>
+    where
+        P: AsRef<Path>,
+        F: AsRef<Path>,
+        Q: AsRef<Path>,
+    {
+        Self::open_config(
+            config_dir.as_ref(),
+            config_file_name.as_ref(),
+            artifact_subdir,
+            true,
+        )
+    }
 
-            let config_string = toml::ser::to_string_pretty(&EgressConfig::new())?;
-            config_file.write_all(config_string.as_bytes())?;
+    /// Open a new `Egress` context like `open`, but without ever creating an `Egress.toml`:
+    /// if there isn't one in `config_dir`, this returns `ErrorKind::MissingConfig` instead. Meant
+    /// for tools which only inspect references, and shouldn't leave a config file behind when
+    /// run in the wrong directory.
+    pub fn open_existing<P, Q>(config_dir: P, artifact_subdir: Q) -> Result<Self, ErrorKind>
+    where
+        P: AsRef<Path>,
+        Q: AsRef<Path>,
+    {
+        Self::open_config(
+            config_dir.as_ref(),
+            Path::new("Egress.toml"),
+            artifact_subdir,
+            false,
+        )
+    }
 
-            config_file.unlock()?;
+    /// Open a context with the config in `config_file_name`, which is initialized with the
+    /// default values if it doesn't exist and `create` is set, and is an error otherwise.
+    fn open_config<Q: AsRef<Path>>(
+        config_dir: &Path,
+        config_file_name: &Path,
+        artifact_subdir: Q,
+        create: bool,
+    ) -> Result<Self, ErrorKind> {
+        let path = config_dir.join(config_file_name);
+        if !create && !path.exists() {
+            return Err(ErrorKind::MissingConfig(path));
         }
 
-        let mut file = File::open(path)?;
-        file.lock_shared()?;
+        let dry_run = env_flag("EGRESS_DRY_RUN");
+        if !path.exists() && !dry_run {
+            fs::create_dir_all(config_dir)?;
+            let config_string = toml::ser::to_string_pretty(&EgressConfig::new())?;
+            store::create_new_file(&path, config_string.as_bytes())?;
+        }
 
-        let config: EgressConfig = {
-            let mut s = String::new();
-            file.read_to_string(&mut s)?;
-            toml::de::from_str(&s)?
+        let mut config: EgressConfig = if path.exists() {
+            toml::de::from_slice(&store::read_file(&path)?)?
+        } else {
+            EgressConfig::new()
         };
+        config.dry_run |= dry_run;
+
+        if let Some(artifact_dir) = std::env::var_os("EGRESS_ARTIFACT_DIR") {
+            config.artifact_dir = PathBuf::from(artifact_dir);
+        }
+        if let Some(atol) = env_float("EGRESS_ATOL")? {
+            validate_tolerance("EGRESS_ATOL", Some(atol))?;
+            config.atol = Some(atol);
+        }
+        if let Some(rtol) = env_float("EGRESS_RTOL")? {
+            validate_tolerance("EGRESS_RTOL", Some(rtol))?;
+            config.rtol = Some(rtol);
+        }
+        config.validate()?;
+        config.report_path = config
+            .report_path
+            .map(|report_path| config_dir.join(report_path));
 
         let artifact_subdir = config_dir
-            .as_ref()
             .join(&config.artifact_dir)
             .join(artifact_subdir.as_ref());
 
-        let artifacts = HashMap::new();
+        let mut egress = Self::from_config(config, artifact_subdir);
+        egress.registered = Some(registry::register(&egress.artifact_subdir)?);
+        Ok(egress)
+    }
+
+    /// Start building an `Egress` context from configuration given in code, without reading
+    /// or creating an `Egress.toml`.
+    pub fn builder() -> EgressBuilder {
+        EgressBuilder::new()
+    }
 
-        Ok(Self {
+    fn from_config(config: EgressConfig, artifact_subdir: PathBuf) -> Self {
+        Self {
             artifact_subdir,
-            artifacts,
+            artifacts: HashMap::new(),
             atol: config.atol,
             rtol: config.rtol,
-        })
+            tolerance_mode: config.tolerance_mode,
+            strict_number_types: config.strict_number_types,
+            integer_atol: config.integer_atol,
+            ulps: config.ulps,
+            create_missing: config.create_missing,
+            inline_threshold_bytes: config.inline_threshold_bytes,
+            format: config.format,
+            json_pretty: config.json_pretty,
+            indent: config.indent,
+            max_reported_mismatches: config.max_reported_mismatches,
+            metadata: config.metadata,
+            extension: config.extension,
+            round_floats_to_digits: config.round_floats_to_digits,
+            fail_on_new_keys: config.fail_on_new_keys,
+            escape_control_chars: config.escape_control_chars,
+            backup_on_overwrite: config.backup_on_overwrite,
+            verbose: config.verbose,
+            write_on_close_against_dir: config.write_on_close_against_dir,
+            report_path: config.report_path,
+            dry_run: config.dry_run,
+            preserve_order: config.preserve_order,
+            #[cfg(feature = "jcs")]
+            jcs: config.jcs,
+            #[cfg(feature = "gzip")]
+            compress: config.compress,
+            capture: false,
+            registered: None,
+            store: Arc::new(FsStore),
+        }
     }
 
     /// Construct a new `Artifact` reference. Any data inserted into the artifact returned
     /// will be written into a directory inside the `artifact_dir` configured in `Egress.toml`.
+    ///
+    /// The name may contain path separators to organize artifacts into subdirectories, e.g.
+    /// `group/case1` is written to `group/case1.json`. It can't be absolute or contain `..`.
+    /// Dots are fine, and are kept as part of the name: `config.prod` is written to
+    /// `config.prod.json`.
+    ///
+    /// Panics if the name is invalid or already taken; see `try_artifact` for a version which
+    /// doesn't.
     pub fn artifact<P: AsRef<Path>>(&mut self, name: P) -> &mut Artifact {
-        let path = name
-            .as_ref()
-            .file_stem()
-            .expect("artifact name must be a file stem!")
-            .to_owned();
-        assert_eq!(&path, name.as_ref(), "artifact name must be a file stem!");
+        match self.try_artifact(name) {
+            Ok(artifact) => artifact,
+            Err(err) => panic!("{}", err),
+        }
+    }
+
+    /// Construct a new `Artifact` reference, returning `ErrorKind::InvalidArtifactName` if the
+    /// name is invalid and `ErrorKind::DuplicateArtifact` if it's already taken. A context made
+    /// with `Egress::builder` claims its `artifact_subdir` here, the first time it's called, and
+    /// returns `ErrorKind::ArtifactSubdirInUse` if another live context is using it.
+    pub fn try_artifact<P: AsRef<Path>>(&mut self, name: P) -> Result<&mut Artifact, ErrorKind> {
+        if self.registered.is_none() {
+            self.registered = Some(registry::register(&self.artifact_subdir)?);
+        }
 
+        let name = name.as_ref();
+        let only_normal_components = name
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)));
+        if !only_normal_components || name.file_name().is_none() {
+            return Err(ErrorKind::InvalidArtifactName(name.to_owned()));
+        }
+
+        let stream_context = self.stream_context(name);
         use std::collections::hash_map::Entry::*;
-        match self.artifacts.entry(PathBuf::from(path)) {
-            Occupied(_) => panic!(
-                "only one artifact allowed with the name `{}`!",
-                name.as_ref().display()
-            ),
-            Vacant(vacant) => vacant.insert(Artifact::new()),
+        match self.artifacts.entry(name.to_owned()) {
+            Occupied(_) => Err(ErrorKind::DuplicateArtifact(name.to_owned())),
+            Vacant(vacant) => {
+                let mut artifact = Artifact::new();
+                artifact.set_stream_context(stream_context);
+                Ok(vacant.insert(artifact))
+            }
         }
     }
 
+    /// Switch this context into capture mode, where `close` unconditionally writes every
+    /// artifact out as the new reference, overwriting whatever was there, and never compares
+    /// anything.
+    ///
+    /// This is for deliberately seeding references from a run you know to be good, rather than
+    /// relying on the first run of a test to create them. Don't leave it on in CI, or nothing
+    /// will ever be checked!
+    ///
+    /// Setting the `EGRESS_RECORD` environment variable to `1` switches every context into
+    /// capture mode, for regenerating all of a suite's references in one go. Unlike
+    /// `EGRESS_UPDATE`, this rewrites references which already match too, which normalizes
+    /// their formatting.
+    ///
+    /// Switch it on before making any artifact with `Artifact::insert_stream`: a stream made
+    /// beforehand stops at its first mismatch, and `close` then returns
+    /// `ErrorKind::TruncatedStream` rather than writing the incomplete stream as the reference.
+    pub fn capture_mode(&mut self) {
+        self.capture = true;
+    }
+
     /// Close the testing context and write new artifacts to disk before reporting
     /// any artifacts which don't match the reference values stored in the `egress/artifacts`
     /// folder.
-    pub fn close(self) -> Result<Report, ErrorKind> {
+    ///
+    /// If the `EGRESS_UPDATE` environment variable is set to `1`, any reference which doesn't
+    /// match is overwritten with the newly produced artifact instead, and the returned `Report`
+    /// is empty. This is how you accept a legitimate change in a test's output.
+    ///
+    /// An artifact without any reference gets one written for it, unless `create_missing` is
+    /// off or the `EGRESS_CI` environment variable is set to `1`, in which case it's reported
+    /// as a `Mismatch::MissingReference`. Blessing the report writes the missing references.
+    ///
+    /// In a dry run, nothing is written at all; see `dry_run`. If a directory or file under
+    /// `artifact_subdir` can't be created or written, e.g. on a read-only filesystem, the error
+    /// is an `ErrorKind::ArtifactIo` naming it.
+    ///
+    /// References on disk which none of the artifacts belong to are left alone, but listed in
+    /// `Report::orphans`, which `Report::assert_unregressed_strict` fails on.
+    pub fn close(mut self) -> Result<Report, ErrorKind> {
+        self.compare()
+    }
+
+    /// Compare and write the artifacts produced so far exactly like `close`, but without
+    /// consuming the context. The artifacts are cleared afterwards, so that new ones can be
+    /// added, even under the same names, and compared in turn, e.g. in a multi-phase test.
+    pub fn compare(&mut self) -> Result<Report, ErrorKind> {
+        let dry_run = self.is_dry_run();
+        let update = env_flag("EGRESS_UPDATE") && !dry_run;
+        let ci = env_flag("EGRESS_CI");
+        let record = (self.capture || env_flag("EGRESS_RECORD")) && !dry_run;
+        let mut mismatches = Vec::new();
+        let mut artifact_names = Vec::new();
+        let mut regressed = Vec::new();
+        let mut would_create = Vec::new();
+        let mut summary = Summary {
+            artifacts: self.artifacts.len(),
+            ..Summary::default()
+        };
+
+        let orphans = self
+            .orphaned_references()?
+            .iter()
+            .filter_map(|path| self.reference_name(path))
+            .map(|name| artifact_key(&name))
+            .collect();
+
+        if !dry_run {
+            create_artifact_dir(&*self.store, &self.artifact_subdir)?;
+        }
+        for (path, artifact) in std::mem::take(&mut self.artifacts) {
+            let path_to_file = self.path_to_file(&path);
+
+            if let (Some(parent), false) = (path_to_file.parent(), dry_run) {
+                create_artifact_dir(&*self.store, parent)?;
+            }
+
+            let truncated = artifact.has_truncated_streams();
+            let (artifact, split) = self.prepare(&path, artifact)?;
+            let stored = split.as_ref().map_or(&artifact, |(stored, _)| stored);
+            let dir = path_to_file.parent().unwrap_or(&self.artifact_subdir);
+            let files = || self.reference_files(&path_to_file, stored, &split);
+
+            if record {
+                check_complete(&path, truncated)?;
+                write_files(&*self.store, files()?, self.backup_on_overwrite)?;
+                summary.created += 1;
+            } else if self.store.exists(&path_to_file) {
+                summary.compared += 1;
+                let raw_reference = self.deserialize_reference(&self.store.read(&path_to_file)?)?;
+                let mut reference = self.normalize(raw_reference.clone())?;
+                sidecar::resolve(&*self.store, &mut reference, stored, &artifact, dir)?;
+
+                let mut artifact_mismatches = self.compare_artifact(&path, &artifact, &reference);
+                if !self.fail_on_new_keys && !update {
+                    artifact_mismatches.retain(|mismatch| !mismatch.is_new_key());
+                    if !dry_run {
+                        self.merge_new_keys(&path_to_file, raw_reference, stored, &split)?;
+                    }
+                }
+
+                if artifact_mismatches.is_empty() {
+                    continue;
+                }
+
+                if update {
+                    check_complete(&path, truncated)?;
+                    write_files(&*self.store, files()?, self.backup_on_overwrite)?;
+                } else {
+                    summary.regressed += 1;
+                    mismatches.extend(artifact_mismatches);
+                    artifact_names.resize(mismatches.len(), artifact_key(&path));
+                    // A stream cut short at its first mismatch is no reference to bless.
+                    if !truncated {
+                        regressed.push(Regressed {
+                            files: files()?,
+                            backup_on_overwrite: self.backup_on_overwrite,
+                            store: self.store.clone(),
+                        });
+                    }
+                }
+            } else if dry_run && self.create_missing && !ci {
+                would_create.push(artifact_key(&path));
+            } else if update || (self.create_missing && !ci) {
+                write_files(&*self.store, files()?, self.backup_on_overwrite)?;
+                summary.created += 1;
+            } else {
+                mismatches.push(Mismatch::MissingReference(artifact_key(&path)));
+                artifact_names.push(artifact_key(&path));
+                summary.regressed += 1;
+                regressed.push(Regressed {
+                    files: files()?,
+                    backup_on_overwrite: self.backup_on_overwrite,
+                    store: self.store.clone(),
+                });
+            }
+        }
+
+        if self.verbose || env_flag("EGRESS_VERBOSE") {
+            eprintln!("egress: {}", summary);
+        }
+
+        let report = Report {
+            mismatches,
+            regressed,
+            max_reported: self.max_reported_mismatches,
+            summary,
+            would_create,
+            orphans,
+            artifact_names,
+        };
+        if let (Some(report_path), false) = (&self.report_path, dry_run) {
+            if let Some(parent) = report_path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            store::write_file(report_path, &serde_json::to_vec_pretty(&report)?)?;
+        }
+
+        Ok(report)
+    }
+
+    /// Close the testing context like `close`, but compare every artifact against the reference
+    /// given for its name in `references` rather than against the one on disk. Nothing is read
+    /// from or written to disk, so this is handy for testing code which uses Egress itself. An
+    /// artifact without a reference is reported as a `Mismatch::MissingReference`, and blessing
+    /// the report does nothing.
+    pub fn close_against(
+        mut self,
+        references: HashMap<PathBuf, Artifact>,
+    ) -> Result<Report, ErrorKind> {
         let mut mismatches = Vec::new();
+        let mut artifact_names = Vec::new();
+        let mut summary = Summary {
+            artifacts: self.artifacts.len(),
+            ..Summary::default()
+        };
+
+        for (path, artifact) in std::mem::take(&mut self.artifacts) {
+            let mut artifact = self.normalize(artifact)?;
+            let mismatches_before = mismatches.len();
+            match references.get(&path) {
+                Some(reference) => {
+                    let mut reference = self.normalize(reference.clone())?;
+                    if artifact.is_hash_only() {
+                        artifact = artifact.hashed();
+                        reference = reference.hashed();
+                    }
+                    mismatches.extend(self.compare_artifact(&path, &artifact, &reference));
+                    summary.compared += 1;
+                }
+                None => mismatches.push(Mismatch::MissingReference(artifact_key(&path))),
+            }
+            if mismatches.len() > mismatches_before {
+                summary.regressed += 1;
+            }
+            artifact_names.resize(mismatches.len(), artifact_key(&path));
+        }
+
+        Ok(Report {
+            mismatches,
+            regressed: Vec::new(),
+            max_reported: self.max_reported_mismatches,
+            summary,
+            would_create: Vec::new(),
+            orphans: Vec::new(),
+            artifact_names,
+        })
+    }
 
-        fs::create_dir_all(&self.artifact_subdir)?;
-        for (path, artifact) in self.artifacts.iter() {
-            let mut path_to_file = self.artifact_subdir.join(path);
-            path_to_file.set_extension("json");
-
-            if path_to_file.exists() {
-                let mut file = File::open(&path_to_file)?;
-                let reference = serde_json::from_reader(&mut file)?;
-                mismatches.extend(artifact.report_mismatches(
-                    path.to_string_lossy().into_owned(),
-                    &reference,
-                    self.atol,
-                    self.rtol,
-                ));
+    /// Close the testing context like `close`, but compare every artifact against the reference
+    /// of the same name in `reference_dir` rather than in `artifact_subdir`, e.g. to compare a
+    /// run against the references checked out from another branch. `reference_dir` is laid out
+    /// like `artifact_subdir`, sidecars and all. An artifact without a reference there is
+    /// reported as a `Mismatch::MissingReference`.
+    ///
+    /// The newly produced artifacts are only written to `artifact_subdir` if
+    /// `write_on_close_against_dir` is set, in which case every one of them is, matching or
+    /// not. Nothing is ever written to `reference_dir`, and blessing the report does nothing.
+    pub fn close_against_dir<P: AsRef<Path>>(
+        mut self,
+        reference_dir: P,
+    ) -> Result<Report, ErrorKind> {
+        let reference_dir = reference_dir.as_ref();
+        let mut mismatches = Vec::new();
+        let mut artifact_names = Vec::new();
+        let mut summary = Summary {
+            artifacts: self.artifacts.len(),
+            ..Summary::default()
+        };
+
+        for (path, artifact) in std::mem::take(&mut self.artifacts) {
+            let truncated = artifact.has_truncated_streams();
+            let (artifact, split) = self.prepare(&path, artifact)?;
+            let stored = split.as_ref().map_or(&artifact, |(stored, _)| stored);
+
+            let reference_file = self.reference_file_in(reference_dir, &path);
+            if self.store.exists(&reference_file) {
+                let raw_reference =
+                    self.deserialize_reference(&self.store.read(&reference_file)?)?;
+                let mut reference = self.normalize(raw_reference)?;
+                let dir = reference_file.parent().unwrap_or(reference_dir);
+                sidecar::resolve(&*self.store, &mut reference, stored, &artifact, dir)?;
+
+                let artifact_mismatches = self.compare_artifact(&path, &artifact, &reference);
+                summary.compared += 1;
+                if !artifact_mismatches.is_empty() {
+                    summary.regressed += 1;
+                }
+                mismatches.extend(artifact_mismatches);
             } else {
-                let mut file = File::create(&path_to_file)?;
-                serde_json::to_writer_pretty(&mut file, artifact)?;
+                mismatches.push(Mismatch::MissingReference(artifact_key(&path)));
+                summary.regressed += 1;
+            }
+            artifact_names.resize(mismatches.len(), artifact_key(&path));
+
+            if self.write_on_close_against_dir && !self.is_dry_run() {
+                check_complete(&path, truncated)?;
+                let path_to_file = self.path_to_file(&path);
+                if let Some(parent) = path_to_file.parent() {
+                    create_artifact_dir(&*self.store, parent)?;
+                }
+                write_files(
+                    &*self.store,
+                    self.reference_files(&path_to_file, stored, &split)?,
+                    self.backup_on_overwrite,
+                )?;
+            }
+        }
+
+        if self.verbose || env_flag("EGRESS_VERBOSE") {
+            eprintln!("egress: {}", summary);
+        }
+
+        Ok(Report {
+            mismatches,
+            regressed: Vec::new(),
+            max_reported: self.max_reported_mismatches,
+            summary,
+            would_create: Vec::new(),
+            orphans: Vec::new(),
+            artifact_names,
+        })
+    }
+
+    /// Compare two reference files exactly as `close` compares a newly produced artifact against
+    /// its reference, with this context's tolerances and settings, e.g. to compare references
+    /// written on different machines. Both are read in this context's format, decompressed if
+    /// it writes compressed references, and their sidecars are read from next to them. Key paths
+    /// in the mismatches are relative to the artifact, e.g. `fruits[1]`.
+    pub fn diff_files<P, Q>(&self, reference: P, new: Q) -> Result<Vec<Mismatch>, ErrorKind>
+    where
+        P: AsRef<Path>,
+        Q: AsRef<Path>,
+    {
+        let load = |path: &Path| -> Result<Artifact, ErrorKind> {
+            let raw = self.deserialize_reference(&self.store.read(path)?)?;
+            let mut artifact = self.normalize(raw)?;
+            let dir = path.parent().unwrap_or_else(|| Path::new(""));
+            let empty = Artifact::new();
+            sidecar::resolve(&*self.store, &mut artifact, &empty, &empty, dir)?;
+            Ok(artifact)
+        };
+
+        let reference = load(reference.as_ref())?;
+        let new = load(new.as_ref())?;
+        Ok(self.compare_artifact(Path::new(""), &new, &reference))
+    }
+
+    /// Get a newly produced artifact ready to be compared and stored: normalize it, reduce it
+    /// to its hash if it's hash-only and add metadata. If `inline_threshold_bytes` is set, also
+    /// returns the artifact as it will be stored, with its big entries split out into sidecars.
+    fn prepare(
+        &self,
+        path: &Path,
+        artifact: Artifact,
+    ) -> Result<(Artifact, Option<sidecar::Split>), ErrorKind> {
+        let mut artifact = self.normalize(artifact)?;
+        if artifact.is_hash_only() {
+            artifact = artifact.hashed();
+        }
+        artifact.set_preserve_order(self.preserve_order);
+        if self.metadata {
+            artifact
+                .entries_mut()
+                .insert(metadata::KEY.to_owned(), metadata::entry());
+        }
+        let split = self.inline_threshold_bytes.map(|threshold| {
+            let stem = path.file_name().unwrap_or_default();
+            sidecar::split(&artifact, &stem.to_string_lossy(), threshold)
+        });
+        Ok((artifact, split))
+    }
+
+    /// The files to write to store `stored` at `path_to_file`: the reference itself, followed by
+    /// its sidecars.
+    fn reference_files(
+        &self,
+        path_to_file: &Path,
+        stored: &Artifact,
+        split: &Option<sidecar::Split>,
+    ) -> Result<Vec<(PathBuf, Vec<u8>)>, ErrorKind> {
+        let dir = path_to_file.parent().unwrap_or(&self.artifact_subdir);
+        let mut files = vec![(path_to_file.to_owned(), self.serialize_reference(stored)?)];
+        if let Some((_, sidecars)) = split {
+            files.extend(
+                sidecars
+                    .iter()
+                    .map(|sidecar| (dir.join(&sidecar.name), sidecar.contents.clone())),
+            );
+        }
+        Ok(files)
+    }
+
+    /// Add the keys of `stored` which its reference doesn't have to the reference on disk, as
+    /// well as the sidecars of any new entries.
+    fn merge_new_keys(
+        &self,
+        path_to_file: &Path,
+        mut reference: Artifact,
+        stored: &Artifact,
+        split: &Option<sidecar::Split>,
+    ) -> Result<(), ErrorKind> {
+        if !reference.merge_new_keys(stored) {
+            return Ok(());
+        }
+        reference.sort_json_keys();
+        reference.set_preserve_order(self.preserve_order);
+
+        let dir = path_to_file.parent().unwrap_or(&self.artifact_subdir);
+        let mut files = vec![(
+            path_to_file.to_owned(),
+            self.serialize_reference(&reference)?,
+        )];
+        if let Some((_, sidecars)) = split {
+            files.extend(
+                sidecars
+                    .iter()
+                    .map(|sidecar| (dir.join(&sidecar.name), sidecar.contents.clone()))
+                    .filter(|(path, _)| !self.store.exists(path)),
+            );
+        }
+        write_files(&*self.store, files, false)
+    }
+
+    /// Compare a normalized artifact against its normalized reference.
+    fn compare_artifact(
+        &self,
+        path: &Path,
+        artifact: &Artifact,
+        reference: &Artifact,
+    ) -> Vec<Mismatch> {
+        artifact.report_mismatches(artifact_key(path), reference, self.tolerance())
+    }
+
+    fn is_dry_run(&self) -> bool {
+        self.dry_run || env_flag("EGRESS_DRY_RUN")
+    }
+
+    fn tolerance(&self) -> Tolerance {
+        Tolerance {
+            atol: self.atol,
+            rtol: self.rtol,
+            mode: self.tolerance_mode,
+            strict_number_types: self.strict_number_types,
+            integer_atol: self.integer_atol,
+            ulps: self.ulps,
+            single_precision: false,
+        }
+    }
+
+    /// Bring an artifact into the form it is stored and compared in.
+    fn normalize(&self, mut artifact: Artifact) -> Result<Artifact, ErrorKind> {
+        artifact.apply_redactions();
+        if let Some(digits) = self.round_floats_to_digits {
+            artifact.round_floats(digits);
+        }
+        artifact.positive_zeros();
+        artifact.sort_json_keys();
+        if self.escape_control_chars {
+            artifact.escape_control_chars();
+        }
+
+        #[cfg(feature = "jcs")]
+        {
+            if self.jcs {
+                return jcs::canonicalize_artifact(&artifact);
+            }
+        }
+
+        Ok(artifact)
+    }
+
+    fn serialize_reference(&self, artifact: &Artifact) -> Result<Vec<u8>, ErrorKind> {
+        let bytes = self.encode_reference(artifact)?;
+
+        #[cfg(feature = "gzip")]
+        {
+            if self.compress {
+                let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+                encoder.write_all(&bytes)?;
+                return Ok(encoder.finish()?);
+            }
+        }
+
+        Ok(bytes)
+    }
+
+    fn deserialize_reference(&self, bytes: &[u8]) -> Result<Artifact, ErrorKind> {
+        decode_reference(self.format, self.compressed(), bytes)
+    }
+
+    fn compressed(&self) -> bool {
+        #[cfg(feature = "gzip")]
+        {
+            self.compress
+        }
+        #[cfg(not(feature = "gzip"))]
+        {
+            false
+        }
+    }
+
+    /// What the artifact `name` needs for `Artifact::insert_stream` to compare against its
+    /// reference as items are produced.
+    fn stream_context(&self, name: &Path) -> StreamContext {
+        let path_to_file = self.path_to_file(name);
+        let (format, compressed) = (self.format, self.compressed());
+        let store = self.store.clone();
+        StreamContext {
+            load_reference: Arc::new(move || {
+                if !store.exists(&path_to_file) {
+                    return Ok(None);
+                }
+                decode_reference(format, compressed, &store.read(&path_to_file)?).map(Some)
+            }),
+            tolerance: self.tolerance(),
+            round_floats_to_digits: self.round_floats_to_digits,
+            consume_all: self.capture || env_flag("EGRESS_RECORD") || env_flag("EGRESS_UPDATE"),
+        }
+    }
+
+    fn encode_reference(&self, artifact: &Artifact) -> Result<Vec<u8>, ErrorKind> {
+        #[cfg(feature = "jcs")]
+        {
+            if self.jcs && self.format == Format::Json {
+                let value = serde_json::to_value(artifact)?;
+                return Ok(jcs::canonicalize(&value).into_bytes());
             }
         }
 
-        Ok(Report { mismatches })
+        if self.format == Format::Json {
+            return Ok(if !self.json_pretty {
+                serde_json::to_vec(artifact)?
+            } else if let Some(indent) = self.indent {
+                let indent = vec![b' '; indent];
+                let formatter = serde_json::ser::PrettyFormatter::with_indent(&indent);
+                let mut bytes = Vec::new();
+                let mut serializer = serde_json::Serializer::with_formatter(&mut bytes, formatter);
+                artifact.serialize(&mut serializer)?;
+                bytes
+            } else {
+                serde_json::to_vec_pretty(artifact)?
+            });
+        }
+
+        self.format.serialize(artifact)
+    }
+
+    /// Close the testing context like `close`, and then delete any reference which no longer
+    /// corresponds to an artifact produced in this context, e.g. after a test was renamed or
+    /// removed.
+    ///
+    /// Only references in this context's format are considered, and only those directly in
+    /// `artifact_subdir` or in the same subdirectory as one of this context's nested artifact
+    /// names. That way, the references of other contexts nested inside this one's
+    /// `artifact_subdir` are left alone.
+    pub fn close_and_prune(self) -> Result<Report, ErrorKind> {
+        let orphans = self.orphaned_references()?;
+
+        let dry_run = self.is_dry_run();
+        let store = self.store.clone();
+        let report = self.close()?;
+        if dry_run {
+            return Ok(report);
+        }
+
+        for path in orphans {
+            store.remove(&path)?;
+        }
+
+        Ok(report)
+    }
+
+    /// Delete the references which don't belong to any of the artifacts named in `live`, like
+    /// `close_and_prune` does for the artifacts produced in a context, e.g. from a script which
+    /// knows which artifacts its tests write. Names take the same form they're passed to
+    /// `artifact` in, e.g. `fruits` or `nested/fruits`, and the same references are considered
+    /// as by `close_and_prune`. Returns the paths of the deleted references, sorted. In a dry
+    /// run, they're only returned.
+    pub fn prune_references<I, P>(&self, live: I) -> Result<Vec<PathBuf>, ErrorKind>
+    where
+        I: IntoIterator<Item = P>,
+        P: AsRef<Path>,
+    {
+        let live = live
+            .into_iter()
+            .map(|name| name.as_ref().to_owned())
+            .collect::<Vec<_>>();
+        let orphans = self.references_orphaned_by(live.iter().map(PathBuf::as_path))?;
+        if !self.is_dry_run() {
+            for path in &orphans {
+                self.store.remove(path)?;
+            }
+        }
+        Ok(orphans)
+    }
+
+    /// The references which don't correspond to any artifact produced so far, sorted.
+    fn orphaned_references(&self) -> Result<Vec<PathBuf>, ErrorKind> {
+        self.references_orphaned_by(self.artifacts.keys().map(PathBuf::as_path))
+    }
+
+    /// The references which don't correspond to any of the artifacts `names`, sorted. Only
+    /// references directly in `artifact_subdir`, or in the same subdirectory as one of the
+    /// nested names, are considered; see `close_and_prune`.
+    fn references_orphaned_by<'a>(
+        &self,
+        names: impl Iterator<Item = &'a Path>,
+    ) -> Result<Vec<PathBuf>, ErrorKind> {
+        let produced = names
+            .map(|path| self.path_to_file(path))
+            .collect::<HashSet<_>>();
+        let mut dirs = produced
+            .iter()
+            .filter_map(|path_to_file| path_to_file.parent())
+            .map(Path::to_path_buf)
+            .collect::<HashSet<_>>();
+        dirs.insert(self.artifact_subdir.clone());
+
+        let mut orphans = self
+            .reference_paths()?
+            .into_iter()
+            .filter(|path| path.parent().is_some_and(|dir| dirs.contains(dir)))
+            .filter(|path| !produced.contains(path))
+            .collect::<Vec<_>>();
+        orphans.sort();
+        Ok(orphans)
+    }
+
+    fn path_to_file(&self, path: &Path) -> PathBuf {
+        self.reference_file_in(&self.artifact_subdir, path)
+    }
+
+    /// The reference file for the artifact `name` in `dir`. The extension is appended to the
+    /// whole name, rather than replacing whatever follows its last dot.
+    fn reference_file_in(&self, dir: &Path, name: &Path) -> PathBuf {
+        let mut path_to_file = dir.join(name).into_os_string();
+        path_to_file.push(".");
+        path_to_file.push(self.extension());
+        PathBuf::from(path_to_file)
+    }
+
+    /// List the names of the references currently on disk in `artifact_subdir`, sorted, in the
+    /// same form they're passed to `artifact` in, e.g. `fruits` or `nested/fruits`. This reads
+    /// the filesystem rather than looking at the artifacts produced so far, so it's useful for
+    /// checking that no stray references have accumulated.
+    ///
+    /// Subdirectories are searched too, so the references of any other context nested inside
+    /// this one's `artifact_subdir` are listed as well.
+    pub fn existing_artifacts(&self) -> Result<Vec<PathBuf>, ErrorKind> {
+        let mut names = self
+            .reference_paths()?
+            .iter()
+            .filter_map(|path| self.reference_name(path))
+            .collect::<Vec<_>>();
+
+        names.sort();
+        Ok(names)
+    }
+
+    /// The name of the artifact a reference file in `artifact_subdir` belongs to, e.g.
+    /// `nested/fruits`.
+    fn reference_name(&self, path: &Path) -> Option<PathBuf> {
+        let stem = self.reference_stem(path)?;
+        let relative = path.strip_prefix(&self.artifact_subdir).ok()?;
+        Some(relative.with_file_name(stem))
+    }
+
+    /// Delete the sidecar files in `artifact_subdir` and its subdirectories which no reference
+    /// refers to any more, e.g. because an entry shrank below `inline_threshold_bytes` or its
+    /// reference was deleted, as well as any temporary files left behind by an interrupted write.
+    /// Returns the paths of the deleted files, sorted.
+    pub fn prune_sidecars(&self) -> Result<Vec<PathBuf>, ErrorKind> {
+        let references = self.reference_paths()?;
+        let mut referenced = HashSet::new();
+        for path_to_file in &references {
+            let reference = self.deserialize_reference(&self.store.read(path_to_file)?)?;
+            let dir = path_to_file.parent().unwrap_or(&self.artifact_subdir);
+            referenced.extend(
+                sidecar::names(&reference)
+                    .into_iter()
+                    .map(|name| dir.join(name)),
+            );
+        }
+
+        let mut pruned = Vec::new();
+        for path in self.store.list(&self.artifact_subdir)? {
+            let name = match path.file_name().and_then(|name| name.to_str()) {
+                Some(name) => name,
+                None => continue,
+            };
+            let is_temporary = name.starts_with('.') && name.ends_with(".tmp");
+            let is_sidecar = sidecar::is_sidecar_name(name) && !references.contains(&path);
+
+            if (is_temporary || is_sidecar) && !referenced.contains(&path) {
+                self.store.remove(&path)?;
+                pruned.push(path);
+            }
+        }
+
+        pruned.sort();
+        Ok(pruned)
+    }
+
+    /// The artifact name a file would be the reference for, going by its extension. This alone
+    /// doesn't tell references apart from sidecars written with the same extension; see
+    /// `reference_paths`.
+    fn reference_stem<'a>(&self, path: &'a Path) -> Option<&'a str> {
+        let suffix = format!(".{}", self.extension());
+        path.file_name()
+            .and_then(|name| name.to_str())
+            .and_then(|name| name.strip_suffix(&suffix))
+            .filter(|stem| !stem.is_empty())
+    }
+
+    /// The reference files in `artifact_subdir` and its subdirectories. Since artifact names may
+    /// contain dots, a file named like a sidecar, e.g. `fruits.list.json`, could be either. It's
+    /// only taken to be a reference if it can be read as one and no other reference refers to
+    /// it as a sidecar, which means reading the references whenever such a file turns up.
+    fn reference_paths(&self) -> Result<Vec<PathBuf>, ErrorKind> {
+        let (ambiguous, mut references): (Vec<_>, Vec<_>) = self
+            .store
+            .list(&self.artifact_subdir)?
+            .into_iter()
+            .filter(|path| self.reference_stem(path).is_some())
+            .partition(|path| {
+                path.file_name()
+                    .and_then(|name| name.to_str())
+                    .is_some_and(sidecar::is_sidecar_name)
+            });
+        if ambiguous.is_empty() {
+            return Ok(references);
+        }
+
+        let mut readable = HashSet::new();
+        let mut sidecars = HashSet::new();
+        for path in references.iter().chain(&ambiguous) {
+            if let Ok(reference) = self.deserialize_reference(&self.store.read(path)?) {
+                let dir = path.parent().unwrap_or(&self.artifact_subdir);
+                sidecars.extend(
+                    sidecar::names(&reference)
+                        .into_iter()
+                        .map(|name| dir.join(name)),
+                );
+                readable.insert(path.clone());
+            }
+        }
+
+        references.extend(
+            ambiguous
+                .into_iter()
+                .filter(|path| readable.contains(path) && !sidecars.contains(path)),
+        );
+        references.sort();
+        Ok(references)
+    }
+
+    fn extension(&self) -> String {
+        let extension = match &self.extension {
+            Some(extension) => extension.trim_start_matches('.'),
+            None => self.format.extension(),
+        };
+
+        #[cfg(feature = "gzip")]
+        {
+            if self.compress {
+                return format!("{}.gz", extension);
+            }
+        }
+
+        extension.to_owned()
     }
 
     /// Shorthand for `.close()?.assert_unregressed()?`.
@@ -241,6 +1619,131 @@ impl Egress {
     }
 }
 
+/// Releases the context's `artifact_subdir` for other contexts to use.
+impl Drop for Egress {
+    fn drop(&mut self) {
+        if let Some(subdir) = self.registered.take() {
+            registry::unregister(&subdir);
+        }
+    }
+}
+
+/// An artifact's name as the leading segment of its mismatches' key paths, and as it's listed
+/// in a `Report`: the name given to `Egress::artifact`, with the components of a nested name
+/// separated by `/` on every platform, e.g. `group/case1`.
+pub(crate) fn artifact_key(name: &Path) -> String {
+    name.components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Refuse to write the artifact `name` as a reference if `truncated`, i.e. it holds a stream
+/// which `Artifact::insert_stream` cut short, as it would lose the rest of the stream.
+fn check_complete(name: &Path, truncated: bool) -> Result<(), ErrorKind> {
+    if truncated {
+        return Err(ErrorKind::TruncatedStream(artifact_key(name)));
+    }
+    Ok(())
+}
+
+/// Write every file to `store`, first copying any which already exist to `<name>.bak` if
+/// `backup` is set. Failures are reported as `ErrorKind::ArtifactIo`, naming the file.
+fn write_files(
+    store: &dyn ArtifactStore,
+    files: Vec<(PathBuf, Vec<u8>)>,
+    backup: bool,
+) -> Result<(), ErrorKind> {
+    for (path, contents) in files {
+        let written = if backup {
+            back_up(store, &path).and_then(|()| store.write(&path, &contents))
+        } else {
+            store.write(&path, &contents)
+        };
+        written.map_err(|source| ErrorKind::ArtifactIo { path, source })?;
+    }
+    Ok(())
+}
+
+/// Create a directory in `store` to hold references, along with any missing parents, naming it
+/// in the `ErrorKind::ArtifactIo` if that fails.
+fn create_artifact_dir(store: &dyn ArtifactStore, dir: &Path) -> Result<(), ErrorKind> {
+    store
+        .create_dir_all(dir)
+        .map_err(|source| ErrorKind::ArtifactIo {
+            path: dir.to_owned(),
+            source,
+        })
+}
+
+/// Copy a file about to be overwritten to `<name>.bak`, replacing any older backup. Does
+/// nothing if the file doesn't exist yet.
+fn back_up(store: &dyn ArtifactStore, path: &Path) -> std::io::Result<()> {
+    let contents = match store.read(path) {
+        Ok(contents) => contents,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(err) => return Err(err),
+    };
+
+    let mut backup = path.as_os_str().to_owned();
+    backup.push(".bak");
+    store.write(Path::new(&backup), &contents)
+}
+
+/// Check that a tolerance, called `name` in the error, is a finite, non-negative number if it's
+/// set at all.
+fn validate_tolerance(name: &str, tolerance: Option<f64>) -> Result<(), ErrorKind> {
+    match tolerance {
+        Some(tolerance) if !tolerance.is_finite() || tolerance < 0.0 => {
+            Err(ErrorKind::InvalidConfig(
+                name.to_owned(),
+                format!("must be a finite, non-negative number, not {}", tolerance),
+            ))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Parse a numeric environment variable like `EGRESS_ATOL`, if it's set.
+fn env_float(name: &str) -> Result<Option<f64>, ErrorKind> {
+    let value = match std::env::var(name) {
+        Ok(value) => value,
+        Err(std::env::VarError::NotPresent) => return Ok(None),
+        Err(std::env::VarError::NotUnicode(value)) => {
+            return Err(ErrorKind::InvalidEnvVar(
+                name.to_owned(),
+                value.to_string_lossy().into_owned(),
+            ))
+        }
+    };
+
+    match value.trim().parse() {
+        Ok(f) => Ok(Some(f)),
+        Err(_) => Err(ErrorKind::InvalidEnvVar(name.to_owned(), value)),
+    }
+}
+
+/// Decode the bytes of a reference file, decompressing them first if `compressed`.
+fn decode_reference(format: Format, compressed: bool, bytes: &[u8]) -> Result<Artifact, ErrorKind> {
+    #[cfg(feature = "gzip")]
+    {
+        if compressed {
+            let mut decompressed = Vec::new();
+            GzDecoder::new(bytes).read_to_end(&mut decompressed)?;
+            return format.deserialize(&decompressed);
+        }
+    }
+    #[cfg(not(feature = "gzip"))]
+    let _ = compressed;
+
+    format.deserialize(bytes)
+}
+
+/// Whether a boolean environment variable like `EGRESS_UPDATE` is switched on.
+fn env_flag(name: &str) -> bool {
+    std::env::var_os(name).is_some_and(|value| value == "1")
+}
+
 /// Shorthand macro for opening an Egress context, keyed by the `module_path!()`
 /// of the file it's called in.
 ///
@@ -262,10 +1765,44 @@ macro_rules! egress {
     }};
 }
 
+/// Create an artifact in an Egress context, named after the function the macro is called in,
+/// so that the name can't drift out of sync with the test's. Panics like `Egress::artifact` if
+/// the name is already taken, e.g. by a second call in the same function.
+///
+/// ```no_run
+/// # use egress::{artifact, egress};
+/// #[test]
+/// fn fruit_basket() {
+///     let mut egress = egress!();
+///     // Stored in `fruit_basket.json`.
+///     artifact!(egress).insert_display("fruit", &"apple");
+///     egress.close().unwrap().assert_unregressed();
+/// }
+/// ```
+#[macro_export]
+macro_rules! artifact {
+    ($egress:expr) => {{
+        fn here() {}
+        let name = $crate::function_name(::std::any::type_name_of_val(&here));
+        $egress.artifact(name)
+    }};
+}
+
+/// The name of the function a nested `fn here()` was declared in, given its type name, e.g.
+/// `fruit_basket` for `tests::fruit_basket::here`. Closures along the way are skipped, and so
+/// is the body `#[egress_test]` wraps in a function of the test's name.
+#[doc(hidden)]
+pub fn function_name(here: &'static str) -> &'static str {
+    let mut path = here.strip_suffix("::here").unwrap_or(here);
+    while let Some(outer) = path.strip_suffix("::{{closure}}") {
+        path = outer;
+    }
+    path = path.strip_suffix("::__egress_test_body").unwrap_or(path);
+    path.rsplit("::").next().unwrap_or(path)
+}
+
 #[cfg(test)]
 mod test {
-    use super::*;
-
     #[test]
     fn open() {
         let _ = egress!();
@@ -0,0 +1,27 @@
+//! Provenance metadata written into references when the `metadata` config option is on, so
+//! that reviewers can tell when and by what a reference was generated. Metadata is stored as an
+//! ordinary entry under a reserved key, which is skipped when comparing.
+
+use ::{
+    serde_json::json,
+    std::time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::Entry;
+
+/// The key metadata is stored under at the top level of a reference.
+pub(crate) const KEY: &str = "$metadata";
+
+/// Describe the current run: the version of the crate under test, if it's being run by
+/// Cargo, the version of Egress, and the time in seconds since the Unix epoch.
+pub(crate) fn entry() -> Entry {
+    let generated_at = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs());
+
+    Entry::Json(json!({
+        "crate_version": std::env::var("CARGO_PKG_VERSION").ok(),
+        "egress_version": env!("CARGO_PKG_VERSION"),
+        "generated_at": generated_at,
+    }))
+}
@@ -0,0 +1,47 @@
+//! A process-wide registry of the artifact subdirectories live `Egress` contexts are using, so
+//! that two contexts which would read and write the same references are caught rather than
+//! silently clobbering each other's output.
+
+use std::{
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use crate::ErrorKind;
+
+static LIVE: Mutex<Vec<PathBuf>> = Mutex::new(Vec::new());
+
+/// Claim `artifact_subdir` for a context, returning the absolute path it was registered under.
+/// Fails with `ErrorKind::ArtifactSubdirInUse` if a live context's subdirectory is the same.
+/// One inside the other is fine, since `egress!()` in a test module sits right above the
+/// subdirectories `#[egress_test]` gives each of the module's tests.
+pub(crate) fn register(artifact_subdir: &Path) -> Result<PathBuf, ErrorKind> {
+    let subdir = absolute(artifact_subdir)?;
+
+    let mut live = LIVE.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    if live.contains(&subdir) {
+        return Err(ErrorKind::ArtifactSubdirInUse(artifact_subdir.to_owned()));
+    }
+
+    live.push(subdir.clone());
+    Ok(subdir)
+}
+
+/// Release a subdirectory claimed with `register`.
+pub(crate) fn unregister(subdir: &Path) {
+    let mut live = LIVE.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    if let Some(i) = live.iter().position(|other| other == subdir) {
+        live.swap_remove(i);
+    }
+}
+
+/// Make a path absolute without touching the filesystem, since the directory may not exist yet.
+/// `Path::components` already drops any `.` components past the first.
+fn absolute(path: &Path) -> std::io::Result<PathBuf> {
+    let path = if path.is_absolute() {
+        path.to_owned()
+    } else {
+        std::env::current_dir()?.join(path)
+    };
+    Ok(path.components().collect())
+}
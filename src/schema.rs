@@ -0,0 +1,86 @@
+//! Validating artifacts against a [JSON Schema], for checking an artifact's structure even when
+//! its reference changes too often for the diff to be useful.
+//!
+//! [JSON Schema]: https://json-schema.org/
+
+use ::{
+    jsonschema::paths::{Location, LocationSegment},
+    serde_json::{Map, Value},
+};
+
+use crate::{Artifact, Entry};
+
+/// Validate the JSON view of `artifact` against `schema`, returning a description of every
+/// violation, prefixed with its key path where it isn't the whole artifact.
+pub(crate) fn validate(artifact: &Artifact, schema: &Value) -> Result<(), Vec<String>> {
+    let validator = jsonschema::validator_for(schema)
+        .map_err(|err| vec![format!("invalid schema: {}", err)])?;
+
+    let instance = to_json(artifact);
+    let errors = validator
+        .iter_errors(&instance)
+        .map(|err| match key_path(&instance, err.instance_path()) {
+            Some(path) => format!("{}: {}", path, err),
+            None => err.to_string(),
+        })
+        .collect::<Vec<_>>();
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
+}
+
+/// View an artifact as a JSON object of its string, JSON, null and nested artifact entries.
+/// Byte and sidecar entries have no meaningful JSON form, so they're left out.
+fn to_json(artifact: &Artifact) -> Value {
+    let mut map = Map::new();
+    for (k, entry) in artifact.entries() {
+        let value = match entry {
+            Entry::Str(s) => Value::String(s.clone()),
+            Entry::Json(value) => value.clone(),
+            Entry::Artifact(art) => to_json(art),
+            Entry::Null => Value::Null,
+            Entry::Bytes(_) | Entry::Sidecar { .. } => continue,
+        };
+        map.insert(k.clone(), value);
+    }
+    Value::Object(map)
+}
+
+/// Turn a JSON pointer into `instance` into a key path like `key.field[0]`, or `None` for the
+/// root.
+fn key_path(instance: &Value, location: &Location) -> Option<String> {
+    let mut path = String::new();
+    let mut value = Some(instance);
+
+    for segment in location.segments() {
+        let key = match segment {
+            LocationSegment::Property(key) => key.into_owned(),
+            LocationSegment::Index(i) => i.to_string(),
+        };
+
+        match value {
+            // Numeric keys of objects look just like array indices in a JSON pointer.
+            Some(Value::Array(array)) => {
+                let i = key.parse::<usize>().unwrap_or(usize::MAX);
+                path.push_str(&format!("[{}]", i));
+                value = array.get(i);
+            }
+            _ => {
+                if !path.is_empty() {
+                    path.push('.');
+                }
+                path.push_str(&key);
+                value = value.and_then(|value| value.get(&key));
+            }
+        }
+    }
+
+    if path.is_empty() {
+        None
+    } else {
+        Some(path)
+    }
+}
@@ -0,0 +1,166 @@
+//! Sidecar files, for entries too big to comfortably store inline in a reference. A sidecar
+//! holds a single entry's contents: raw bytes in a `.bin` file, a string in a `.txt` file or
+//! pretty-printed JSON in a `.json` file. The reference itself only keeps an `Entry::Sidecar`
+//! with the sidecar's name and the hash of its contents, so comparing against an unchanged
+//! sidecar never has to read it.
+
+use ::{
+    sha2::{Digest, Sha256},
+    std::path::Path,
+};
+
+use crate::{Artifact, ArtifactStore, Entry, ErrorKind};
+
+/// A sidecar file to be written next to a reference.
+pub(crate) struct Sidecar {
+    pub name: String,
+    pub contents: Vec<u8>,
+}
+
+/// An artifact as it's stored, with its big entries replaced by `Entry::Sidecar`s, along with
+/// the sidecars to write next to it.
+pub(crate) type Split = (Artifact, Vec<Sidecar>);
+
+/// Move every entry of `artifact`, or of its nested artifacts, which is bigger than
+/// `threshold` bytes out into a sidecar. Sidecars are named after the artifact, `stem`, and
+/// the entry's key path, e.g. `stem.nested.key.bin`, with each part escaped by `escape_name`.
+pub(crate) fn split(artifact: &Artifact, stem: &str, threshold: usize) -> Split {
+    let mut stored = artifact.clone();
+    let mut sidecars = Vec::new();
+    split_entries(&mut stored, &escape_name(stem), threshold, &mut sidecars);
+    (stored, sidecars)
+}
+
+/// Escape one part of a sidecar name, so that dots only ever separate the parts and no two
+/// key paths share a name: a key `a.b` becomes `a%2Eb`, unlike the key `b` nested under `a`.
+/// Path separators are escaped too, keeping the name to a single, portable path component.
+fn escape_name(part: &str) -> String {
+    let mut escaped = String::with_capacity(part.len());
+    for c in part.chars() {
+        match c {
+            '%' => escaped.push_str("%25"),
+            '.' => escaped.push_str("%2E"),
+            '/' => escaped.push_str("%2F"),
+            '\\' => escaped.push_str("%5C"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn split_entries(artifact: &mut Artifact, prefix: &str, threshold: usize, out: &mut Vec<Sidecar>) {
+    for (k, entry) in artifact.entries_mut().iter_mut() {
+        let prefix = format!("{}.{}", prefix, escape_name(k));
+
+        let (contents, extension) = match entry {
+            Entry::Artifact(nested) => {
+                split_entries(nested, &prefix, threshold, out);
+                continue;
+            }
+            Entry::Bytes(bytes) => (bytes.clone(), "bin"),
+            Entry::Str(s) => (s.clone().into_bytes(), "txt"),
+            Entry::Json(value) => (
+                serde_json::to_vec_pretty(value).expect("JSON values always serialize"),
+                "json",
+            ),
+            Entry::Sidecar { .. } | Entry::Null => continue,
+        };
+
+        if contents.len() > threshold {
+            let name = format!("{}.{}", prefix, extension);
+            *entry = Entry::Sidecar {
+                path: name.clone(),
+                hash: hash(&contents),
+            };
+            out.push(Sidecar { name, contents });
+        }
+    }
+}
+
+/// The names of the sidecar files `reference` and its nested artifacts refer to.
+pub(crate) fn names(reference: &Artifact) -> Vec<String> {
+    let mut names = Vec::new();
+    for entry in reference.entries().values() {
+        match entry {
+            Entry::Sidecar { path, .. } => names.extend(
+                Path::new(path)
+                    .file_name()
+                    .map(|name| name.to_string_lossy().into_owned()),
+            ),
+            Entry::Artifact(nested) => names.extend(self::names(nested)),
+            Entry::Str(_) | Entry::Json(_) | Entry::Bytes(_) | Entry::Null => {}
+        }
+    }
+    names
+}
+
+/// Whether a file name looks like it belongs to a sidecar: the artifact's name, a key path,
+/// and one of the extensions sidecars are written with.
+pub(crate) fn is_sidecar_name(name: &str) -> bool {
+    let mut parts = name.split('.');
+    let stem = parts.next().unwrap_or_default();
+    let rest = parts.collect::<Vec<_>>();
+    !stem.is_empty()
+        && rest.len() >= 2
+        && matches!(rest.last(), Some(&"bin") | Some(&"txt") | Some(&"json"))
+}
+
+/// The hex SHA-256 hash of `contents`.
+pub(crate) fn hash(contents: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(contents))
+}
+
+/// Replace the sidecar entries of a `reference` read from `dir` in `store` with the entries they stand
+/// for, so that it can be compared against the `full` newly produced artifact. Sidecars with
+/// the same hash as the corresponding sidecar in the new artifact's `stored` form are taken to
+/// be unchanged, and aren't read at all.
+pub(crate) fn resolve(
+    store: &dyn ArtifactStore,
+    reference: &mut Artifact,
+    stored: &Artifact,
+    full: &Artifact,
+    dir: &Path,
+) -> Result<(), ErrorKind> {
+    for (k, entry) in reference.entries_mut().iter_mut() {
+        match entry {
+            Entry::Sidecar { path, hash } => {
+                let unchanged = match (stored.entries().get(k), full.entries().get(k)) {
+                    (Some(Entry::Sidecar { hash: new_hash, .. }), Some(new))
+                        if new_hash == hash =>
+                    {
+                        Some(new.clone())
+                    }
+                    _ => None,
+                };
+                *entry = match unchanged {
+                    Some(new) => new,
+                    // Sidecars always sit right next to their reference.
+                    None => load(
+                        store,
+                        &dir.join(Path::new(path).file_name().unwrap_or_default()),
+                    )?,
+                };
+            }
+            Entry::Artifact(nested) => {
+                let empty = Artifact::new();
+                let (stored, full) = match (stored.entries().get(k), full.entries().get(k)) {
+                    (Some(Entry::Artifact(stored)), Some(Entry::Artifact(full))) => (stored, full),
+                    _ => (&empty, &empty),
+                };
+                resolve(store, nested, stored, full, dir)?;
+            }
+            Entry::Str(_) | Entry::Json(_) | Entry::Bytes(_) | Entry::Null => {}
+        }
+    }
+
+    Ok(())
+}
+
+fn load(store: &dyn ArtifactStore, path: &Path) -> Result<Entry, ErrorKind> {
+    let contents = store.read(path)?;
+    Ok(match path.extension().and_then(|ext| ext.to_str()) {
+        Some("txt") => Entry::Str(String::from_utf8_lossy(&contents).into_owned()),
+        Some("json") => Entry::Json(serde_json::from_slice(&contents)?),
+        _ => Entry::Bytes(contents),
+    })
+}
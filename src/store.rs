@@ -0,0 +1,220 @@
+//! Where references and their sidecar files are kept. Every `Egress` context reads and writes
+//! them through an `ArtifactStore`, which is the filesystem unless the context was built with
+//! another one, like a `MemoryStore` for tests which shouldn't touch the disk.
+
+use ::{
+    fs2::FileExt,
+    std::{
+        collections::BTreeMap,
+        fmt,
+        fs::{self, File, OpenOptions},
+        io::{self, Read, Write},
+        path::{Path, PathBuf},
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc, Mutex,
+        },
+    },
+};
+
+/// Storage for the files of an `Egress` context: references, their sidecars and backups. Paths
+/// are the ones the files would have on disk, inside the context's `artifact_subdir`, so a store
+/// which isn't backed by the filesystem can simply use them as keys.
+pub trait ArtifactStore: fmt::Debug + Send + Sync {
+    /// Read the whole contents of a file. A file which doesn't exist is an error of kind
+    /// `io::ErrorKind::NotFound`.
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>>;
+
+    /// Write a file, replacing its previous contents if it already exists. Its directory has
+    /// already been made with `create_dir_all`.
+    fn write(&self, path: &Path, contents: &[u8]) -> io::Result<()>;
+
+    /// List every file in `dir` and its subdirectories, in any order, or none if `dir` doesn't
+    /// exist.
+    fn list(&self, dir: &Path) -> io::Result<Vec<PathBuf>>;
+
+    /// Whether a file exists.
+    fn exists(&self, path: &Path) -> bool;
+
+    /// Delete a file.
+    fn remove(&self, path: &Path) -> io::Result<()>;
+
+    /// Make a directory for files to be written into, along with any missing parents. Stores
+    /// without directories don't need to do anything, which is the default.
+    fn create_dir_all(&self, dir: &Path) -> io::Result<()> {
+        let _ = dir;
+        Ok(())
+    }
+}
+
+/// The default store, which keeps files on disk. Files are locked while they're read or
+/// written, so that tests running concurrently never see one half written.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FsStore;
+
+impl ArtifactStore for FsStore {
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        read_file(path)
+    }
+
+    fn write(&self, path: &Path, contents: &[u8]) -> io::Result<()> {
+        write_file(path, contents)
+    }
+
+    fn list(&self, dir: &Path) -> io::Result<Vec<PathBuf>> {
+        files_under(dir)
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        path.is_file()
+    }
+
+    fn remove(&self, path: &Path) -> io::Result<()> {
+        fs::remove_file(path)
+    }
+
+    fn create_dir_all(&self, dir: &Path) -> io::Result<()> {
+        fs::create_dir_all(dir)
+    }
+}
+
+/// A store which keeps files in memory, for testing code which uses Egress without touching the
+/// disk. Clones share the same files, so a context built with a clone of a store sees the
+/// references written by a context built with the original.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryStore {
+    files: Arc<Mutex<BTreeMap<PathBuf, Vec<u8>>>>,
+}
+
+impl MemoryStore {
+    /// Create an empty store.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn files(&self) -> std::sync::MutexGuard<'_, BTreeMap<PathBuf, Vec<u8>>> {
+        self.files
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl ArtifactStore for MemoryStore {
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        self.files()
+            .get(path)
+            .cloned()
+            .ok_or_else(|| not_found(path))
+    }
+
+    fn write(&self, path: &Path, contents: &[u8]) -> io::Result<()> {
+        self.files().insert(path.to_owned(), contents.to_vec());
+        Ok(())
+    }
+
+    fn list(&self, dir: &Path) -> io::Result<Vec<PathBuf>> {
+        Ok(self
+            .files()
+            .keys()
+            .filter(|path| path.starts_with(dir))
+            .cloned()
+            .collect())
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        self.files().contains_key(path)
+    }
+
+    fn remove(&self, path: &Path) -> io::Result<()> {
+        match self.files().remove(path) {
+            Some(_) => Ok(()),
+            None => Err(not_found(path)),
+        }
+    }
+}
+
+fn not_found(path: &Path) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::NotFound,
+        format!("no file at `{}`", path.display()),
+    )
+}
+
+/// Write a reference file while holding an exclusive lock on it, so that another test reading
+/// or writing the same file concurrently never sees it half written. The lock is released as
+/// soon as the file is written, so contexts in the same process can't deadlock on each other.
+pub(crate) fn write_file(path: &Path, contents: &[u8]) -> io::Result<()> {
+    loop {
+        match OpenOptions::new().write(true).open(path) {
+            Ok(mut file) => {
+                file.lock_exclusive()?;
+                let result = file.set_len(0).and_then(|()| file.write_all(contents));
+                file.unlock()?;
+                return result;
+            }
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                if create_new_file(path, contents)? {
+                    return Ok(());
+                }
+            }
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+/// Create a file with the given contents if it doesn't exist yet, returning whether it was
+/// created. The contents are written to a temporary file first and then linked into place, so
+/// the file never exists without them, not even before its first lock could be taken.
+pub(crate) fn create_new_file(path: &Path, contents: &[u8]) -> io::Result<bool> {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+    let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+    let temp = path.with_file_name(format!(
+        ".{}.{}-{}.tmp",
+        file_name,
+        std::process::id(),
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+    fs::write(&temp, contents)?;
+    let linked = fs::hard_link(&temp, path);
+    fs::remove_file(&temp)?;
+
+    match linked {
+        Ok(()) => Ok(true),
+        Err(err) if err.kind() == io::ErrorKind::AlreadyExists => Ok(false),
+        Err(err) => Err(err),
+    }
+}
+
+/// Read a reference file while holding a shared lock on it; see `write_file`.
+pub(crate) fn read_file(path: &Path) -> io::Result<Vec<u8>> {
+    let mut file = File::open(path)?;
+    file.lock_shared()?;
+    let mut contents = Vec::new();
+    let result = file.read_to_end(&mut contents);
+    file.unlock()?;
+    result.map(|_| contents)
+}
+
+/// Every file in `dir` and its subdirectories, or none if `dir` doesn't exist.
+fn files_under(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    let mut dirs = vec![dir.to_owned()];
+
+    while let Some(dir) = dirs.pop() {
+        if !dir.is_dir() {
+            continue;
+        }
+
+        for dir_entry in fs::read_dir(dir)? {
+            let path = dir_entry?.path();
+            if path.is_dir() {
+                dirs.push(path);
+            } else {
+                files.push(path);
+            }
+        }
+    }
+
+    Ok(files)
+}
@@ -0,0 +1,132 @@
+use {
+    egress::{Artifact, Egress, Entry, Mismatch},
+    serde_json::json,
+};
+
+#[test]
+fn inserted_element() {
+    let dir = tempfile::tempdir().unwrap();
+
+    let mut egress = Egress::open(dir.path(), "arrays").unwrap();
+    egress
+        .artifact("log")
+        .insert_json("lines", json!(["a", "b", "c"]));
+    egress.close().unwrap().assert_unregressed();
+
+    let mut egress = Egress::open(dir.path(), "arrays").unwrap();
+    egress
+        .artifact("log")
+        .insert_json("lines", json!(["a", "x", "b", "c"]));
+    let report = egress.close().unwrap();
+
+    assert_eq!(
+        report.mismatches(),
+        &[
+            Mismatch::LengthMismatch(
+                "log.lines".to_owned(),
+                4,
+                3,
+                Entry::Json(json!(["a", "x", "b", "c"])),
+                Entry::Json(json!(["a", "b", "c"])),
+            ),
+            Mismatch::NotInReference("log.lines[1]".to_owned(), Entry::Json(json!("x"))),
+        ]
+    );
+}
+
+#[test]
+fn removed_and_changed_elements() {
+    let dir = tempfile::tempdir().unwrap();
+
+    let mut egress = Egress::open(dir.path(), "arrays").unwrap();
+    egress
+        .artifact("log")
+        .insert_json("lines", json!(["a", "b", "c", "d"]));
+    egress.close().unwrap().assert_unregressed();
+
+    let mut egress = Egress::open(dir.path(), "arrays").unwrap();
+    egress
+        .artifact("log")
+        .insert_json("lines", json!(["a", "c", "e"]));
+    let report = egress.close().unwrap();
+
+    assert_eq!(report.len(), 3);
+    report.assert_mismatch("log.lines");
+    assert!(report.mismatches().contains(&Mismatch::NotProduced(
+        "log.lines[1]".to_owned(),
+        Entry::Json(json!("b"))
+    )));
+    assert!(report.mismatches().contains(&Mismatch::NotEq(
+        "log.lines[2]".to_owned(),
+        Entry::Json(json!("e")),
+        Entry::Json(json!("d")),
+    )));
+}
+
+fn reordered(unordered: bool) -> Vec<Mismatch> {
+    let dir = tempfile::tempdir().unwrap();
+
+    let mut egress = Egress::open(dir.path(), "arrays").unwrap();
+    egress
+        .artifact("files")
+        .insert_json("found", json!(["a.rs", "b.rs", "c.rs", "c.rs"]));
+    egress.close().unwrap().assert_unregressed();
+
+    let mut egress = Egress::open(dir.path(), "arrays").unwrap();
+    let artifact = egress.artifact("files");
+    artifact.insert_json("found", json!(["c.rs", "a.rs", "c.rs", "b.rs"]));
+    if unordered {
+        artifact.unordered("found");
+    }
+    egress.close().unwrap().mismatches().to_vec()
+}
+
+#[test]
+fn unordered() {
+    assert_ne!(reordered(false), vec![]);
+    assert_eq!(reordered(true), vec![]);
+}
+
+#[test]
+fn unordered_reports_missing_elements() {
+    let dir = tempfile::tempdir().unwrap();
+
+    let mut egress = Egress::open(dir.path(), "arrays").unwrap();
+    egress
+        .artifact("files")
+        .insert_json("found", json!(["a.rs", "b.rs", "b.rs"]));
+    egress.close().unwrap().assert_unregressed();
+
+    let mut egress = Egress::open(dir.path(), "arrays").unwrap();
+    let artifact = egress.artifact("files");
+    artifact.insert_json("found", json!(["c.rs", "b.rs", "a.rs"]));
+    artifact.unordered("found");
+    let report = egress.close().unwrap();
+
+    assert_eq!(
+        report.mismatches(),
+        &[
+            Mismatch::NotInReference("files.found[0]".to_owned(), Entry::Json(json!("c.rs"))),
+            Mismatch::NotProduced("files.found[2]".to_owned(), Entry::Json(json!("b.rs"))),
+        ]
+    );
+}
+
+#[test]
+fn unordered_in_nested_artifact() {
+    let nested = |found: serde_json::Value| {
+        let mut inner = Artifact::new();
+        inner.insert_json("found", found);
+        let mut outer = Artifact::new();
+        outer.insert("nested", Entry::Artifact(inner));
+        outer
+    };
+
+    let reference = nested(json!(["a.rs", "b.rs", "c.rs"]));
+    let mut reordered = nested(json!(["c.rs", "a.rs", "b.rs"]));
+    assert!(!reordered.diff(&reference, None, None).is_empty());
+
+    reordered.unordered("nested.found");
+    let mismatches = reordered.diff(&reference, None, None);
+    assert!(mismatches.is_empty(), "{:?}", mismatches);
+}
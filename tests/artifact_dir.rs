@@ -0,0 +1,17 @@
+use egress::Egress;
+
+#[test]
+fn artifact_dir_override() {
+    let dir = tempfile::tempdir().unwrap();
+    let overridden = tempfile::tempdir().unwrap();
+
+    std::env::set_var("EGRESS_ARTIFACT_DIR", overridden.path());
+    let mut egress = Egress::open(dir.path(), "override").unwrap();
+    std::env::remove_var("EGRESS_ARTIFACT_DIR");
+
+    egress.artifact("fruits").insert_display("fruit", &"apple");
+    egress.close().unwrap().assert_unregressed();
+
+    assert!(overridden.path().join("override/fruits.json").exists());
+    assert!(!dir.path().join("egress/artifacts/override").exists());
+}
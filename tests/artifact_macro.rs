@@ -0,0 +1,40 @@
+use egress::{artifact, Egress};
+
+#[test]
+fn named_after_the_test() {
+    let dir = tempfile::tempdir().unwrap();
+
+    let mut egress = Egress::open(dir.path(), "artifact_macro").unwrap();
+    artifact!(egress).insert_display("fruit", &"apple");
+    egress.close().unwrap().assert_unregressed();
+
+    assert!(dir
+        .path()
+        .join("egress/artifacts/artifact_macro/named_after_the_test.json")
+        .exists());
+}
+
+#[test]
+fn named_after_the_function_around_a_closure() {
+    let dir = tempfile::tempdir().unwrap();
+
+    let mut egress = Egress::open(dir.path(), "artifact_macro").unwrap();
+    let fill = |egress: &mut Egress| {
+        artifact!(egress).insert_display("fruit", &"apple");
+    };
+    fill(&mut egress);
+    egress.close().unwrap().assert_unregressed();
+
+    assert!(dir
+        .path()
+        .join("egress/artifacts/artifact_macro/named_after_the_function_around_a_closure.json")
+        .exists());
+}
+
+#[test]
+fn named_after_an_egress_test() {
+    assert_eq!(
+        egress::function_name("egress_test::with_egress::__egress_test_body::here"),
+        "with_egress"
+    );
+}
@@ -0,0 +1,29 @@
+use {egress::Egress, std::fs};
+
+#[test]
+fn blessing_backs_up_the_previous_reference() {
+    let dir = tempfile::tempdir().unwrap();
+    let reference = dir.path().join("egress/artifacts/backup/fruits.json");
+    let backup = dir.path().join("egress/artifacts/backup/fruits.json.bak");
+
+    let mut egress = Egress::open(dir.path(), "backup").unwrap();
+    egress.backup_on_overwrite = true;
+    egress.artifact("fruits").insert_display("fruit", &"apple");
+    egress.close().unwrap().assert_unregressed();
+    assert!(!backup.exists());
+
+    let previous = fs::read_to_string(&reference).unwrap();
+    let mut egress = Egress::open(dir.path(), "backup").unwrap();
+    egress.backup_on_overwrite = true;
+    egress.artifact("fruits").insert_display("fruit", &"pear");
+    egress.close().unwrap().bless().unwrap();
+
+    assert_eq!(fs::read_to_string(&backup).unwrap(), previous);
+    assert!(fs::read_to_string(&reference).unwrap().contains("pear"));
+
+    // Without the option, nothing is backed up.
+    let mut egress = Egress::open(dir.path(), "backup").unwrap();
+    egress.artifact("fruits").insert_display("fruit", &"plum");
+    egress.close().unwrap().bless().unwrap();
+    assert_eq!(fs::read_to_string(&backup).unwrap(), previous);
+}
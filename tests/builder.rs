@@ -0,0 +1,38 @@
+use {
+    egress::{Egress, ErrorKind},
+    serde_json::json,
+};
+
+#[test]
+fn round_trip() {
+    let dir = tempfile::tempdir().unwrap();
+    let build = || {
+        Egress::builder()
+            .artifact_dir(dir.path().join("references"))
+            .artifact_subdir("builder")
+            .atol(Some(0.1))
+            .rtol(None)
+            .build()
+            .unwrap()
+    };
+
+    let mut egress = build();
+    egress.artifact("float").insert_json("x", json!(1.0));
+    egress.close().unwrap().assert_unregressed();
+
+    let mut egress = build();
+    egress.artifact("float").insert_json("x", json!(1.05));
+    egress.close().unwrap().assert_unregressed();
+
+    assert!(dir.path().join("references/builder/float.json").exists());
+    assert!(!dir.path().join("Egress.toml").exists());
+}
+
+#[test]
+fn invalid_tolerances() {
+    let negative = Egress::builder().atol(Some(-0.1)).build();
+    assert!(matches!(negative, Err(ErrorKind::InvalidConfig(name, _)) if name == "atol"));
+
+    let nan = Egress::builder().rtol(Some(f64::NAN)).build();
+    assert!(matches!(nan, Err(ErrorKind::InvalidConfig(name, _)) if name == "rtol"));
+}
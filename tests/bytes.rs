@@ -0,0 +1,52 @@
+use egress::Egress;
+
+#[test]
+fn round_trip() {
+    let dir = tempfile::tempdir().unwrap();
+    // Deliberately not valid UTF-8.
+    let bytes = [0xde, 0xad, 0xbe, 0xef, 0xff, 0x00, 0xc3, 0x28];
+
+    for _ in 0..2 {
+        let mut egress = Egress::open(dir.path(), "bytes").unwrap();
+        egress.artifact("round_trip").insert_bytes("bytes", &bytes);
+        egress.close().unwrap().assert_unregressed();
+    }
+
+    assert!(dir
+        .path()
+        .join("egress/artifacts/bytes/round_trip.json")
+        .exists());
+}
+
+#[test]
+fn stored_compactly() {
+    let dir = tempfile::tempdir().unwrap();
+    let bytes = (0..1024).map(|i| (i * 7) as u8).collect::<Vec<_>>();
+
+    for _ in 0..2 {
+        let mut egress = Egress::open(dir.path(), "bytes").unwrap();
+        egress.artifact("compact").insert_bytes("bytes", &bytes);
+        egress.close().unwrap().assert_unregressed();
+    }
+
+    let path = dir.path().join("egress/artifacts/bytes/compact.json");
+    let stored = std::fs::read_to_string(path).unwrap();
+    // 1 KiB of base64 is 1368 characters, plus a little bit of JSON around it.
+    assert!(stored.len() < 1500, "{}", stored);
+}
+
+#[test]
+fn legacy_number_arrays() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("egress/artifacts/bytes");
+    std::fs::create_dir_all(&path).unwrap();
+    std::fs::write(
+        path.join("legacy.json"),
+        r#"{ "bytes": { "Bytes": [1, 2, 3] } }"#,
+    )
+    .unwrap();
+
+    let mut egress = Egress::open(dir.path(), "bytes").unwrap();
+    egress.artifact("legacy").insert_bytes("bytes", &[1, 2, 3]);
+    egress.close().unwrap().assert_unregressed();
+}
@@ -0,0 +1,23 @@
+use {egress::Egress, std::fs};
+
+#[test]
+fn capture_mode_overwrites_without_comparing() {
+    let dir = tempfile::tempdir().unwrap();
+    let reference_path = dir.path().join("egress/artifacts/capture/fruits.json");
+
+    let mut egress = Egress::open(dir.path(), "capture").unwrap();
+    egress.artifact("fruits").insert_display("fruit", &"apple");
+    egress.close().unwrap().assert_unregressed();
+
+    let mut egress = Egress::open(dir.path(), "capture").unwrap();
+    egress.capture_mode();
+    egress.artifact("fruits").insert_display("fruit", &"pear");
+    egress.close().unwrap().assert_unregressed();
+    assert!(fs::read_to_string(&reference_path)
+        .unwrap()
+        .contains("pear"));
+
+    let mut egress = Egress::open(dir.path(), "capture").unwrap();
+    egress.artifact("fruits").insert_display("fruit", &"apple");
+    egress.close().unwrap().assert_mismatch("fruits.fruit");
+}
@@ -0,0 +1,49 @@
+//! Runs without the test harness, since it would otherwise intercept `println!` before it
+//! reaches the redirected stdout.
+
+use egress::{Artifact, Egress, Entry};
+
+fn main() {
+    let dir = tempfile::tempdir().unwrap();
+
+    for _ in 0..2 {
+        let mut egress = Egress::open(dir.path(), "captured").unwrap();
+        let artifact = egress.artifact("output");
+        artifact
+            .insert_captured("greeting", || {
+                println!("hello,");
+                println!("world");
+                println!();
+            })
+            .unwrap();
+        egress.close().unwrap().assert_unregressed();
+    }
+
+    let reference =
+        std::fs::read(dir.path().join("egress/artifacts/captured/output.json")).unwrap();
+    let reference: std::collections::BTreeMap<String, Entry> =
+        serde_json::from_slice(&reference).unwrap();
+    assert_eq!(
+        reference["greeting"],
+        Entry::Str("hello,\nworld".to_owned())
+    );
+
+    // A panic in the closure still puts stdout back, so it can be captured again.
+    let hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(|_| ()));
+    let panicked = std::panic::catch_unwind(|| {
+        let _ = Artifact::new().insert_captured("panic", || panic!("oops"));
+    });
+    std::panic::set_hook(hook);
+    assert!(panicked.is_err());
+
+    let mut artifact = Artifact::new();
+    artifact
+        .insert_captured("again", || println!("still works"))
+        .unwrap();
+    let mut expected = Artifact::new();
+    expected.insert_display("again", &"still works");
+    assert_eq!(artifact, expected);
+
+    println!("captured: ok");
+}
@@ -0,0 +1,87 @@
+use egress::{describe_changes, Egress};
+
+#[test]
+fn describe_changes_between_directories() {
+    let old = tempfile::tempdir().unwrap();
+    let new = tempfile::tempdir().unwrap();
+
+    let mut egress = Egress::open(old.path(), "changes").unwrap();
+    egress.artifact("changed").insert_display("fruit", &"apple");
+    egress
+        .artifact("unchanged")
+        .insert_display("fruit", &"apple");
+    egress.artifact("removed").insert_display("fruit", &"apple");
+    egress.close().unwrap().assert_unregressed();
+
+    let mut egress = Egress::open(new.path(), "changes").unwrap();
+    egress.artifact("changed").insert_display("fruit", &"pear");
+    egress
+        .artifact("unchanged")
+        .insert_display("fruit", &"apple");
+    egress.artifact("added").insert_display("fruit", &"apple");
+    egress.close().unwrap().assert_unregressed();
+
+    let changes = describe_changes(
+        old.path().join("egress/artifacts"),
+        new.path().join("egress/artifacts"),
+    )
+    .unwrap();
+
+    let summary = changes
+        .iter()
+        .map(|(path, mismatches)| {
+            let mismatches = serde_json::to_value(mismatches).unwrap();
+            (path.to_string_lossy().into_owned(), mismatches)
+        })
+        .collect::<Vec<_>>();
+
+    assert_eq!(
+        summary,
+        vec![
+            (
+                "changes/added.json".to_owned(),
+                serde_json::json!([{ "NotInReference": ["changes/added.fruit", { "Str": "apple" }] }]),
+            ),
+            (
+                "changes/changed.json".to_owned(),
+                serde_json::json!([{ "NotEq": ["changes/changed.fruit", { "Str": "pear" }, { "Str": "apple" }] }]),
+            ),
+            (
+                "changes/removed.json".to_owned(),
+                serde_json::json!([{ "NotProduced": ["changes/removed.fruit", { "Str": "apple" }] }]),
+            ),
+        ]
+    );
+}
+
+#[test]
+fn changed_sidecar() {
+    let old = tempfile::tempdir().unwrap();
+    let new = tempfile::tempdir().unwrap();
+
+    let mut egress = Egress::open(old.path(), "changes").unwrap();
+    egress.inline_threshold_bytes = Some(16);
+    let artifact = egress.artifact("large");
+    artifact.insert_display("log", &"a long line of output");
+    artifact.insert_json("table", serde_json::json!({ "rows": [1, 2, 3, 4, 5, 6] }));
+    egress.close().unwrap().assert_unregressed();
+
+    let mut egress = Egress::open(new.path(), "changes").unwrap();
+    egress.inline_threshold_bytes = Some(16);
+    let artifact = egress.artifact("large");
+    artifact.insert_display("log", &"a long line of output");
+    artifact.insert_json("table", serde_json::json!({ "rows": [1, 2, 3, 4, 5, 7] }));
+    egress.close().unwrap().assert_unregressed();
+
+    let changes = describe_changes(
+        old.path().join("egress/artifacts"),
+        new.path().join("egress/artifacts"),
+    )
+    .unwrap();
+
+    assert_eq!(changes.len(), 1, "{:?}", changes);
+    let (path, mismatches) = &changes[0];
+    assert_eq!(path.to_string_lossy(), "changes/large.json");
+    assert_eq!(mismatches.len(), 1, "{:?}", mismatches);
+    assert_eq!(mismatches[0].path(), "changes/large.table.rows[5]");
+}
@@ -0,0 +1,36 @@
+use {
+    egress::{Egress, Mismatch},
+    std::fs,
+};
+
+fn produce(dir: &std::path::Path, configure: impl FnOnce(&mut Egress)) -> egress::Report {
+    let mut egress = Egress::open(dir, "ci").unwrap();
+    configure(&mut egress);
+    egress.artifact("fruits").insert_display("fruit", &"apple");
+    egress.close().unwrap()
+}
+
+#[test]
+fn missing_reference() {
+    let dir = tempfile::tempdir().unwrap();
+    let reference_path = dir.path().join("egress/artifacts/ci/fruits.json");
+
+    produce(dir.path(), |_| ()).assert_unregressed();
+    fs::remove_file(&reference_path).unwrap();
+
+    let report = produce(dir.path(), |egress| egress.create_missing = false);
+    assert_eq!(
+        report.mismatches(),
+        &[Mismatch::MissingReference("fruits".to_owned())]
+    );
+    assert!(!reference_path.exists());
+
+    std::env::set_var("EGRESS_CI", "1");
+    let report = produce(dir.path(), |_| ());
+    std::env::remove_var("EGRESS_CI");
+    report.assert_mismatch("fruits");
+    assert!(!reference_path.exists());
+
+    report.bless().unwrap();
+    produce(dir.path(), |egress| egress.create_missing = false).assert_unregressed();
+}
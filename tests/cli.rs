@@ -0,0 +1,207 @@
+use {assert_cmd::Command, egress::Egress, std::fs};
+
+fn egress_cli() -> Command {
+    Command::cargo_bin("egress").unwrap()
+}
+
+fn write_config(dir: &std::path::Path, settings: &str) {
+    let config = format!("artifact_dir = \"egress/artifacts/\"\n{}", settings);
+    fs::write(dir.join("Egress.toml"), config).unwrap();
+}
+
+fn record(dir: &std::path::Path) {
+    let mut egress = Egress::open(dir, "cli").unwrap();
+    egress.inline_threshold_bytes = Some(16);
+    egress.artifact("fruits").insert_display("fruit", &"apple");
+    egress
+        .artifact("group/vegetables")
+        .insert_json("list", serde_json::json!(["carrot", "potato", "leek"]));
+    egress.close().unwrap().assert_unregressed();
+}
+
+#[test]
+fn list() {
+    let dir = tempfile::tempdir().unwrap();
+    record(dir.path());
+
+    egress_cli()
+        .arg("--config-dir")
+        .arg(dir.path())
+        .args(["list", "cli"])
+        .assert()
+        .success()
+        .stdout("fruits\ngroup/vegetables\n");
+}
+
+#[test]
+fn list_without_config() {
+    let dir = tempfile::tempdir().unwrap();
+
+    egress_cli()
+        .arg("--config-dir")
+        .arg(dir.path())
+        .arg("list")
+        .assert()
+        .code(2)
+        .stderr(predicates::str::contains("Egress.toml"));
+    assert!(!dir.path().join("Egress.toml").exists());
+}
+
+#[test]
+fn show() {
+    let dir = tempfile::tempdir().unwrap();
+    record(dir.path());
+
+    let output = egress_cli()
+        .arg("show")
+        .arg(dir.path().join("egress/artifacts/cli/fruits.json"))
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    let shown: serde_json::Value = serde_json::from_slice(&output).unwrap();
+    assert_eq!(shown["fruit"]["Str"], "apple");
+}
+
+#[test]
+fn diff() {
+    let dir = tempfile::tempdir().unwrap();
+    let reference = dir.path().join("reference.json");
+    let new = dir.path().join("new.json");
+    fs::write(
+        &reference,
+        r#"{"x": {"Json": 1.0}, "name": {"Str": "apple"}}"#,
+    )
+    .unwrap();
+    fs::write(&new, r#"{"x": {"Json": 1.05}, "name": {"Str": "apple"}}"#).unwrap();
+
+    let diff = || {
+        let mut cli = egress_cli();
+        cli.arg("--config-dir")
+            .arg(dir.path())
+            .arg("diff")
+            .arg(&reference)
+            .arg(&new);
+        cli
+    };
+
+    // Without an `Egress.toml` there's nothing to compare with.
+    diff().assert().code(2);
+    assert!(!dir.path().join("Egress.toml").exists());
+
+    write_config(dir.path(), "");
+    diff()
+        .assert()
+        .code(1)
+        .stdout(predicates::str::contains("MISMATCH"));
+    diff().args(["--atol", "0.1"]).assert().success().stdout("");
+
+    // Tolerances and settings come from `Egress.toml`.
+    write_config(dir.path(), "atol = 0.1\n");
+    diff().assert().success().stdout("");
+    write_config(dir.path(), "atol = 0.0\nround_floats_to_digits = 1\n");
+    diff().assert().success().stdout("");
+
+    egress_cli()
+        .arg("--config-dir")
+        .arg(dir.path())
+        .arg("diff")
+        .arg(&reference)
+        .arg(dir.path().join("new.exe"))
+        .assert()
+        .code(2);
+}
+
+#[cfg(feature = "gzip")]
+#[test]
+fn diff_compressed() {
+    let dir = tempfile::tempdir().unwrap();
+    write_config(
+        dir.path(),
+        "compress = true\nextension = \"snap\"\ninline_threshold_bytes = 16\n",
+    );
+    for (subdir, fruit) in [("old", "apple"), ("new", "pear")] {
+        let mut egress = Egress::open(dir.path(), subdir).unwrap();
+        let artifact = egress.artifact("fruits");
+        artifact.insert_display("fruit", &"apple");
+        artifact.insert_json("basket", serde_json::json!([fruit, "plum", "cherry"]));
+        egress.close().unwrap().assert_unregressed();
+    }
+
+    egress_cli()
+        .arg("--config-dir")
+        .arg(dir.path())
+        .arg("diff")
+        .arg(dir.path().join("egress/artifacts/old/fruits.snap.gz"))
+        .arg(dir.path().join("egress/artifacts/new/fruits.snap.gz"))
+        .assert()
+        .code(1)
+        .stdout(predicates::str::contains("basket[0]"));
+}
+
+#[test]
+fn prune_sidecars() {
+    let dir = tempfile::tempdir().unwrap();
+    record(dir.path());
+
+    // A sidecar of an entry the reference no longer has, and a reference no test writes.
+    let orphan = dir
+        .path()
+        .join("egress/artifacts/cli/group/vegetables.notes.txt");
+    fs::write(&orphan, "peeled and chopped").unwrap();
+    let mut egress = Egress::open(dir.path(), "cli/stale").unwrap();
+    egress.artifact("fruits").insert_display("fruit", &"pear");
+    egress.close().unwrap().assert_unregressed();
+
+    egress_cli()
+        .arg("--config-dir")
+        .arg(dir.path())
+        .args(["prune-sidecars", "cli"])
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("vegetables.notes.txt"));
+    assert!(!orphan.exists());
+    assert!(dir
+        .path()
+        .join("egress/artifacts/cli/group/vegetables.list.json")
+        .exists());
+    assert!(dir
+        .path()
+        .join("egress/artifacts/cli/stale/fruits.json")
+        .exists());
+}
+
+#[test]
+fn prune() {
+    let dir = tempfile::tempdir().unwrap();
+    record(dir.path());
+
+    // A reference of an artifact which isn't written any more, and one of a nested context.
+    let mut egress = Egress::open(dir.path(), "cli").unwrap();
+    egress.artifact("renamed").insert_display("fruit", &"apple");
+    egress.close().unwrap().assert_unregressed();
+    let mut egress = Egress::open(dir.path(), "cli/nested").unwrap();
+    egress.artifact("fruits").insert_display("fruit", &"pear");
+    egress.close().unwrap().assert_unregressed();
+
+    let artifacts = dir.path().join("egress/artifacts/cli");
+    egress_cli()
+        .arg("--config-dir")
+        .arg(dir.path())
+        .args(["prune", "cli", "fruits", "group/vegetables"])
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("renamed.json"));
+    assert!(!artifacts.join("renamed.json").exists());
+    assert!(artifacts.join("fruits.json").exists());
+    assert!(artifacts.join("group/vegetables.json").exists());
+    assert!(artifacts.join("nested/fruits.json").exists());
+}
+
+#[test]
+fn usage() {
+    egress_cli().assert().code(2);
+    egress_cli().arg("frobnicate").assert().code(2);
+    egress_cli().arg("prune").assert().code(2);
+}
@@ -0,0 +1,29 @@
+use {
+    egress::{Artifact, Egress},
+    serde_json::json,
+    std::{collections::HashMap, path::PathBuf},
+};
+
+#[test]
+fn in_memory_reference() {
+    let dir = tempfile::tempdir().unwrap();
+
+    let mut reference = Artifact::new();
+    reference.insert_display("fruit", &"apple");
+    reference.insert_json("counts", json!({ "apple": 1, "pear": 2 }));
+    let references = HashMap::from([(PathBuf::from("fruits"), reference)]);
+
+    let mut egress = Egress::open(dir.path(), "close_against").unwrap();
+    let artifact = egress.artifact("fruits");
+    artifact.insert_display("fruit", &"apple");
+    artifact.insert_json("counts", json!({ "apple": 1, "pear": 3 }));
+    egress
+        .artifact("vegetables")
+        .insert_display("vegetable", &"carrot");
+    let report = egress.close_against(references).unwrap();
+
+    assert_eq!(report.len(), 2, "{:?}", report.mismatches());
+    report.assert_mismatch("fruits.counts.pear");
+    report.assert_mismatch("vegetables");
+    assert!(!dir.path().join("egress/artifacts/close_against").exists());
+}
@@ -0,0 +1,45 @@
+use {egress::Egress, std::fs};
+
+#[test]
+fn compares_against_another_directory() {
+    let other = tempfile::tempdir().unwrap();
+    let mut egress = Egress::open(other.path(), "branch").unwrap();
+    egress.artifact("fruits").insert_display("fruit", &"apple");
+    egress
+        .artifact("group/vegetables")
+        .insert_display("vegetable", &"carrot");
+    egress.close().unwrap().assert_unregressed();
+    let reference_dir = other.path().join("egress/artifacts/branch");
+
+    let dir = tempfile::tempdir().unwrap();
+    let produced = dir.path().join("egress/artifacts/current");
+    let close = |fruit: &str, write: bool| {
+        let mut egress = Egress::open(dir.path(), "current").unwrap();
+        egress.write_on_close_against_dir = write;
+        egress.artifact("fruits").insert_display("fruit", &fruit);
+        egress
+            .artifact("group/vegetables")
+            .insert_display("vegetable", &"carrot");
+        egress.artifact("new").insert_display("fruit", &fruit);
+        egress.close_against_dir(&reference_dir).unwrap()
+    };
+
+    let report = close("pear", false);
+    report.assert_mismatch("fruits.fruit");
+    report.assert_mismatch("new");
+    assert_eq!(report.len(), 2, "{}", report);
+    assert_eq!(report.summary().compared, 2);
+    assert!(!produced.exists());
+
+    let report = close("apple", true);
+    assert_eq!(report.len(), 1, "{}", report);
+    report.assert_mismatch("new");
+    assert!(produced.join("group/vegetables.json").exists());
+    assert!(produced.join("new.json").exists());
+
+    // The other directory is left alone.
+    assert!(!reference_dir.join("new.json").exists());
+    assert!(fs::read_to_string(reference_dir.join("fruits.json"))
+        .unwrap()
+        .contains("apple"));
+}
@@ -0,0 +1,65 @@
+use egress::{Artifact, Egress, Entry};
+
+fn parse(entry: &Entry) -> f64 {
+    match entry {
+        Entry::Str(s) => s.parse().unwrap(),
+        Entry::Json(value) => value.as_str().unwrap().parse().unwrap(),
+        other => panic!("unexpected entry {:?}", other),
+    }
+}
+
+fn close_enough(a: &Entry, b: &Entry) -> bool {
+    (parse(a) - parse(b)).abs() < 1e-6
+}
+
+#[test]
+fn custom_comparator() {
+    let dir = tempfile::tempdir().unwrap();
+
+    let mut egress = Egress::open(dir.path(), "comparator").unwrap();
+    let artifact = egress.artifact("matrix");
+    artifact.insert_display("determinant", &"0.5");
+    artifact.insert_serialize("rows", &["1.0", "2.0"]).unwrap();
+    egress.close().unwrap().assert_unregressed();
+
+    let mut egress = Egress::open(dir.path(), "comparator").unwrap();
+    let artifact = egress.artifact("matrix");
+    artifact.insert_display("determinant", &"5.0000000001e-1");
+    artifact
+        .insert_serialize("rows", &["1.0000000001", "2"])
+        .unwrap();
+    artifact.set_comparator("determinant", close_enough);
+    artifact.set_comparator("rows[*]", close_enough);
+    let report = egress.close().unwrap();
+    assert!(report.is_empty(), "{:?}", report.mismatches());
+
+    let mut egress = Egress::open(dir.path(), "comparator").unwrap();
+    let artifact = egress.artifact("matrix");
+    artifact.insert_display("determinant", &"0.6");
+    artifact.insert_serialize("rows", &["1", "2.5"]).unwrap();
+    artifact.set_comparator("determinant", close_enough);
+    artifact.set_comparator("rows[*]", close_enough);
+    let report = egress.close().unwrap();
+    report.assert_mismatch("matrix.determinant");
+    report.assert_mismatch("matrix.rows[1]");
+    assert!(!report.has_mismatch("matrix.rows[0]"));
+}
+
+#[test]
+fn comparator_in_nested_artifact() {
+    let nested = |determinant: &str| {
+        let mut inner = Artifact::new();
+        inner.insert_display("determinant", &determinant);
+        let mut outer = Artifact::new();
+        outer.insert("nested", Entry::Artifact(inner));
+        outer
+    };
+
+    let reference = nested("0.5");
+    let mut drifted = nested("5.0000000001e-1");
+    assert_eq!(drifted.diff(&reference, None, None).len(), 1);
+
+    drifted.set_comparator("nested.determinant", close_enough);
+    let mismatches = drifted.diff(&reference, None, None);
+    assert!(mismatches.is_empty(), "{:?}", mismatches);
+}
@@ -0,0 +1,28 @@
+use egress::Egress;
+
+#[test]
+fn compare_in_phases() {
+    let dir = tempfile::tempdir().unwrap();
+
+    let mut egress = Egress::open(dir.path(), "compare").unwrap();
+    egress.artifact("setup").insert_display("state", &"ready");
+    egress.compare().unwrap().assert_unregressed();
+    assert!(dir
+        .path()
+        .join("egress/artifacts/compare/setup.json")
+        .exists());
+
+    // The first phase's artifacts are gone, so its names can be reused, and they're compared
+    // against the references the first phase wrote.
+    egress.artifact("run").insert_display("result", &42);
+    egress.artifact("setup").insert_display("state", &"done");
+    let report = egress.compare().unwrap();
+    assert_eq!(report.len(), 1, "{}", report);
+    report.assert_mismatch("setup.state");
+
+    egress.close().unwrap().assert_unregressed();
+    assert!(dir
+        .path()
+        .join("egress/artifacts/compare/run.json")
+        .exists());
+}
@@ -0,0 +1,74 @@
+use {
+    egress::{Egress, ErrorKind},
+    std::fs,
+};
+
+#[test]
+fn custom_config_file() {
+    let dir = tempfile::tempdir().unwrap();
+
+    let egress = Egress::open_with_config_file(dir.path(), "egress.shared.toml", "config").unwrap();
+    egress.close().unwrap().assert_unregressed();
+    assert!(dir.path().join("egress.shared.toml").exists());
+    assert!(!dir.path().join("Egress.toml").exists());
+
+    let config = fs::read_to_string(dir.path().join("egress.shared.toml")).unwrap();
+    let config = config.replace("egress/artifacts/", "shared/");
+    fs::write(dir.path().join("egress.shared.toml"), config).unwrap();
+
+    let mut egress =
+        Egress::open_with_config_file(dir.path(), "egress.shared.toml", "config").unwrap();
+    egress.artifact("fruits").insert_display("fruit", &"apple");
+    egress.close().unwrap().assert_unregressed();
+    assert!(dir.path().join("shared/config/fruits.json").exists());
+}
+
+#[test]
+fn negative_atol() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(
+        dir.path().join("Egress.toml"),
+        "artifact_dir = \"egress/artifacts/\"\natol = -0.1\nrtol = 0.0\n",
+    )
+    .unwrap();
+
+    match Egress::open(dir.path(), "config") {
+        Err(ErrorKind::InvalidConfig(name, _)) => assert_eq!(name, "atol"),
+        other => panic!(
+            "expected an invalid config error, got {:?}",
+            other.map(|_| ())
+        ),
+    }
+}
+
+#[test]
+fn nan_rtol() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(
+        dir.path().join("Egress.toml"),
+        "artifact_dir = \"egress/artifacts/\"\natol = 0.0\nrtol = nan\n",
+    )
+    .unwrap();
+
+    assert!(matches!(
+        Egress::open(dir.path(), "config"),
+        Err(ErrorKind::InvalidConfig(..))
+    ));
+}
+
+#[test]
+fn open_existing_requires_config() {
+    let dir = tempfile::tempdir().unwrap();
+
+    match Egress::open_existing(dir.path(), "config") {
+        Err(ErrorKind::MissingConfig(path)) => assert_eq!(path, dir.path().join("Egress.toml")),
+        other => panic!(
+            "expected a missing config error, got {:?}",
+            other.map(|_| ())
+        ),
+    }
+    assert!(!dir.path().join("Egress.toml").exists());
+
+    Egress::open(dir.path(), "config").unwrap();
+    Egress::open_existing(dir.path(), "config").unwrap();
+}
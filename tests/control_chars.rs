@@ -0,0 +1,32 @@
+use {egress::Egress, std::fs};
+
+#[test]
+fn escape_control_chars() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir
+        .path()
+        .join("egress/artifacts/control_chars/output.json");
+    let colored = "\u{1b}[31mred\u{1b}[0m\tapple\n";
+
+    for _ in 0..2 {
+        let mut egress = Egress::open(dir.path(), "control_chars").unwrap();
+        egress.escape_control_chars = true;
+        egress.artifact("output").insert_display("line", &colored);
+        egress.close().unwrap().assert_unregressed();
+    }
+
+    let reference = fs::read_to_string(&path).unwrap();
+    assert!(!reference.contains("\\u001b"), "{}", reference);
+    assert!(
+        reference.contains(r#""\\u{1b}[31mred\\u{1b}[0m\tapple\n""#),
+        "{}",
+        reference
+    );
+
+    let mut egress = Egress::open(dir.path(), "control_chars").unwrap();
+    egress.escape_control_chars = true;
+    egress
+        .artifact("output")
+        .insert_display("line", &"\u{1b}[32mred\u{1b}[0m\tapple\n");
+    egress.close().unwrap().assert_mismatch("output.line");
+}
@@ -0,0 +1,96 @@
+use {
+    egress::{Artifact, Entry, Mismatch},
+    serde_json::json,
+};
+
+fn fruits(fruit: &str, count: f64) -> Artifact {
+    let mut artifact = Artifact::new();
+    artifact.insert_display("fruit", &fruit);
+    artifact.insert_json("count", json!(count));
+    artifact
+}
+
+#[test]
+fn identical() {
+    assert_eq!(
+        fruits("apple", 1.0).diff(&fruits("apple", 1.0), None, None),
+        vec![]
+    );
+}
+
+#[test]
+fn changed_entries() {
+    let mismatches = fruits("pear", 1.5).diff(&fruits("apple", 1.0), None, None);
+
+    assert_eq!(
+        mismatches,
+        vec![
+            Mismatch::NotEq(
+                "count".to_owned(),
+                Entry::Json(json!(1.5)),
+                Entry::Json(json!(1.0))
+            ),
+            Mismatch::NotEq(
+                "fruit".to_owned(),
+                Entry::Str("pear".to_owned()),
+                Entry::Str("apple".to_owned())
+            ),
+        ]
+    );
+}
+
+#[test]
+fn tolerances() {
+    let new = fruits("apple", 1.05);
+    let reference = fruits("apple", 1.0);
+
+    assert_eq!(new.diff(&reference, Some(0.1), None), vec![]);
+    assert_eq!(new.diff(&reference, None, Some(0.1)), vec![]);
+    assert_eq!(new.diff(&reference, Some(0.01), None).len(), 1);
+    assert_eq!(new.diff(&reference, None, None).len(), 1);
+}
+
+#[test]
+fn missing_entries() {
+    let mut new = fruits("apple", 1.0);
+    new.insert_display("vegetable", &"carrot");
+    let mut reference = fruits("apple", 1.0);
+    reference.insert_display("grain", &"rice");
+
+    assert_eq!(
+        new.diff(&reference, None, None),
+        vec![
+            Mismatch::NotInReference("vegetable".to_owned(), Entry::Str("carrot".to_owned())),
+            Mismatch::NotProduced("grain".to_owned(), Entry::Str("rice".to_owned())),
+        ]
+    );
+}
+
+#[test]
+fn nested_artifacts() {
+    let mut new = Artifact::new();
+    new.insert("basket", Entry::Artifact(fruits("pear", 1.0)));
+    let mut reference = Artifact::new();
+    reference.insert("basket", Entry::Artifact(fruits("apple", 1.0)));
+
+    assert_eq!(
+        new.diff(&reference, None, None),
+        vec![Mismatch::NotEq(
+            "basket.fruit".to_owned(),
+            Entry::Str("pear".to_owned()),
+            Entry::Str("apple".to_owned())
+        )]
+    );
+}
+
+#[test]
+fn nested_artifact_tolerance() {
+    let mut loose = fruits("apple", 1.05);
+    loose.set_atol(Some(0.1));
+    let mut new = Artifact::new();
+    new.insert("basket", Entry::Artifact(loose));
+    let mut reference = Artifact::new();
+    reference.insert("basket", Entry::Artifact(fruits("apple", 1.0)));
+
+    assert_eq!(new.diff(&reference, None, None), vec![]);
+}
@@ -0,0 +1,33 @@
+use {egress::Artifact, std::fmt};
+
+struct Fraction(u32, u32);
+
+impl fmt::Display for Fraction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if f.alternate() {
+            write!(
+                f,
+                "{} / {} = {:.2}",
+                self.0,
+                self.1,
+                self.0 as f64 / self.1 as f64
+            )
+        } else {
+            write!(f, "{}/{}", self.0, self.1)
+        }
+    }
+}
+
+#[test]
+fn insert_display_alternate() {
+    let mut artifact = Artifact::new();
+    artifact.insert_display("plain", &Fraction(1, 4));
+    artifact.insert_display_alternate("alternate", &Fraction(1, 4));
+
+    let mut expected = Artifact::new();
+    expected.insert_display("plain", &"1/4");
+    expected.insert_display("alternate", &"1 / 4 = 0.25");
+
+    let mismatches = artifact.diff(&expected, None, None);
+    assert!(mismatches.is_empty(), "{:?}", mismatches);
+}
@@ -0,0 +1,56 @@
+use {egress::Egress, std::fs};
+
+fn files_under(dir: &std::path::Path) -> Vec<std::path::PathBuf> {
+    let mut files = Vec::new();
+    for entry in fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        if path.is_dir() {
+            files.extend(files_under(&path));
+        } else {
+            files.push(path);
+        }
+    }
+    files.sort();
+    files
+}
+
+#[test]
+fn missing_reference_isnt_created() {
+    let dir = tempfile::tempdir().unwrap();
+    Egress::open(dir.path(), "dry_run")
+        .unwrap()
+        .close()
+        .unwrap()
+        .assert_unregressed();
+    let before = files_under(dir.path());
+
+    let mut egress = Egress::open(dir.path(), "dry_run").unwrap();
+    egress.dry_run = true;
+    egress.artifact("fruits").insert_display("fruit", &"apple");
+    let report = egress.close().unwrap();
+
+    assert!(report.is_empty(), "{}", report);
+    assert_eq!(report.would_create(), ["fruits"]);
+    assert_eq!(files_under(dir.path()), before);
+}
+
+#[test]
+fn compares_existing_references() {
+    let dir = tempfile::tempdir().unwrap();
+
+    let mut egress = Egress::open(dir.path(), "dry_run").unwrap();
+    egress.artifact("fruits").insert_display("fruit", &"apple");
+    egress.close().unwrap().assert_unregressed();
+    let reference = dir.path().join("egress/artifacts/dry_run/fruits.json");
+    let contents = fs::read(&reference).unwrap();
+
+    let mut egress = Egress::open(dir.path(), "dry_run").unwrap();
+    egress.dry_run = true;
+    egress.capture_mode();
+    egress.artifact("fruits").insert_display("fruit", &"pear");
+    let report = egress.close().unwrap();
+
+    report.assert_mismatch("fruits.fruit");
+    assert!(report.would_create().is_empty());
+    assert_eq!(fs::read(&reference).unwrap(), contents);
+}
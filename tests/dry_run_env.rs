@@ -0,0 +1,14 @@
+use egress::Egress;
+
+#[test]
+fn env_var_writes_nothing() {
+    std::env::set_var("EGRESS_DRY_RUN", "1");
+    let dir = tempfile::tempdir().unwrap();
+
+    let mut egress = Egress::open(dir.path(), "dry_run").unwrap();
+    egress.artifact("fruits").insert_display("fruit", &"apple");
+    let report = egress.close().unwrap();
+
+    assert_eq!(report.would_create(), ["fruits"]);
+    assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 0);
+}
@@ -0,0 +1,29 @@
+#![cfg(feature = "macros")]
+
+use egress::{artifact, egress_test, Artifact, Egress};
+
+#[egress_test]
+fn with_artifact(artifact: &mut Artifact) {
+    artifact.insert_serialize("1 + 1", &(1 + 1)).unwrap();
+    artifact.insert_display("fruit", &"apple");
+}
+
+#[egress_test]
+fn with_egress(egress: &mut Egress) {
+    egress.artifact("fruits").insert_display("fruit", &"apple");
+    egress
+        .artifact("vegetables")
+        .insert_display("vegetable", &"carrot");
+}
+
+#[egress_test]
+fn with_artifact_macro(egress: &mut Egress) {
+    artifact!(egress).insert_display("fruit", &"apple");
+}
+
+#[egress_test]
+#[should_panic(expected = "oops")]
+fn panicking(artifact: &mut Artifact) {
+    artifact.insert_display("fruit", &"apple");
+    panic!("oops");
+}
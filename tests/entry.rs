@@ -0,0 +1,101 @@
+use {
+    egress::{Artifact, Egress, Entry, ErrorKind, MismatchKind},
+    serde_json::json,
+};
+
+#[test]
+fn conversions() {
+    assert_eq!(Entry::from("apple"), Entry::Str("apple".to_owned()));
+    assert_eq!(
+        Entry::from("apple".to_owned()),
+        Entry::Str("apple".to_owned())
+    );
+    assert_eq!(Entry::from(json!([1, 2])), Entry::Json(json!([1, 2])));
+    assert_eq!(Entry::from(vec![1u8, 2]), Entry::Bytes(vec![1, 2]));
+    assert_eq!(
+        Entry::try_from_serialize(&("apple", 1)).unwrap(),
+        Entry::Json(json!(["apple", 1]))
+    );
+    assert_eq!(
+        Entry::try_from_serialize(&f64::NAN).unwrap(),
+        Entry::Json(json!("NaN"))
+    );
+}
+
+#[test]
+fn insert_converted() {
+    let mut artifact = Artifact::new();
+    artifact.insert("fruit", "apple".into());
+    artifact.insert("counts", json!({ "apple": 1 }).into());
+
+    let mut expected = Artifact::new();
+    expected.insert_display("fruit", &"apple");
+    expected.insert_json("counts", json!({ "apple": 1 }));
+
+    assert!(artifact.diff(&expected, None, None).is_empty());
+}
+
+#[test]
+fn remove_and_clear() {
+    let mut artifact = Artifact::new();
+    artifact.insert_display("fruit", &"apple");
+    assert_eq!(artifact.remove("fruit"), Some(Entry::from("apple")));
+    assert_eq!(artifact.remove("fruit"), None);
+    artifact.insert_display("fruit", &"pear");
+
+    let mut expected = Artifact::new();
+    expected.insert_display("fruit", &"pear");
+    assert!(artifact.diff(&expected, None, None).is_empty());
+
+    artifact.clear();
+    assert!(artifact.diff(&Artifact::new(), None, None).is_empty());
+    artifact.insert_display("fruit", &"plum");
+}
+
+#[test]
+fn merge() {
+    let mut artifact = Artifact::new();
+    artifact.insert_display("fruit", &"apple");
+
+    let mut helper = Artifact::new();
+    helper.insert_json("counts", json!({ "apple": 1 }));
+    helper.insert_display("vegetable", &"leek");
+    artifact.merge(helper).unwrap();
+
+    let mut expected = Artifact::new();
+    expected.insert_display("fruit", &"apple");
+    expected.insert_json("counts", json!({ "apple": 1 }));
+    expected.insert_display("vegetable", &"leek");
+    assert!(artifact.diff(&expected, None, None).is_empty());
+
+    let mut colliding = Artifact::new();
+    colliding.insert_display("berry", &"cherry");
+    colliding.insert_display("fruit", &"pear");
+    let err = artifact.merge(colliding).unwrap_err();
+    assert!(matches!(err, ErrorKind::DuplicateEntry(ref name) if name == "fruit"));
+    assert!(artifact.diff(&expected, None, None).is_empty());
+}
+
+#[test]
+fn null_is_distinct_from_missing() {
+    let dir = tempfile::tempdir().unwrap();
+
+    let mut egress = Egress::open(dir.path(), "entry").unwrap();
+    egress.artifact("fruits").insert_null("fruit");
+    egress.close().unwrap().assert_unregressed();
+
+    let mut egress = Egress::open(dir.path(), "entry").unwrap();
+    egress.artifact("fruits").insert_null("fruit");
+    egress.close().unwrap().assert_unregressed();
+
+    let mut egress = Egress::open(dir.path(), "entry").unwrap();
+    egress.artifact("fruits").insert_display("fruit", &"apple");
+    let report = egress.close().unwrap();
+    assert_eq!(report.mismatches()[0].kind(), MismatchKind::NotEq);
+
+    let mut egress = Egress::open(dir.path(), "entry").unwrap();
+    egress.artifact("fruits");
+    let report = egress.close().unwrap();
+    assert_eq!(report.mismatches()[0].kind(), MismatchKind::NotProduced);
+    assert_eq!(report.mismatches()[0].path(), "fruits.fruit");
+}
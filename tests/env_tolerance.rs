@@ -0,0 +1,55 @@
+use {
+    egress::{Egress, ErrorKind},
+    serde_json::json,
+};
+
+// Environment variables are process-wide, so everything touching them lives in one test.
+#[test]
+fn tolerances_from_the_environment() {
+    let dir = tempfile::tempdir().unwrap();
+    let close = |x: f64| {
+        let mut egress = Egress::open(dir.path(), "env_tolerance").unwrap();
+        egress.artifact("float").insert_json("x", json!(x));
+        egress.close().unwrap()
+    };
+
+    close(1.0).assert_unregressed();
+    assert_eq!(close(1.05).len(), 1);
+
+    std::env::set_var("EGRESS_ATOL", "0.1");
+    std::env::set_var("EGRESS_RTOL", "0.1");
+    close(1.05).assert_unregressed();
+    assert_eq!(close(1.5).len(), 1);
+
+    // Setting a tolerance in code still wins.
+    let mut egress = Egress::open(dir.path(), "env_tolerance").unwrap();
+    egress.atol = Some(0.0);
+    egress.artifact("float").insert_json("x", json!(1.05));
+    assert_eq!(egress.close().unwrap().len(), 1);
+
+    std::env::set_var("EGRESS_RTOL", "loose");
+    let not_a_number = Egress::open(dir.path(), "env_tolerance");
+    std::env::set_var("EGRESS_RTOL", "-0.1");
+    let negative = Egress::open(dir.path(), "env_tolerance");
+    std::env::set_var("EGRESS_RTOL", "0.1");
+    std::env::set_var("EGRESS_ATOL", "NaN");
+    let nan = Egress::open(dir.path(), "env_tolerance");
+    std::env::remove_var("EGRESS_ATOL");
+    std::env::remove_var("EGRESS_RTOL");
+
+    match not_a_number {
+        Err(ErrorKind::InvalidEnvVar(name, value)) => {
+            assert_eq!(name, "EGRESS_RTOL");
+            assert_eq!(value, "loose");
+        }
+        other => panic!("expected an InvalidEnvVar error, got {:?}", other),
+    }
+    match negative {
+        Err(ErrorKind::InvalidConfig(name, _)) => assert_eq!(name, "EGRESS_RTOL"),
+        other => panic!("expected an InvalidConfig error, got {:?}", other),
+    }
+    match nan {
+        Err(ErrorKind::InvalidConfig(name, _)) => assert_eq!(name, "EGRESS_ATOL"),
+        other => panic!("expected an InvalidConfig error, got {:?}", other),
+    }
+}
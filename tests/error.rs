@@ -0,0 +1,71 @@
+use {
+    egress::{Artifact, Egress, Entry, ErrorKind},
+    std::error::Error,
+};
+
+fn open_in_file() -> Result<Egress, Box<dyn Error>> {
+    let file = tempfile::NamedTempFile::new()?;
+    // The "config directory" is actually a file, so it can't be created.
+    Ok(Egress::open(file.path(), "error")?)
+}
+
+#[test]
+fn errors_are_std_errors() {
+    let err = open_in_file().unwrap_err();
+    let err = err.downcast_ref::<ErrorKind>().unwrap();
+
+    assert!(matches!(err, ErrorKind::IoError(_)));
+    assert!(err.to_string().starts_with("I/O error: "));
+    assert!(err.source().unwrap().is::<std::io::Error>());
+}
+
+#[test]
+fn duplicate_entry() {
+    let mut artifact = Artifact::new();
+    artifact
+        .try_insert("fruit", Entry::Str("apple".to_owned()))
+        .unwrap();
+
+    let err = artifact
+        .try_insert("fruit", Entry::Str("pear".to_owned()))
+        .unwrap_err();
+    assert!(matches!(err, ErrorKind::DuplicateEntry(ref name) if name == "fruit"));
+}
+
+#[test]
+fn invalid_artifact_name() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut egress = Egress::open(dir.path(), "error").unwrap();
+
+    let err = egress.try_artifact("../case").unwrap_err();
+    assert!(matches!(err, ErrorKind::InvalidArtifactName(_)));
+    assert!(egress.try_artifact("case").is_ok());
+    assert!(matches!(
+        egress.try_artifact("case").unwrap_err(),
+        ErrorKind::DuplicateArtifact(_)
+    ));
+}
+
+#[test]
+fn unwritable_artifact_dir() {
+    let file = tempfile::NamedTempFile::new().unwrap();
+    // A directory can't be created inside a file, even with permission to write anywhere.
+    let artifact_dir = file.path().join("artifacts");
+
+    let mut egress = Egress::builder()
+        .artifact_dir(&artifact_dir)
+        .artifact_subdir("error")
+        .build()
+        .unwrap();
+    egress.artifact("case").insert_display("fruit", &"apple");
+    let err = egress.close().unwrap_err();
+
+    match &err {
+        ErrorKind::ArtifactIo { path, .. } => assert_eq!(path, &artifact_dir.join("error")),
+        other => panic!("expected ErrorKind::ArtifactIo, got {:?}", other),
+    }
+    assert!(err
+        .to_string()
+        .contains(&artifact_dir.join("error").display().to_string()));
+    assert!(err.source().unwrap().is::<std::io::Error>());
+}
@@ -0,0 +1,29 @@
+use {egress::Egress, std::path::PathBuf};
+
+#[test]
+fn existing_artifacts() {
+    let dir = tempfile::tempdir().unwrap();
+
+    let egress = Egress::open(dir.path(), "existing").unwrap();
+    assert!(egress.existing_artifacts().unwrap().is_empty());
+    drop(egress);
+
+    let mut egress = Egress::open(dir.path(), "existing").unwrap();
+    egress.inline_threshold_bytes = Some(16);
+    egress.artifact("fruits").insert_display("fruit", &"apple");
+    egress
+        .artifact("group/vegetables")
+        .insert_json("list", serde_json::json!(["carrot", "potato", "leek"]));
+    egress.close().unwrap().assert_unregressed();
+    assert!(dir
+        .path()
+        .join("egress/artifacts/existing/group/vegetables.list.json")
+        .exists());
+
+    // Only the references themselves are listed, not the sidecar of `group/vegetables`.
+    let egress = Egress::open(dir.path(), "existing").unwrap();
+    assert_eq!(
+        egress.existing_artifacts().unwrap(),
+        vec![PathBuf::from("fruits"), PathBuf::from("group/vegetables")]
+    );
+}
@@ -0,0 +1,21 @@
+use egress::Egress;
+
+#[test]
+fn custom_extension() {
+    let dir = tempfile::tempdir().unwrap();
+
+    let mut egress = Egress::open(dir.path(), "extension").unwrap();
+    egress.extension = Some(".snap.json".to_owned());
+    egress.artifact("fruits").insert_display("fruit", &"apple");
+    egress.close().unwrap().assert_unregressed();
+
+    let subdir = dir.path().join("egress/artifacts/extension");
+    assert!(subdir.join("fruits.snap.json").exists());
+    assert!(!subdir.join("fruits.json").exists());
+
+    let mut egress = Egress::open(dir.path(), "extension").unwrap();
+    egress.extension = Some("snap.json".to_owned());
+    egress.artifact("fruits").insert_display("fruit", &"pear");
+    let report = egress.close().unwrap();
+    report.assert_mismatch("fruits.fruit");
+}
@@ -0,0 +1,102 @@
+use {egress::Egress, serde::Serialize, std::fs};
+
+#[derive(Serialize)]
+struct Stats {
+    mean: f64,
+    max: f32,
+    min: f64,
+    values: Vec<Option<f64>>,
+}
+
+#[test]
+fn non_finite_round_trip() {
+    let dir = tempfile::tempdir().unwrap();
+    let stats = Stats {
+        mean: f64::NAN,
+        max: f32::INFINITY,
+        min: f64::NEG_INFINITY,
+        values: vec![Some(1.5), None, Some(f64::NAN)],
+    };
+
+    for _ in 0..2 {
+        let mut egress = Egress::open(dir.path(), "floats").unwrap();
+        egress
+            .artifact("non_finite")
+            .insert_serialize("stats", &stats)
+            .unwrap();
+        egress.close().unwrap().assert_unregressed();
+    }
+
+    let reference: serde_json::Value = serde_json::from_str(
+        &fs::read_to_string(dir.path().join("egress/artifacts/floats/non_finite.json")).unwrap(),
+    )
+    .unwrap();
+    assert_eq!(
+        reference["stats"]["Json"],
+        serde_json::json!({
+            "mean": "NaN",
+            "max": "Infinity",
+            "min": "-Infinity",
+            "values": [1.5, null, "NaN"],
+        })
+    );
+}
+
+#[test]
+fn round_floats_to_digits() {
+    let dir = tempfile::tempdir().unwrap();
+
+    let mut egress = Egress::open(dir.path(), "floats").unwrap();
+    egress.round_floats_to_digits = Some(6);
+    egress.artifact("rounded").insert_json(
+        "values",
+        serde_json::json!([0.123456789012345, 12345.6789012345, 7]),
+    );
+    egress.close().unwrap().assert_unregressed();
+
+    let reference: serde_json::Value = serde_json::from_str(
+        &fs::read_to_string(dir.path().join("egress/artifacts/floats/rounded.json")).unwrap(),
+    )
+    .unwrap();
+    assert_eq!(
+        reference["values"]["Json"],
+        serde_json::json!([0.123457, 12345.7, 7])
+    );
+
+    // A difference past the rounded digits doesn't show up as a mismatch.
+    let mut egress = Egress::open(dir.path(), "floats").unwrap();
+    egress.round_floats_to_digits = Some(6);
+    egress
+        .artifact("rounded")
+        .insert_json("values", serde_json::json!([0.123456999, 12345.6700001, 7]));
+    egress.close().unwrap().assert_unregressed();
+}
+
+#[test]
+fn negative_zero() {
+    let dir = tempfile::tempdir().unwrap();
+    let reference = dir.path().join("egress/artifacts/floats/zero.json");
+
+    let mut egress = Egress::open(dir.path(), "floats").unwrap();
+    egress
+        .artifact("zero")
+        .insert_json("values", serde_json::json!([0.0, { "z": 0.0 }]));
+    egress.close().unwrap().assert_unregressed();
+    let written = fs::read_to_string(&reference).unwrap();
+
+    let mut egress = Egress::open(dir.path(), "floats").unwrap();
+    egress
+        .artifact("zero")
+        .insert_json("values", serde_json::json!([-0.0, { "z": -0.0 }]));
+    egress.close().unwrap().assert_unregressed();
+
+    // Stored as `0.0` as well, so that recording again doesn't change the reference.
+    fs::remove_file(&reference).unwrap();
+    let mut egress = Egress::open(dir.path(), "floats").unwrap();
+    egress
+        .artifact("zero")
+        .insert_json("values", serde_json::json!([-0.0, { "z": -0.0 }]));
+    egress.close().unwrap().assert_unregressed();
+    assert_eq!(fs::read_to_string(&reference).unwrap(), written);
+    assert!(!written.contains("-0.0"));
+}
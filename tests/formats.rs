@@ -0,0 +1,121 @@
+use {
+    egress::{Artifact, Egress, Entry, ErrorKind, Format, Report},
+    serde_json::{json, Value},
+    std::path::Path,
+};
+
+fn close_with(dir: &Path, format: Format, result: Value) -> Report {
+    let mut egress = Egress::open(dir, "formats").unwrap();
+    egress.format = format;
+    let artifact = egress.artifact("artifact");
+    artifact.insert_json("result", result);
+    artifact.insert_display("summary", &"two fruits");
+    let mut nested = Artifact::new();
+    nested.insert_json(
+        "sizes",
+        json!({ "apple": { "grams": 150 }, "banana": { "grams": 120 } }),
+    );
+    nested.insert_bytes("raw", &[0, 1, 2, 0xff]);
+    artifact.insert("nested", Entry::Artifact(nested));
+    egress.close().unwrap()
+}
+
+fn round_trip(format: Format) {
+    let dir = tempfile::tempdir().unwrap();
+    let result = json!({ "fruits": ["apple", "banana"], "count": 2, "ratio": 0.5 });
+
+    close_with(dir.path(), format, result.clone()).assert_unregressed();
+    let reference = dir
+        .path()
+        .join("egress/artifacts/formats/artifact")
+        .with_extension(format.extension());
+    assert!(reference.exists());
+
+    close_with(dir.path(), format, result).assert_unregressed();
+
+    let changed = json!({ "fruits": ["apple", "pear"], "count": 2, "ratio": 0.5 });
+    let report = close_with(dir.path(), format, changed);
+    report.assert_mismatch("artifact.result.fruits[1]");
+    assert!(!report.has_mismatch("artifact.summary"));
+}
+
+#[cfg(feature = "yaml")]
+#[test]
+fn yaml_round_trip() {
+    round_trip(Format::Yaml);
+}
+
+#[cfg(feature = "cbor")]
+#[test]
+fn cbor_round_trip() {
+    round_trip(Format::Cbor);
+}
+
+#[test]
+fn toml_round_trip() {
+    round_trip(Format::Toml);
+}
+
+#[test]
+fn toml_rejects_null() {
+    let dir = tempfile::tempdir().unwrap();
+
+    let mut egress = Egress::open(dir.path(), "formats").unwrap();
+    egress.format = Format::Toml;
+    egress
+        .artifact("artifact")
+        .insert_json("result", json!({ "fruits": ["apple", null] }));
+
+    match egress.close() {
+        Err(ErrorKind::TomlNull(path)) => assert_eq!(path, "result.fruits[1]"),
+        other => panic!("expected a TomlNull error, got {:?}", other),
+    }
+}
+
+#[cfg(feature = "ron")]
+#[test]
+fn ron_round_trip() {
+    round_trip(Format::Ron);
+}
+
+#[cfg(feature = "cbor")]
+#[test]
+fn cbor_large_nested() {
+    let dir = tempfile::tempdir().unwrap();
+    let tensor = |changed: f64| {
+        let rows = (0..100)
+            .map(|i| {
+                let row = (0..100)
+                    .map(|j| {
+                        if (i, j) == (42, 7) {
+                            changed
+                        } else {
+                            (i * j) as f64 / 3.0
+                        }
+                    })
+                    .collect::<Vec<_>>();
+                json!({ "index": i, "values": row })
+            })
+            .collect::<Vec<_>>();
+        json!({ "shape": [100, 100], "rows": rows })
+    };
+    let close = |value: Value| {
+        let mut egress = Egress::open(dir.path(), "formats").unwrap();
+        egress.format = Format::Cbor;
+        let artifact = egress.artifact("tensor");
+        artifact.insert_json("tensor", value);
+        artifact.insert_bytes("raw", &[0, 1, 2, 0xff]);
+        egress.close().unwrap()
+    };
+
+    close(tensor(0.5)).assert_unregressed();
+    close(tensor(0.5)).assert_unregressed();
+
+    let reference = dir.path().join("egress/artifacts/formats/tensor.cbor");
+    let pretty = serde_json::to_vec_pretty(&tensor(0.5)).unwrap();
+    assert!(std::fs::metadata(reference).unwrap().len() < pretty.len() as u64 / 2);
+
+    let report = close(tensor(0.25));
+    assert_eq!(report.len(), 1);
+    report.assert_mismatch("tensor.tensor.rows[42].values[7]");
+}
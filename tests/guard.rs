@@ -0,0 +1,51 @@
+use {
+    egress::Egress,
+    std::panic::{self, AssertUnwindSafe},
+};
+
+#[test]
+fn dropping_checks_for_regressions() {
+    let dir = tempfile::tempdir().unwrap();
+
+    {
+        let mut egress = Egress::open_guarded(dir.path(), "guard").unwrap();
+        egress.artifact("fruits").insert_display("fruit", &"apple");
+    }
+    assert!(dir
+        .path()
+        .join("egress/artifacts/guard/fruits.json")
+        .exists());
+
+    // Matching output passes quietly.
+    {
+        let mut egress = Egress::open_guarded(dir.path(), "guard").unwrap();
+        egress.artifact("fruits").insert_display("fruit", &"apple");
+    }
+
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+        let mut egress = Egress::open_guarded(dir.path(), "guard").unwrap();
+        egress.artifact("fruits").insert_display("fruit", &"pear");
+    }));
+    let panic = result.unwrap_err();
+    let message = panic.downcast_ref::<&str>().copied().unwrap_or_default();
+    assert!(message.contains("found mismatches"), "{}", message);
+}
+
+#[test]
+fn dropping_while_panicking_does_not_check() {
+    let dir = tempfile::tempdir().unwrap();
+
+    {
+        let mut egress = Egress::open_guarded(dir.path(), "guard").unwrap();
+        egress.artifact("fruits").insert_display("fruit", &"apple");
+    }
+
+    // A regression while already panicking doesn't abort the process with a double panic.
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+        let mut egress = Egress::open_guarded(dir.path(), "guard").unwrap();
+        egress.artifact("fruits").insert_display("fruit", &"pear");
+        panic!("the test failed first");
+    }));
+    let panic = result.unwrap_err();
+    assert_eq!(panic.downcast_ref::<&str>(), Some(&"the test failed first"));
+}
@@ -0,0 +1,35 @@
+#![cfg(feature = "gzip")]
+
+use {egress::Egress, serde_json::json, std::path::Path};
+
+fn close_with(dir: &Path, compress: bool, count: usize) -> egress::Report {
+    let mut egress = Egress::open(dir, "gzip").unwrap();
+    egress.compress = compress;
+    egress.artifact("fruits").insert_json(
+        "list",
+        json!((0..count)
+            .map(|i| format!("apple {}", i % 10))
+            .collect::<Vec<_>>()),
+    );
+    egress.close().unwrap()
+}
+
+#[test]
+fn compressed_round_trip() {
+    let dir = tempfile::tempdir().unwrap();
+    let subdir = dir.path().join("egress/artifacts/gzip");
+
+    close_with(dir.path(), true, 1000).assert_unregressed();
+    close_with(dir.path(), false, 1000).assert_unregressed();
+    let compressed = subdir.join("fruits.json.gz").metadata().unwrap().len();
+    let uncompressed = subdir.join("fruits.json").metadata().unwrap().len();
+    assert!(
+        compressed < uncompressed / 4,
+        "{} vs {}",
+        compressed,
+        uncompressed
+    );
+
+    close_with(dir.path(), true, 1000).assert_unregressed();
+    close_with(dir.path(), true, 999).assert_mismatch("fruits.list");
+}
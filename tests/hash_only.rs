@@ -0,0 +1,41 @@
+use {
+    egress::{Egress, Mismatch},
+    serde_json::json,
+    std::fs,
+};
+
+fn weights(scale: f64) -> serde_json::Value {
+    json!((0..10_000).map(|i| i as f64 * scale).collect::<Vec<_>>())
+}
+
+#[test]
+fn changed_artifact_is_one_mismatch() {
+    let dir = tempfile::tempdir().unwrap();
+
+    let mut egress = Egress::open(dir.path(), "hash_only").unwrap();
+    let artifact = egress.artifact("model");
+    artifact.insert_json("weights", weights(0.5));
+    artifact.insert_display("name", &"big");
+    artifact.store_hash_only();
+    egress.close().unwrap().assert_unregressed();
+
+    let reference = dir.path().join("egress/artifacts/hash_only/model.json");
+    assert!(fs::metadata(&reference).unwrap().len() < 256);
+
+    let mut egress = Egress::open(dir.path(), "hash_only").unwrap();
+    let artifact = egress.artifact("model");
+    artifact.insert_json("weights", weights(0.5));
+    artifact.insert_display("name", &"big");
+    artifact.store_hash_only();
+    egress.close().unwrap().assert_unregressed();
+
+    let mut egress = Egress::open(dir.path(), "hash_only").unwrap();
+    let artifact = egress.artifact("model");
+    artifact.insert_json("weights", weights(0.25));
+    artifact.insert_display("name", &"bigger");
+    artifact.store_hash_only();
+    let report = egress.close().unwrap();
+
+    assert_eq!(report.mismatches().len(), 1, "{}", report);
+    assert!(matches!(report.mismatches()[0], Mismatch::NotEq(..)));
+}
@@ -0,0 +1,88 @@
+use {egress::Egress, serde_json::json};
+
+#[test]
+fn ignored_timestamp() {
+    let dir = tempfile::tempdir().unwrap();
+
+    let mut egress = Egress::open(dir.path(), "ignore").unwrap();
+    let artifact = egress.artifact("run");
+    artifact.insert_json("result", json!({ "value": 1, "timestamp": 1600000000 }));
+    artifact.insert_display("started", &"12:00:00");
+    egress.close().unwrap().assert_unregressed();
+
+    let mut egress = Egress::open(dir.path(), "ignore").unwrap();
+    let artifact = egress.artifact("run");
+    artifact.insert_json("result", json!({ "value": 1, "timestamp": 1700000000 }));
+    artifact.insert_display("started", &"13:30:00");
+    artifact.ignore("result.timestamp");
+    artifact.ignore("started");
+    let report = egress.close().unwrap();
+
+    assert!(report.is_empty(), "{:?}", report.mismatches());
+}
+
+#[test]
+fn ignored_wildcard() {
+    let dir = tempfile::tempdir().unwrap();
+
+    let metadata = |a, b, c| {
+        json!({
+            "first": { "timestamp": a, "id": 1 },
+            "second": { "timestamp": b, "id": 2 },
+            "runs": [{ "duration": c }, { "duration": c }],
+        })
+    };
+
+    let mut egress = Egress::open(dir.path(), "ignore").unwrap();
+    egress
+        .artifact("run")
+        .insert_json("metadata", metadata(1, 2, 3));
+    egress.close().unwrap().assert_unregressed();
+
+    let mut egress = Egress::open(dir.path(), "ignore").unwrap();
+    let artifact = egress.artifact("run");
+    artifact.insert_json("metadata", metadata(4, 5, 6));
+    artifact.ignore("metadata.*.timestamp");
+    artifact.ignore("metadata.runs[*].duration");
+    egress.close().unwrap().assert_unregressed();
+}
+
+#[test]
+fn ignored_recursive_wildcard() {
+    let dir = tempfile::tempdir().unwrap();
+
+    let results = |elapsed| {
+        json!({
+            "elapsed_ms": elapsed,
+            "parse": { "elapsed_ms": elapsed, "count": 3 },
+            "build": { "steps": [{ "elapsed_ms": elapsed, "count": 4 }] },
+        })
+    };
+    let close = |elapsed, count, pattern: &str| {
+        let mut egress = Egress::open(dir.path(), "ignore").unwrap();
+        let artifact = egress.artifact("glob");
+        let mut results = results(elapsed);
+        results["build"]["steps"][0]["count"] = json!(count);
+        artifact.insert_json("results", results);
+        artifact.ignore(pattern);
+        egress.close().unwrap()
+    };
+
+    close(1, 4, "none").assert_unregressed();
+
+    // A single `*` only covers one level.
+    let report = close(2, 4, "results.*.elapsed_ms");
+    assert!(!report.has_mismatch("glob.results.parse.elapsed_ms"));
+    report.assert_mismatch("glob.results.elapsed_ms");
+    report.assert_mismatch("glob.results.build.steps[0].elapsed_ms");
+    assert_eq!(report.len(), 2);
+
+    // A `**` covers any number of levels, including none.
+    close(2, 4, "results.**.elapsed_ms").assert_unregressed();
+    close(2, 4, "**.elapsed_ms").assert_unregressed();
+
+    // Everything else is still compared.
+    let report = close(2, 5, "results.**.elapsed_ms");
+    assert_eq!(report.len(), 1);
+    report.assert_mismatch("glob.results.build.steps[0].count");
+}
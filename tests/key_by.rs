@@ -0,0 +1,85 @@
+use {
+    egress::{Artifact, Egress, Entry, Mismatch},
+    serde_json::{json, Value},
+};
+
+fn close_with(dir: &std::path::Path, records: Value) -> egress::Report {
+    let mut egress = Egress::open(dir, "key_by").unwrap();
+    let artifact = egress.artifact("records");
+    artifact.insert_json("runs", records);
+    artifact.key_by("runs", "id");
+    egress.close().unwrap()
+}
+
+#[test]
+fn reordered_records_with_one_change() {
+    let dir = tempfile::tempdir().unwrap();
+
+    close_with(
+        dir.path(),
+        json!([
+            { "id": 1, "value": "apple" },
+            { "id": 2, "value": "banana" },
+            { "id": 3, "value": "cherry" },
+        ]),
+    )
+    .assert_unregressed();
+
+    let report = close_with(
+        dir.path(),
+        json!([
+            { "id": 3, "value": "cherry" },
+            { "id": 1, "value": "apple" },
+            { "id": 2, "value": "blueberry" },
+        ]),
+    );
+
+    assert_eq!(report.len(), 1, "{}", report);
+    assert!(matches!(&report.mismatches()[0], Mismatch::NotEq(..)));
+    report.assert_mismatch("records.runs[id=2].value");
+}
+
+#[test]
+fn added_and_removed_records() {
+    let dir = tempfile::tempdir().unwrap();
+
+    close_with(
+        dir.path(),
+        json!([{ "id": "a", "value": 1 }, { "id": "b", "value": 2 }]),
+    )
+    .assert_unregressed();
+
+    let report = close_with(
+        dir.path(),
+        json!([{ "id": "c", "value": 3 }, { "id": "a", "value": 1 }]),
+    );
+
+    assert_eq!(report.len(), 2, "{}", report);
+    assert!(matches!(
+        &report.mismatches()[0],
+        Mismatch::NotInReference(path, _) if path == "records.runs[id=c]"
+    ));
+    assert!(matches!(
+        &report.mismatches()[1],
+        Mismatch::NotProduced(path, _) if path == "records.runs[id=b]"
+    ));
+}
+
+#[test]
+fn key_by_in_nested_artifact() {
+    let nested = |records: Value| {
+        let mut inner = Artifact::new();
+        inner.insert_json("runs", records);
+        let mut outer = Artifact::new();
+        outer.insert("nested", Entry::Artifact(inner));
+        outer
+    };
+
+    let reference = nested(json!([{ "id": 1, "value": "a" }, { "id": 2, "value": "b" }]));
+    let mut reordered = nested(json!([{ "id": 2, "value": "b" }, { "id": 1, "value": "a" }]));
+    assert!(!reordered.diff(&reference, None, None).is_empty());
+
+    reordered.key_by("nested.runs", "id");
+    let mismatches = reordered.diff(&reference, None, None);
+    assert!(mismatches.is_empty(), "{:?}", mismatches);
+}
@@ -0,0 +1,71 @@
+use {egress::Egress, serde::Serialize, std::fs};
+
+#[derive(Serialize)]
+struct Reading {
+    zone: &'static str,
+    temperature: f64,
+    altitude: u32,
+    nested: Nested,
+}
+
+#[derive(Serialize)]
+struct Nested {
+    second: u32,
+    first: u32,
+}
+
+#[test]
+fn object_keys_are_sorted() {
+    let dir = tempfile::tempdir().unwrap();
+    let reading = Reading {
+        zone: "north",
+        temperature: 20.5,
+        altitude: 300,
+        nested: Nested {
+            second: 2,
+            first: 1,
+        },
+    };
+
+    let mut egress = Egress::open(dir.path(), "key_order").unwrap();
+    egress.json_pretty = false;
+    egress
+        .artifact("reading")
+        .insert_serialize("reading", &reading)
+        .unwrap();
+    egress.close().unwrap().assert_unregressed();
+
+    let reference =
+        fs::read_to_string(dir.path().join("egress/artifacts/key_order/reading.json")).unwrap();
+    assert_eq!(
+        reference,
+        r#"{"reading":{"Json":{"altitude":300,"nested":{"first":1,"second":2},"temperature":20.5,"zone":"north"}}}"#
+    );
+}
+
+#[test]
+fn insertion_order_is_preserved() {
+    let dir = tempfile::tempdir().unwrap();
+
+    for steps in [
+        ["step_1", "step_2", "step_10"],
+        ["step_10", "step_2", "step_1"],
+    ] {
+        let mut egress = Egress::open(dir.path(), "key_order").unwrap();
+        egress.json_pretty = false;
+        egress.preserve_order = true;
+        let artifact = egress.artifact("steps");
+        for step in steps {
+            artifact.insert_display(step, &"done");
+        }
+        // Only the first run writes the reference; the order never causes a mismatch.
+        egress.close().unwrap().assert_unregressed();
+    }
+
+    let reference =
+        fs::read_to_string(dir.path().join("egress/artifacts/key_order/steps.json")).unwrap();
+    assert_eq!(
+        reference,
+        r#"{"step_1":{"Str":"done"},"step_2":{"Str":"done"},"step_10":{"Str":"done"}}"#
+    );
+}
@@ -0,0 +1,50 @@
+use egress::{Egress, ErrorKind};
+
+#[test]
+fn same_subdir_errors() {
+    let dir = tempfile::tempdir().unwrap();
+
+    let first = Egress::open(dir.path(), "live").unwrap();
+    assert!(matches!(
+        Egress::open(dir.path(), "live"),
+        Err(ErrorKind::ArtifactSubdirInUse(_))
+    ));
+    let nested = Egress::open(dir.path(), "live/nested").unwrap();
+    let sibling = Egress::open(dir.path(), "lively").unwrap();
+
+    // Closing a context frees its subdirectory up again.
+    first.close().unwrap().assert_unregressed();
+    let mut second = Egress::open(dir.path(), "live").unwrap();
+    second.artifact("fruits").insert_display("fruit", &"apple");
+    second.close().unwrap().assert_unregressed();
+    drop(nested);
+    drop(sibling);
+}
+
+#[test]
+fn built_contexts_claim_on_first_artifact() {
+    let dir = tempfile::tempdir().unwrap();
+    let build = || {
+        Egress::builder()
+            .artifact_dir(dir.path().join("references"))
+            .artifact_subdir("live")
+            .build()
+            .unwrap()
+    };
+
+    let mut first = build();
+    first.artifact("fruits").insert_display("fruit", &"apple");
+
+    let mut second = build();
+    assert!(matches!(
+        second.try_artifact("vegetables"),
+        Err(ErrorKind::ArtifactSubdirInUse(_))
+    ));
+
+    first.close().unwrap().assert_unregressed();
+    second
+        .try_artifact("vegetables")
+        .unwrap()
+        .insert_display("vegetable", &"carrot");
+    second.close().unwrap().assert_unregressed();
+}
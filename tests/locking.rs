@@ -0,0 +1,60 @@
+use {
+    egress::{Artifact, Egress},
+    serde_json::json,
+    std::path::Path,
+};
+
+fn close_with(dir: &Path, subdir: &str, name: &str, value: usize) -> egress::Report {
+    let mut egress = Egress::open(dir, subdir).unwrap();
+    egress.artifact(name).insert_json(
+        "values",
+        json!((0..1000).map(|i| i * value).collect::<Vec<_>>()),
+    );
+    egress.close().unwrap()
+}
+
+#[test]
+fn concurrent_contexts() {
+    let dir = tempfile::tempdir().unwrap();
+
+    // Contexts can't share a subdirectory within a process, but they still share the config
+    // and the artifact directory.
+    std::thread::scope(|scope| {
+        for subdir in ["locking/first", "locking/second"] {
+            let dir = dir.path();
+            scope.spawn(move || {
+                for _ in 0..20 {
+                    close_with(dir, subdir, "values", 1).assert_unregressed();
+                }
+            });
+        }
+    });
+
+    for subdir in ["locking/first", "locking/second"] {
+        close_with(dir.path(), subdir, "values", 1).assert_unregressed();
+    }
+}
+
+#[test]
+fn reading_while_writing() {
+    let dir = tempfile::tempdir().unwrap();
+    let reference = dir.path().join("egress/artifacts/locking/shared.json");
+    close_with(dir.path(), "locking", "shared", 1).assert_unregressed();
+
+    // A reader, like another test process, never sees a reference half written.
+    std::thread::scope(|scope| {
+        let dir = dir.path();
+        scope.spawn(move || {
+            for value in 2..22 {
+                close_with(dir, "locking", "shared", value).bless().unwrap();
+            }
+        });
+        scope.spawn(|| {
+            for _ in 0..200 {
+                Artifact::load(&reference).unwrap();
+            }
+        });
+    });
+
+    close_with(dir.path(), "locking", "shared", 21).assert_unregressed();
+}
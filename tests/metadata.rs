@@ -0,0 +1,40 @@
+use {
+    egress::Egress,
+    serde_json::{json, Value},
+    std::{fs, path::Path},
+};
+
+fn reference(dir: &Path) -> Value {
+    let path = dir.join("egress/artifacts/metadata/fruits.json");
+    serde_json::from_slice(&fs::read(path).unwrap()).unwrap()
+}
+
+#[test]
+fn metadata_written_and_not_compared() {
+    let dir = tempfile::tempdir().unwrap();
+
+    let mut egress = Egress::open(dir.path(), "metadata").unwrap();
+    egress.metadata = true;
+    egress.artifact("fruits").insert_display("fruit", &"apple");
+    egress.close().unwrap().assert_unregressed();
+
+    let metadata = &reference(dir.path())["$metadata"]["Json"];
+    assert_eq!(metadata["egress_version"], env!("CARGO_PKG_VERSION"));
+    assert_eq!(metadata["crate_version"], env!("CARGO_PKG_VERSION"));
+    assert!(metadata["generated_at"].as_u64().unwrap() > 0);
+
+    let mut stale = reference(dir.path());
+    stale["$metadata"]["Json"] = json!({ "crate_version": "0.0.1", "generated_at": 1 });
+    let path = dir.path().join("egress/artifacts/metadata/fruits.json");
+    fs::write(&path, serde_json::to_vec_pretty(&stale).unwrap()).unwrap();
+
+    let mut egress = Egress::open(dir.path(), "metadata").unwrap();
+    egress.metadata = true;
+    egress.artifact("fruits").insert_display("fruit", &"apple");
+    egress.close().unwrap().assert_unregressed();
+
+    // References without metadata still match artifacts with it, and vice versa.
+    let mut egress = Egress::open(dir.path(), "metadata").unwrap();
+    egress.artifact("fruits").insert_display("fruit", &"apple");
+    egress.close().unwrap().assert_unregressed();
+}
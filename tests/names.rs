@@ -0,0 +1,52 @@
+use egress::Egress;
+
+#[test]
+fn nested_subdirectory() {
+    let dir = tempfile::tempdir().unwrap();
+
+    for _ in 0..2 {
+        let mut egress = Egress::open(dir.path(), "names").unwrap();
+        egress
+            .artifact("group/case1")
+            .insert_display("fruit", &"apple");
+        egress.close().unwrap().assert_unregressed();
+    }
+
+    assert!(dir
+        .path()
+        .join("egress/artifacts/names/group/case1.json")
+        .exists());
+}
+
+#[test]
+fn traversal_is_rejected() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut egress = Egress::open(dir.path(), "names").unwrap();
+
+    assert!(egress.try_artifact("../escape").is_err());
+    assert!(egress.try_artifact("group/../../escape").is_err());
+    assert!(egress.try_artifact("/tmp/escape").is_err());
+}
+
+#[test]
+fn dotted_names() {
+    let dir = tempfile::tempdir().unwrap();
+    let references = dir.path().join("egress/artifacts/names");
+
+    for _ in 0..2 {
+        let mut egress = Egress::open(dir.path(), "names").unwrap();
+        egress
+            .artifact("config.prod")
+            .insert_display("fruit", &"apple");
+        egress.close().unwrap().assert_unregressed();
+    }
+
+    assert!(references.join("config.prod.json").exists());
+    assert!(!references.join("config.json").exists());
+
+    let egress = Egress::open(dir.path(), "names").unwrap();
+    assert_eq!(
+        egress.existing_artifacts().unwrap(),
+        vec![std::path::PathBuf::from("config.prod")]
+    );
+}
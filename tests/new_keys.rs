@@ -0,0 +1,46 @@
+use {
+    egress::Egress,
+    serde_json::{json, Value},
+    std::{fs, path::Path},
+};
+
+fn close_with(dir: &Path, values: Value, summary: Option<&str>) -> egress::Report {
+    let mut egress = Egress::open(dir, "new_keys").unwrap();
+    egress.fail_on_new_keys = false;
+    let artifact = egress.artifact("fruits");
+    artifact.insert_json("values", values);
+    if let Some(summary) = summary {
+        artifact.insert_display("summary", &summary);
+    }
+    egress.close().unwrap()
+}
+
+#[test]
+fn new_keys_are_merged() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("egress/artifacts/new_keys/fruits.json");
+
+    close_with(dir.path(), json!({ "apple": 1 }), None).assert_unregressed();
+
+    let report = close_with(dir.path(), json!({ "apple": 1, "pear": 2 }), Some("two"));
+    assert!(report.is_empty(), "{:?}", report.mismatches());
+    let reference: Value = serde_json::from_slice(&fs::read(&path).unwrap()).unwrap();
+    assert_eq!(
+        reference,
+        json!({
+            "summary": { "Str": "two" },
+            "values": { "Json": { "apple": 1, "pear": 2 } },
+        })
+    );
+
+    // Changed and removed keys still fail, while new keys get merged all the same.
+    let report = close_with(dir.path(), json!({ "apple": 3, "plum": 4 }), Some("two"));
+    report.assert_mismatch("fruits.values.apple");
+    report.assert_mismatch("fruits.values.pear");
+    assert!(!report.has_mismatch("fruits.values.plum"));
+    let reference: Value = serde_json::from_slice(&fs::read(&path).unwrap()).unwrap();
+    assert_eq!(
+        reference["values"]["Json"],
+        json!({ "apple": 1, "pear": 2, "plum": 4 })
+    );
+}
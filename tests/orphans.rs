@@ -0,0 +1,31 @@
+use {
+    egress::Egress,
+    std::panic::{self, AssertUnwindSafe},
+};
+
+#[test]
+fn strict_assertion_fails_on_orphans() {
+    let dir = tempfile::tempdir().unwrap();
+
+    let mut egress = Egress::open(dir.path(), "orphans").unwrap();
+    egress.artifact("kept").insert_display("fruit", &"apple");
+    egress.artifact("renamed").insert_display("fruit", &"pear");
+    egress.close().unwrap().assert_unregressed_strict();
+
+    let close = || {
+        let mut egress = Egress::open(dir.path(), "orphans").unwrap();
+        egress.artifact("kept").insert_display("fruit", &"apple");
+        egress.close().unwrap()
+    };
+
+    let report = close();
+    assert_eq!(report.orphans(), ["renamed"]);
+    report.assert_unregressed();
+
+    let result = panic::catch_unwind(AssertUnwindSafe(|| close().assert_unregressed_strict()));
+    assert!(result.is_err());
+    assert!(dir
+        .path()
+        .join("egress/artifacts/orphans/renamed.json")
+        .exists());
+}
@@ -0,0 +1,50 @@
+use {
+    egress::Egress,
+    serde_json::json,
+    std::{fs, path::Path},
+};
+
+fn close_with(dir: &Path, json_pretty: bool, indent: Option<usize>) -> egress::Report {
+    let mut egress = Egress::open(dir, "pretty").unwrap();
+    egress.json_pretty = json_pretty;
+    egress.indent = indent;
+    let artifact = egress.artifact("artifact");
+    artifact.insert_json(
+        "result",
+        json!({ "fruits": ["apple", "banana"], "count": 2 }),
+    );
+    artifact.insert_display("summary", &"two fruits");
+    egress.close().unwrap()
+}
+
+fn reference(dir: &Path) -> String {
+    fs::read_to_string(dir.join("egress/artifacts/pretty/artifact.json")).unwrap()
+}
+
+#[test]
+fn compact_matches_pretty_reference() {
+    let dir = tempfile::tempdir().unwrap();
+
+    close_with(dir.path(), true, None).assert_unregressed();
+    assert!(reference(dir.path()).contains('\n'));
+    close_with(dir.path(), false, None).assert_unregressed();
+}
+
+#[test]
+fn pretty_matches_compact_reference() {
+    let dir = tempfile::tempdir().unwrap();
+
+    close_with(dir.path(), false, None).assert_unregressed();
+    assert!(!reference(dir.path()).contains('\n'));
+    close_with(dir.path(), true, None).assert_unregressed();
+}
+
+#[test]
+fn indent() {
+    let dir = tempfile::tempdir().unwrap();
+
+    close_with(dir.path(), true, Some(4)).assert_unregressed();
+    let reference = reference(dir.path());
+    assert!(reference.starts_with("{\n    \""), "{}", reference);
+    close_with(dir.path(), true, None).assert_unregressed();
+}
@@ -0,0 +1,44 @@
+use egress::Egress;
+
+#[test]
+fn prune_orphans() {
+    let dir = tempfile::tempdir().unwrap();
+    let references = dir.path().join("egress/artifacts/prune");
+
+    let mut egress = Egress::open(dir.path(), "prune").unwrap();
+    egress.artifact("kept").insert_display("fruit", &"apple");
+    egress.artifact("orphan").insert_display("fruit", &"pear");
+    egress
+        .artifact("group/orphan")
+        .insert_display("fruit", &"plum");
+    egress
+        .artifact("group/kept")
+        .insert_display("fruit", &"fig");
+    egress.close().unwrap().assert_unregressed();
+
+    // Another context's references, nested in this one's directory.
+    let mut egress = Egress::open(dir.path(), "prune/other").unwrap();
+    egress
+        .artifact("unrelated")
+        .insert_display("fruit", &"kiwi");
+    egress.close().unwrap().assert_unregressed();
+
+    // Plain `close` leaves orphans alone.
+    let mut egress = Egress::open(dir.path(), "prune").unwrap();
+    egress.artifact("kept").insert_display("fruit", &"apple");
+    egress.close().unwrap().assert_unregressed();
+    assert!(references.join("orphan.json").exists());
+
+    let mut egress = Egress::open(dir.path(), "prune").unwrap();
+    egress.artifact("kept").insert_display("fruit", &"apple");
+    egress
+        .artifact("group/kept")
+        .insert_display("fruit", &"fig");
+    egress.close_and_prune().unwrap().assert_unregressed();
+
+    assert!(references.join("kept.json").exists());
+    assert!(!references.join("orphan.json").exists());
+    assert!(references.join("group/kept.json").exists());
+    assert!(!references.join("group/orphan.json").exists());
+    assert!(references.join("other/unrelated.json").exists());
+}
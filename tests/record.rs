@@ -0,0 +1,42 @@
+use {egress::Egress, std::fs};
+
+#[test]
+fn record_mode_overwrites_every_reference() {
+    let dir = tempfile::tempdir().unwrap();
+    let subdir = dir.path().join("egress/artifacts/record");
+
+    let mut egress = Egress::open(dir.path(), "record").unwrap();
+    egress.artifact("fruits").insert_display("fruit", &"apple");
+    egress
+        .artifact("vegetables")
+        .insert_display("vegetable", &"carrot");
+    egress.close().unwrap().assert_unregressed();
+
+    // A reference which matches, but is formatted differently.
+    fs::write(
+        subdir.join("vegetables.json"),
+        r#"{"vegetable":{"Str":"carrot"}}"#,
+    )
+    .unwrap();
+
+    std::env::set_var("EGRESS_RECORD", "1");
+    let mut egress = Egress::open(dir.path(), "record").unwrap();
+    egress.artifact("fruits").insert_display("fruit", &"pear");
+    egress
+        .artifact("vegetables")
+        .insert_display("vegetable", &"carrot");
+    let report = egress.close().unwrap();
+    std::env::remove_var("EGRESS_RECORD");
+
+    assert!(report.is_empty());
+    assert!(fs::read_to_string(subdir.join("fruits.json"))
+        .unwrap()
+        .contains("pear"));
+    assert!(fs::read_to_string(subdir.join("vegetables.json"))
+        .unwrap()
+        .contains('\n'));
+
+    let mut egress = Egress::open(dir.path(), "record").unwrap();
+    egress.artifact("fruits").insert_display("fruit", &"pear");
+    egress.close().unwrap().assert_unregressed();
+}
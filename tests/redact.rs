@@ -0,0 +1,41 @@
+use {
+    egress::{Egress, Entry},
+    serde::Serialize,
+    std::{
+        collections::hash_map::RandomState,
+        fs,
+        hash::{BuildHasher, Hasher},
+    },
+};
+
+#[derive(Serialize)]
+struct Session {
+    user: &'static str,
+    id: u64,
+}
+
+fn random_id() -> u64 {
+    RandomState::new().build_hasher().finish()
+}
+
+#[test]
+fn redacted_field() {
+    let dir = tempfile::tempdir().unwrap();
+
+    for _ in 0..2 {
+        let mut egress = Egress::open(dir.path(), "redact").unwrap();
+        let artifact = egress.artifact("session");
+        let session = Session {
+            user: "alice",
+            id: random_id(),
+        };
+        artifact.insert_serialize("session", &session).unwrap();
+        artifact.redact("session.id", Entry::Str("<id>".to_owned()));
+        egress.close().unwrap().assert_unregressed();
+    }
+
+    let stored =
+        fs::read_to_string(dir.path().join("egress/artifacts/redact/session.json")).unwrap();
+    assert!(stored.contains(r#""id": "<id>""#), "{}", stored);
+    assert!(stored.contains(r#""user": "alice""#), "{}", stored);
+}
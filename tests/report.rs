@@ -0,0 +1,421 @@
+use egress::{Artifact, Egress, Entry, Mismatch, MismatchKind, Report, Summary};
+
+#[test]
+fn assert_mismatch() {
+    let dir = tempfile::tempdir().unwrap();
+
+    let mut egress = Egress::open(dir.path(), "report").unwrap();
+    let artifact = egress.artifact("fruits");
+    artifact.insert_display("fruit", &"apple");
+    artifact.insert_display("vegetable", &"carrot");
+    egress.close().unwrap().assert_unregressed();
+
+    let mut egress = Egress::open(dir.path(), "report").unwrap();
+    let artifact = egress.artifact("fruits");
+    artifact.insert_display("fruit", &"pear");
+    artifact.insert_display("vegetable", &"carrot");
+    let report = egress.close().unwrap();
+
+    assert!(report.has_mismatch("fruits.fruit"));
+    assert!(!report.has_mismatch("fruits.vegetable"));
+    report.assert_mismatch("fruits.fruit");
+}
+
+#[test]
+#[should_panic(expected = "expected a mismatch at `fruits.fruit`")]
+fn assert_mismatch_panics_when_unregressed() {
+    let dir = tempfile::tempdir().unwrap();
+
+    let mut egress = Egress::open(dir.path(), "report").unwrap();
+    egress.artifact("fruits").insert_display("fruit", &"apple");
+    egress.close().unwrap().assert_mismatch("fruits.fruit");
+}
+
+#[test]
+fn bless() {
+    let dir = tempfile::tempdir().unwrap();
+
+    let mut egress = Egress::open(dir.path(), "report").unwrap();
+    egress.artifact("fruits").insert_display("fruit", &"apple");
+    egress
+        .artifact("vegetables")
+        .insert_display("vegetable", &"carrot");
+    egress.close().unwrap().assert_unregressed();
+
+    let mut egress = Egress::open(dir.path(), "report").unwrap();
+    egress.artifact("fruits").insert_display("fruit", &"pear");
+    egress
+        .artifact("vegetables")
+        .insert_display("vegetable", &"carrot");
+    let report = egress.close().unwrap();
+    report.assert_mismatch("fruits.fruit");
+    report.bless().unwrap();
+
+    let mut egress = Egress::open(dir.path(), "report").unwrap();
+    egress.artifact("fruits").insert_display("fruit", &"pear");
+    egress
+        .artifact("vegetables")
+        .insert_display("vegetable", &"carrot");
+    egress.close().unwrap().assert_unregressed();
+}
+
+#[test]
+fn inspect_mismatches() {
+    let dir = tempfile::tempdir().unwrap();
+
+    let mut egress = Egress::open(dir.path(), "report").unwrap();
+    let artifact = egress.artifact("fruits");
+    artifact.insert_display("fruit", &"apple");
+    artifact.insert_display("vegetable", &"carrot");
+    egress.close().unwrap().assert_unregressed();
+
+    let mut egress = Egress::open(dir.path(), "report").unwrap();
+    let artifact = egress.artifact("fruits");
+    artifact.insert_display("fruit", &"pear");
+    artifact.insert_display("grain", &"rice");
+    let report = egress.close().unwrap();
+
+    assert!(!report.is_empty());
+    assert_eq!(report.len(), 3);
+
+    let mut kinds = report
+        .mismatches()
+        .iter()
+        .map(|mismatch| match mismatch {
+            Mismatch::NotEq(path, ..) => format!("changed {}", path),
+            Mismatch::NotInReference(path, _) => format!("added {}", path),
+            Mismatch::NotProduced(path, _) => format!("removed {}", path),
+            Mismatch::LengthMismatch(path, ..) => format!("resized {}", path),
+            Mismatch::MissingReference(path) => format!("missing {}", path),
+        })
+        .collect::<Vec<_>>();
+    kinds.sort();
+
+    assert_eq!(
+        kinds,
+        [
+            "added fruits.grain",
+            "changed fruits.fruit",
+            "removed fruits.vegetable"
+        ]
+    );
+}
+
+#[test]
+fn mismatch_paths_and_kinds() {
+    let dir = tempfile::tempdir().unwrap();
+
+    let mut egress = Egress::open(dir.path(), "report").unwrap();
+    let artifact = egress.artifact("fruits");
+    artifact.insert_display("fruit", &"apple");
+    artifact.insert_display("vegetable", &"carrot");
+    egress.close().unwrap().assert_unregressed();
+
+    let mut egress = Egress::open(dir.path(), "report").unwrap();
+    let artifact = egress.artifact("fruits");
+    artifact.insert_display("fruit", &"pear");
+    artifact.insert_display("grain", &"rice");
+    let report = egress.close().unwrap();
+
+    let mut paths = report
+        .mismatches()
+        .iter()
+        .map(|mismatch| (mismatch.path(), mismatch.kind()))
+        .collect::<Vec<_>>();
+    paths.sort_by_key(|&(path, _)| path);
+
+    assert_eq!(
+        paths,
+        [
+            ("fruits.fruit", MismatchKind::NotEq),
+            ("fruits.grain", MismatchKind::NotInReference),
+            ("fruits.vegetable", MismatchKind::NotProduced),
+        ]
+    );
+}
+
+#[test]
+fn display() {
+    let dir = tempfile::tempdir().unwrap();
+
+    let mut egress = Egress::open(dir.path(), "report").unwrap();
+    let artifact = egress.artifact("fruits");
+    artifact.insert_display("fruit", &"apple");
+    artifact.insert_display("vegetable", &"carrot");
+    egress.close().unwrap().assert_unregressed();
+
+    let mut egress = Egress::open(dir.path(), "report").unwrap();
+    egress.artifact("fruits").insert_display("fruit", &"pear");
+    let report = egress.close().unwrap();
+    let displayed = report.to_string();
+
+    // Key paths and diffs may be colored, so only look for the plain text around them.
+    assert!(displayed.contains("fruits.fruit"));
+    assert!(displayed.contains("not the same as the reference value"));
+    assert!(displayed.contains("fruits.vegetable"));
+    assert!(displayed.contains("exists in the reference but was not found here"));
+    assert_eq!(
+        displayed
+            .lines()
+            .filter(|line| line.starts_with("MISMATCH: "))
+            .count(),
+        2
+    );
+}
+
+#[test]
+fn by_artifact() {
+    let dir = tempfile::tempdir().unwrap();
+    let close_with = |fruit: &str, vegetable: &str| {
+        let mut egress = Egress::open(dir.path(), "report").unwrap();
+        let fruits = egress.artifact("fruits");
+        fruits.insert_display("first", &fruit);
+        fruits.insert_display("second", &fruit);
+        egress
+            .artifact("vegetables")
+            .insert_display("first", &vegetable);
+        egress.artifact("grains").insert_display("first", &"rice");
+        egress.close().unwrap()
+    };
+    close_with("apple", "carrot").assert_unregressed();
+
+    let report = close_with("pear", "leek");
+    let grouped = report.by_artifact();
+
+    assert_eq!(grouped.len(), 2);
+    assert_eq!(grouped["fruits"].len(), 2);
+    assert_eq!(grouped["vegetables"].len(), 1);
+    assert_eq!(grouped["vegetables"][0].path(), "vegetables.first");
+
+    let displayed = report.to_string();
+    assert_eq!(displayed.matches("In artifact `fruits`:").count(), 1);
+    assert_eq!(displayed.matches("In artifact `vegetables`:").count(), 1);
+    assert!(!displayed.contains("grains"));
+}
+
+#[test]
+fn junit_xml() {
+    let dir = tempfile::tempdir().unwrap();
+
+    let mut egress = Egress::open(dir.path(), "report").unwrap();
+    let artifact = egress.artifact("fruits");
+    artifact.insert_display("fruit", &"apple");
+    artifact.insert_display("vegetable", &"carrot");
+    artifact.insert_display("sauce", &"<ketchup & mustard>");
+    let report = egress.close().unwrap();
+
+    let xml = report.to_junit_xml("egress");
+    let doc = roxmltree::Document::parse(&xml).unwrap();
+    let suite = doc.root_element();
+    assert_eq!(suite.tag_name().name(), "testsuite");
+    assert_eq!(suite.attribute("failures"), Some("0"));
+    assert_eq!(
+        suite
+            .descendants()
+            .filter(|n| n.has_tag_name("failure"))
+            .count(),
+        0
+    );
+
+    let mut egress = Egress::open(dir.path(), "report").unwrap();
+    let artifact = egress.artifact("fruits");
+    artifact.insert_display("fruit", &"pear");
+    artifact.insert_display("vegetable", &"carrot");
+    artifact.insert_display("sauce", &"<mayonnaise>");
+    let report = egress.close().unwrap();
+
+    let xml = report.to_junit_xml("egress");
+    let doc = roxmltree::Document::parse(&xml).unwrap();
+    let suite = doc.root_element();
+    let failures = suite
+        .descendants()
+        .filter(|n| n.has_tag_name("failure"))
+        .collect::<Vec<_>>();
+    assert_eq!(failures.len(), report.len());
+    assert_eq!(suite.attribute("failures"), Some("2"));
+    assert!(suite
+        .children()
+        .any(|n| n.attribute("name") == Some("fruits.sauce")));
+    assert!(failures
+        .iter()
+        .any(|n| n.text().unwrap().contains("<ketchup & mustard>")));
+}
+
+#[test]
+fn into_result() {
+    let dir = tempfile::tempdir().unwrap();
+
+    let mut egress = Egress::open(dir.path(), "report").unwrap();
+    egress.artifact("fruits").insert_display("fruit", &"apple");
+    egress.close().unwrap().into_result().unwrap();
+
+    let mut egress = Egress::open(dir.path(), "report").unwrap();
+    egress.artifact("fruits").insert_display("fruit", &"pear");
+    let report = egress.close().unwrap().into_result().unwrap_err();
+
+    assert_eq!(report.len(), 1);
+    assert!(report.has_mismatch("fruits.fruit"));
+}
+
+#[test]
+fn max_reported_mismatches() {
+    let dir = tempfile::tempdir().unwrap();
+    let fruits = |fruit: &'static str| vec![fruit; 20];
+
+    let mut egress = Egress::open(dir.path(), "report").unwrap();
+    egress
+        .artifact("fruits")
+        .insert_serialize("list", &fruits("apple"))
+        .unwrap();
+    egress.close().unwrap().assert_unregressed();
+
+    let mut egress = Egress::open(dir.path(), "report").unwrap();
+    egress.max_reported_mismatches = Some(5);
+    egress
+        .artifact("fruits")
+        .insert_serialize("list", &fruits("pear"))
+        .unwrap();
+    let report = egress.close().unwrap();
+
+    assert_eq!(report.len(), 20);
+    let printed = report.to_string();
+    assert_eq!(printed.matches("MISMATCH: ").count(), 5);
+    assert!(printed.ends_with("... and 15 more"), "{}", printed);
+}
+
+#[test]
+fn merge() {
+    let dir = tempfile::tempdir().unwrap();
+
+    let close_with = |subdir: &str, fruit: &str| {
+        let mut egress = Egress::open(dir.path(), subdir).unwrap();
+        egress.artifact("fruits").insert_display("fruit", &fruit);
+        egress.close().unwrap()
+    };
+    close_with("first", "apple").assert_unregressed();
+    close_with("second", "apple").assert_unregressed();
+
+    let passing = close_with("first", "apple");
+    let failing = close_with("second", "pear");
+    let merged = passing.merge(failing);
+    assert_eq!(merged.len(), 1);
+    assert!(merged.clone().into_result().is_err());
+
+    let merged = vec![close_with("first", "pear"), close_with("second", "pear")]
+        .into_iter()
+        .collect::<egress::Report>();
+    assert_eq!(merged.len(), 2);
+    assert_eq!(merged.to_string().matches("MISMATCH: ").count(), 2);
+
+    merged.bless().unwrap();
+    close_with("first", "pear").assert_unregressed();
+    close_with("second", "pear").assert_unregressed();
+}
+
+#[test]
+fn summary_counts() {
+    let dir = tempfile::tempdir().unwrap();
+
+    let mut egress = Egress::open(dir.path(), "report_summary").unwrap();
+    egress.artifact("same").insert_display("fruit", &"apple");
+    egress.artifact("changed").insert_display("fruit", &"pear");
+    let report = egress.close().unwrap();
+    assert_eq!(
+        report.summary(),
+        Summary {
+            artifacts: 2,
+            created: 2,
+            compared: 0,
+            regressed: 0,
+        }
+    );
+
+    let mut egress = Egress::open(dir.path(), "report_summary").unwrap();
+    egress.verbose = true;
+    egress.artifact("same").insert_display("fruit", &"apple");
+    egress.artifact("changed").insert_display("fruit", &"plum");
+    egress.artifact("new").insert_display("fruit", &"fig");
+    let report = egress.close().unwrap();
+    assert_eq!(
+        report.summary().to_string(),
+        "3 artifacts, 1 created, 2 compared, 1 regressed"
+    );
+}
+
+#[test]
+fn display_previews_missing_and_new_values() {
+    let missing = Mismatch::NotProduced(
+        "fruits.vegetable".to_owned(),
+        Entry::Str("carrot".to_owned()),
+    );
+    assert!(missing.to_string().contains("\"carrot\""));
+
+    let huge = Mismatch::NotInReference(
+        "fruits.weights".to_owned(),
+        Entry::Json(serde_json::json!(vec![1234; 1000])),
+    );
+    let displayed = huge.to_string();
+    assert!(displayed.contains("[1234,1234,"));
+    assert!(displayed.contains("..."));
+    assert!(displayed.len() < 200, "{}", displayed);
+}
+
+#[test]
+fn report_file() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(
+        dir.path().join("Egress.toml"),
+        "artifact_dir = \"egress/artifacts/\"\natol = 0.0\nrtol = 0.0\nreport_path = \"egress/last_report.json\"\n",
+    )
+    .unwrap();
+    let report_path = dir.path().join("egress/last_report.json");
+    let read_back = || -> Report {
+        serde_json::from_str(&std::fs::read_to_string(&report_path).unwrap()).unwrap()
+    };
+
+    let mut egress = Egress::open(dir.path(), "report_file").unwrap();
+    egress.artifact("fruits").insert_display("fruit", &"apple");
+    egress.close().unwrap().assert_unregressed();
+    assert!(read_back().is_empty());
+
+    let mut egress = Egress::open(dir.path(), "report_file").unwrap();
+    egress.artifact("fruits").insert_display("fruit", &"pear");
+    let report = egress.close().unwrap();
+    assert_eq!(report.len(), 1);
+    assert_eq!(read_back().mismatches(), report.mismatches());
+}
+
+#[test]
+fn nested_artifact_paths() {
+    let dir = tempfile::tempdir().unwrap();
+    let close = |fruit: &str, count: u32| {
+        let mut inner = Artifact::new();
+        inner.insert_display("fruit", &fruit);
+        inner.insert_json("counts", serde_json::json!([1, count]));
+        let mut middle = Artifact::new();
+        middle.insert("inner", Entry::Artifact(inner));
+
+        let mut egress = Egress::open(dir.path(), "report").unwrap();
+        egress
+            .artifact("group/outer")
+            .insert("middle", Entry::Artifact(middle));
+        egress.close().unwrap()
+    };
+
+    close("apple", 2).assert_unregressed();
+    let report = close("pear", 3);
+
+    let paths = report
+        .mismatches()
+        .iter()
+        .map(Mismatch::path)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        paths,
+        [
+            "group/outer.middle.inner.counts[1]",
+            "group/outer.middle.inner.fruit",
+        ]
+    );
+    assert_eq!(report.by_artifact()["group/outer"].len(), 2);
+}
@@ -0,0 +1,42 @@
+#![cfg(feature = "schema")]
+
+use {egress::Artifact, serde_json::json};
+
+#[test]
+fn validate_schema() {
+    let schema = json!({
+        "type": "object",
+        "properties": {
+            "summary": { "type": "string" },
+            "result": {
+                "type": "object",
+                "properties": {
+                    "count": { "type": "integer" },
+                    "fruits": { "type": "array", "items": { "type": "string" } },
+                },
+                "required": ["count"],
+            },
+        },
+        "required": ["result"],
+    });
+
+    let mut artifact = Artifact::new();
+    artifact.insert_display("summary", &"two fruits");
+    artifact.insert_json("result", json!({ "count": 2, "fruits": ["apple", "pear"] }));
+    artifact.validate_schema(&schema).unwrap();
+
+    let mut artifact = Artifact::new();
+    artifact.insert_display("summary", &"two fruits");
+    artifact.insert_json("result", json!({ "count": "two", "fruits": ["apple", 2] }));
+    let errors = artifact.validate_schema(&schema).unwrap_err();
+
+    assert_eq!(errors.len(), 2, "{:?}", errors);
+    assert!(errors.iter().any(|err| err.starts_with("result.count: ")));
+    assert!(errors
+        .iter()
+        .any(|err| err.starts_with("result.fruits[1]: ")));
+
+    let errors = Artifact::new().validate_schema(&schema).unwrap_err();
+    assert_eq!(errors.len(), 1, "{:?}", errors);
+    assert!(errors[0].contains("\"result\""), "{:?}", errors);
+}
@@ -0,0 +1,57 @@
+use {
+    egress::{Egress, ErrorKind},
+    serde::Serialize,
+    serde_json::{json, Value},
+    std::fs,
+};
+
+#[derive(Serialize)]
+struct Fruit {
+    name: &'static str,
+    count: u32,
+}
+
+#[test]
+fn insert_serialize_all() {
+    let dir = tempfile::tempdir().unwrap();
+    let fruits = || {
+        vec![
+            Fruit {
+                name: "apple",
+                count: 1,
+            },
+            Fruit {
+                name: "pear",
+                count: 2,
+            },
+        ]
+    };
+
+    let mut egress = Egress::open(dir.path(), "serialize_all").unwrap();
+    egress
+        .artifact("fruits")
+        .insert_serialize_all("fruit", fruits())
+        .unwrap();
+    egress.close().unwrap().assert_unregressed();
+
+    let path = dir
+        .path()
+        .join("egress/artifacts/serialize_all/fruits.json");
+    let reference: Value = serde_json::from_slice(&fs::read(path).unwrap()).unwrap();
+    assert_eq!(
+        reference,
+        json!({
+            "fruit[0]": { "Json": { "name": "apple", "count": 1 } },
+            "fruit[1]": { "Json": { "name": "pear", "count": 2 } },
+        })
+    );
+
+    let mut egress = Egress::open(dir.path(), "serialize_all").unwrap();
+    let artifact = egress.artifact("fruits");
+    artifact.insert_serialize_all("fruit", fruits()).unwrap();
+    let err = artifact
+        .insert_serialize_all("fruit", fruits())
+        .unwrap_err();
+    assert!(matches!(err, ErrorKind::DuplicateEntry(key) if key == "fruit[0]"));
+    egress.close().unwrap().assert_unregressed();
+}
@@ -0,0 +1,53 @@
+use {
+    egress::{Egress, ErrorKind},
+    serde_json::json,
+};
+
+/// A handle which doesn't implement `Serialize`, and whose address changes from run to run.
+struct Handle {
+    ptr: *const u8,
+    label: &'static str,
+}
+
+static BUFFER: [u8; 4] = [1, 2, 3, 4];
+
+#[test]
+fn custom_serializer() {
+    let dir = tempfile::tempdir().unwrap();
+    let close = |offset: usize| {
+        let handle = Handle {
+            ptr: BUFFER[offset..].as_ptr(),
+            label: "buffer",
+        };
+        let mut egress = Egress::open(dir.path(), "serialize_with").unwrap();
+        egress
+            .artifact("handle")
+            .insert_serialize_with("handle", &handle, |handle| {
+                Ok(json!({ "label": handle.label, "null": handle.ptr.is_null() }))
+            })
+            .unwrap();
+        egress.close().unwrap()
+    };
+
+    close(0).assert_unregressed();
+    // Only the stable parts of the handle are stored, so a different address still matches.
+    close(1).assert_unregressed();
+
+    let reference = std::fs::read_to_string(
+        dir.path()
+            .join("egress/artifacts/serialize_with/handle.json"),
+    )
+    .unwrap();
+    assert!(reference.contains("\"label\": \"buffer\""));
+}
+
+#[test]
+fn errors_are_passed_on() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut egress = Egress::open(dir.path(), "serialize_with").unwrap();
+    let artifact = egress.artifact("failing");
+    let result = artifact.insert_serialize_with("value", &(), |()| {
+        Err(ErrorKind::DuplicateEntry("value".to_owned()))
+    });
+    assert!(matches!(result, Err(ErrorKind::DuplicateEntry(_))));
+}
@@ -0,0 +1,81 @@
+use {egress::Egress, std::fs};
+
+fn close(dir: &std::path::Path, big: &[u8]) -> egress::Report {
+    let mut egress = Egress::open(dir, "sidecar").unwrap();
+    egress.inline_threshold_bytes = Some(64);
+    let artifact = egress.artifact("blobs");
+    artifact.insert_bytes("small", &[1, 2, 3]);
+    artifact.insert_bytes("large", big);
+    egress.close().unwrap()
+}
+
+#[test]
+fn large_entries_become_sidecars() {
+    let dir = tempfile::tempdir().unwrap();
+    let references = dir.path().join("egress/artifacts/sidecar");
+    let big = vec![7; 1024];
+
+    close(dir.path(), &big).assert_unregressed();
+
+    let sidecar = references.join("blobs.large.bin");
+    assert_eq!(fs::read(&sidecar).unwrap(), big);
+    assert!(!references.join("blobs.small.bin").exists());
+    let reference = fs::read_to_string(references.join("blobs.json")).unwrap();
+    assert!(reference.contains("Sidecar"), "{}", reference);
+    assert!(reference.contains("blobs.large.bin"), "{}", reference);
+    assert!(reference.len() < 512, "{}", reference);
+
+    close(dir.path(), &big).assert_unregressed();
+
+    let mut changed = big.clone();
+    changed[100] = 8;
+    let report = close(dir.path(), &changed);
+    report.assert_mismatch("blobs.large");
+    report.bless().unwrap();
+    assert_eq!(fs::read(&sidecar).unwrap(), changed);
+
+    close(dir.path(), &changed).assert_unregressed();
+}
+
+#[test]
+fn unchanged_sidecars_are_not_read() {
+    let dir = tempfile::tempdir().unwrap();
+    let big = vec![7; 1024];
+
+    close(dir.path(), &big).assert_unregressed();
+
+    // Matching hashes mean the sidecar never needs to be opened.
+    fs::remove_file(dir.path().join("egress/artifacts/sidecar/blobs.large.bin")).unwrap();
+    close(dir.path(), &big).assert_unregressed();
+}
+
+#[test]
+fn dotted_keys_get_their_own_sidecars() {
+    let dir = tempfile::tempdir().unwrap();
+    let references = dir.path().join("egress/artifacts/sidecar");
+    let close = |dotted: &str, nested: &str| {
+        let mut egress = Egress::open(dir.path(), "sidecar").unwrap();
+        egress.inline_threshold_bytes = Some(8);
+        let artifact = egress.artifact("logs");
+        artifact.insert_display("a.b", &dotted);
+        let mut inner = egress::Artifact::new();
+        inner.insert_display("b", &nested);
+        artifact.insert("a", egress::Entry::Artifact(inner));
+        egress.close().unwrap()
+    };
+
+    close("the dotted key's log", "the nested key's log").assert_unregressed();
+    assert_eq!(
+        fs::read_to_string(references.join("logs.a%2Eb.txt")).unwrap(),
+        "the dotted key's log"
+    );
+    assert_eq!(
+        fs::read_to_string(references.join("logs.a.b.txt")).unwrap(),
+        "the nested key's log"
+    );
+
+    close("the dotted key's log", "the nested key's log").assert_unregressed();
+    let report = close("the dotted key's log", "a changed nested log");
+    assert_eq!(report.len(), 1, "{}", report);
+    report.assert_mismatch("logs.a.b");
+}
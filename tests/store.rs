@@ -0,0 +1,49 @@
+use {
+    egress::{ArtifactStore, Egress, MemoryStore},
+    serde_json::json,
+};
+
+#[test]
+fn memory_store_round_trip() {
+    let dir = tempfile::tempdir().unwrap();
+    let store = MemoryStore::new();
+    let reference = dir.path().join("references/store/answer.json");
+    let build = || {
+        Egress::builder()
+            .artifact_dir(dir.path().join("references"))
+            .artifact_subdir("store")
+            .store(store.clone())
+            .build()
+            .unwrap()
+    };
+
+    let mut egress = build();
+    egress.artifact("answer").insert_json("x", json!(42));
+    let report = egress.close().unwrap();
+    assert_eq!(report.summary().created, 1);
+    report.assert_unregressed();
+
+    assert!(store.exists(&reference));
+    assert!(!dir.path().join("references").exists());
+
+    let mut egress = build();
+    egress.artifact("answer").insert_json("x", json!(42));
+    let report = egress.close().unwrap();
+    assert_eq!(report.summary().compared, 1);
+    report.assert_unregressed();
+
+    let mut egress = build();
+    egress.artifact("answer").insert_json("x", json!(43));
+    let report = egress.close().unwrap();
+    report.assert_mismatch("answer.x");
+    report.bless().unwrap();
+
+    let blessed: serde_json::Value =
+        serde_json::from_slice(&store.read(&reference).unwrap()).unwrap();
+    assert_eq!(blessed["x"], json!({ "Json": 43 }));
+    assert!(!dir.path().join("references").exists());
+
+    let mut egress = build();
+    egress.artifact("answer").insert_json("x", json!(43));
+    egress.close().unwrap().assert_unregressed();
+}
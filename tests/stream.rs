@@ -0,0 +1,114 @@
+use {
+    egress::{Egress, Mismatch},
+    std::cell::Cell,
+};
+
+const LEN: u64 = 100_000;
+
+/// Close a context with `items` streamed into it, returning the report and how many items were
+/// pulled from the iterator.
+fn close_with<I: Iterator<Item = u64>>(dir: &std::path::Path, items: I) -> (egress::Report, u64) {
+    let pulled = Cell::new(0);
+    let mut egress = Egress::open(dir, "stream").unwrap();
+    egress
+        .artifact("sequence")
+        .insert_stream("values", items.inspect(|_| pulled.set(pulled.get() + 1)))
+        .unwrap();
+    (egress.close().unwrap(), pulled.get())
+}
+
+#[test]
+fn matching_stream() {
+    let dir = tempfile::tempdir().unwrap();
+
+    let (report, pulled) = close_with(dir.path(), 0..LEN);
+    report.assert_unregressed();
+    assert_eq!(pulled, LEN);
+
+    let (report, pulled) = close_with(dir.path(), 0..LEN);
+    report.assert_unregressed();
+    assert_eq!(pulled, LEN);
+}
+
+#[test]
+fn early_mismatch_stops_the_stream() {
+    let dir = tempfile::tempdir().unwrap();
+    close_with(dir.path(), 0..LEN).0.assert_unregressed();
+
+    let changed = (0..LEN).map(|i| if i == 10 { 1000 } else { i });
+    let (report, pulled) = close_with(dir.path(), changed);
+
+    assert_eq!(
+        pulled, 11,
+        "the rest of the stream shouldn't have been produced"
+    );
+    assert_eq!(report.len(), 1, "{}", report);
+    assert!(matches!(&report.mismatches()[0], Mismatch::NotEq(..)));
+    report.assert_mismatch("sequence.values[10]");
+
+    // Blessing an incomplete stream mustn't truncate its reference.
+    report.bless().unwrap();
+    close_with(dir.path(), 0..LEN).0.assert_unregressed();
+}
+
+#[test]
+fn longer_stream_stops_past_the_reference() {
+    let dir = tempfile::tempdir().unwrap();
+    close_with(dir.path(), 0..100).0.assert_unregressed();
+
+    let (report, pulled) = close_with(dir.path(), 0..LEN);
+
+    assert_eq!(pulled, 101);
+    assert_eq!(report.len(), 1, "{}", report);
+    assert!(matches!(
+        &report.mismatches()[0],
+        Mismatch::NotInReference(..)
+    ));
+    report.assert_mismatch("sequence.values[100]");
+}
+
+#[test]
+fn shorter_stream() {
+    let dir = tempfile::tempdir().unwrap();
+    close_with(dir.path(), 0..100).0.assert_unregressed();
+
+    let (report, pulled) = close_with(dir.path(), 0..99);
+
+    assert_eq!(pulled, 99);
+    assert!(report
+        .mismatches()
+        .iter()
+        .any(|mismatch| matches!(mismatch, Mismatch::LengthMismatch(..))));
+}
+
+#[test]
+fn capture_after_a_truncated_stream_writes_nothing() {
+    let dir = tempfile::tempdir().unwrap();
+    close_with(dir.path(), 0..10).0.assert_unregressed();
+    let reference = dir.path().join("egress/artifacts/stream/sequence.json");
+    let before = std::fs::read(&reference).unwrap();
+
+    let mut egress = Egress::open(dir.path(), "stream").unwrap();
+    egress
+        .artifact("sequence")
+        .insert_stream("values", (0..10).map(|i| i * 100))
+        .unwrap();
+    egress.capture_mode();
+    match egress.close() {
+        Err(egress::ErrorKind::TruncatedStream(name)) => assert_eq!(name, "sequence"),
+        other => panic!("expected a truncated stream error, got {:?}", other),
+    }
+    assert_eq!(std::fs::read(&reference).unwrap(), before);
+
+    // Switched on first, capture mode consumes the whole stream.
+    let mut egress = Egress::open(dir.path(), "stream").unwrap();
+    egress.capture_mode();
+    egress
+        .artifact("sequence")
+        .insert_stream("values", (0..10).map(|i| i * 100))
+        .unwrap();
+    egress.close().unwrap().assert_unregressed();
+    let (report, pulled) = close_with(dir.path(), (0..10).map(|i| i * 100));
+    report.assert_unregressed();
+    assert_eq!(pulled, 10);
+}
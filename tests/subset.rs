@@ -0,0 +1,45 @@
+use {egress::Egress, serde_json::json};
+
+#[test]
+fn extra_keys_pass() {
+    let dir = tempfile::tempdir().unwrap();
+
+    let mut egress = Egress::open(dir.path(), "subset").unwrap();
+    let artifact = egress.artifact("config");
+    artifact.insert_json("settings", json!({ "name": "fruits", "count": 2 }));
+    egress.close().unwrap().assert_unregressed();
+
+    let mut egress = Egress::open(dir.path(), "subset").unwrap();
+    let artifact = egress.artifact("config");
+    artifact.insert_json(
+        "settings",
+        json!({ "name": "fruits", "count": 2, "colour": "red" }),
+    );
+    artifact.insert_display("summary", &"two fruits");
+    artifact.subset_match();
+    let report = egress.close().unwrap();
+
+    assert!(report.is_empty(), "{:?}", report.mismatches());
+}
+
+#[test]
+fn missing_keys_still_fail() {
+    let dir = tempfile::tempdir().unwrap();
+
+    let mut egress = Egress::open(dir.path(), "subset").unwrap();
+    let artifact = egress.artifact("config");
+    artifact.insert_json("settings", json!({ "name": "fruits", "count": 2 }));
+    artifact.insert_json("list", json!([1, 2]));
+    egress.close().unwrap().assert_unregressed();
+
+    let mut egress = Egress::open(dir.path(), "subset").unwrap();
+    let artifact = egress.artifact("config");
+    artifact.insert_json("settings", json!({ "name": "fruits", "colour": "red" }));
+    artifact.insert_json("list", json!([1, 2, 3]));
+    artifact.subset_match();
+    let report = egress.close().unwrap();
+
+    report.assert_mismatch("config.settings.count");
+    report.assert_mismatch("config.list[2]");
+    assert!(!report.has_mismatch("config.settings.colour"));
+}
@@ -0,0 +1,73 @@
+use {
+    egress::Egress,
+    std::time::{Duration, UNIX_EPOCH},
+};
+
+#[test]
+fn durations_within_atol() {
+    let dir = tempfile::tempdir().unwrap();
+
+    let mut egress = Egress::open(dir.path(), "time").unwrap();
+    egress
+        .artifact("timings")
+        .insert_duration("elapsed", Duration::from_micros(1_500_000));
+    egress.close().unwrap().assert_unregressed();
+
+    // Three microseconds apart, within an `atol` of ten microseconds.
+    let mut egress = Egress::open(dir.path(), "time").unwrap();
+    egress.atol = Some(10_000.0);
+    egress.rtol = None;
+    egress
+        .artifact("timings")
+        .insert_duration("elapsed", Duration::from_micros(1_500_003));
+    egress.close().unwrap().assert_unregressed();
+
+    let mut egress = Egress::open(dir.path(), "time").unwrap();
+    egress
+        .artifact("timings")
+        .insert_duration("elapsed", Duration::from_micros(1_500_003));
+    egress.close().unwrap().assert_mismatch("timings.elapsed");
+}
+
+#[test]
+fn system_times_within_atol() {
+    let dir = tempfile::tempdir().unwrap();
+    let time = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+
+    let mut egress = Egress::open(dir.path(), "time").unwrap();
+    egress
+        .artifact("timings")
+        .insert_system_time("started", time);
+    egress.close().unwrap().assert_unregressed();
+
+    let mut egress = Egress::open(dir.path(), "time").unwrap();
+    egress.atol = Some(1.0);
+    egress.rtol = None;
+    egress
+        .artifact("timings")
+        .insert_system_time("started", time + Duration::from_micros(500));
+    egress.close().unwrap().assert_unregressed();
+
+    let mut egress = Egress::open(dir.path(), "time").unwrap();
+    egress.atol = Some(1.0);
+    egress.rtol = None;
+    egress
+        .artifact("timings")
+        .insert_system_time("started", time + Duration::from_millis(5));
+    egress.close().unwrap().assert_mismatch("timings.started");
+}
+
+#[test]
+fn system_time_before_epoch() {
+    let dir = tempfile::tempdir().unwrap();
+
+    let mut egress = Egress::open(dir.path(), "time").unwrap();
+    egress
+        .artifact("timings")
+        .insert_system_time("started", UNIX_EPOCH - Duration::from_millis(1500));
+    egress.close().unwrap().assert_unregressed();
+
+    let reference =
+        std::fs::read_to_string(dir.path().join("egress/artifacts/time/timings.json")).unwrap();
+    assert!(reference.contains("-1500.0"), "{}", reference);
+}
@@ -0,0 +1,375 @@
+use {
+    egress::{Artifact, Egress, Entry, ToleranceMode},
+    serde_json::json,
+};
+
+#[test]
+fn exact_subtree() {
+    let dir = tempfile::tempdir().unwrap();
+
+    let reference = json!({ "position": 1.0, "checksum": { "low": 2.0, "high": 3.0 } });
+    let drifted = json!({ "position": 1.01, "checksum": { "low": 2.01, "high": 3.0 } });
+
+    let mut egress = Egress::open(dir.path(), "tolerance").unwrap();
+    egress.artifact("exact").insert_json("result", reference);
+    egress.close().unwrap().assert_unregressed();
+
+    let mut egress = Egress::open(dir.path(), "tolerance").unwrap();
+    egress.atol = Some(0.1);
+    egress.rtol = None;
+    let artifact = egress.artifact("exact");
+    artifact.insert_json("result", drifted);
+    artifact.exact_subtree("result.checksum");
+    let report = egress.close().unwrap();
+
+    report.assert_mismatch("exact.result.checksum.low");
+    assert!(!report.has_mismatch("exact.result.position"));
+    assert!(!report.has_mismatch("exact.result.checksum.high"));
+}
+
+fn compare(reference: f64, value: f64, configure: impl FnOnce(&mut Egress)) -> bool {
+    let dir = tempfile::tempdir().unwrap();
+
+    let mut egress = Egress::open(dir.path(), "tolerance").unwrap();
+    egress.artifact("float").insert_json("x", json!(reference));
+    egress.close().unwrap().assert_unregressed();
+
+    let mut egress = Egress::open(dir.path(), "tolerance").unwrap();
+    configure(&mut egress);
+    egress.artifact("float").insert_json("x", json!(value));
+    !egress.close().unwrap().has_mismatch("float.x")
+}
+
+fn tolerance(atol: Option<f64>, rtol: Option<f64>) -> impl FnOnce(&mut Egress) {
+    move |egress| {
+        egress.atol = atol;
+        egress.rtol = rtol;
+    }
+}
+
+#[test]
+fn rtol_is_symmetric() {
+    assert!(compare(100.0, 105.0, tolerance(None, Some(0.1))));
+    assert!(compare(100.0, 95.0, tolerance(None, Some(0.1))));
+    assert!(!compare(100.0, 120.0, tolerance(None, Some(0.1))));
+    assert!(!compare(100.0, 50.0, tolerance(None, Some(0.1))));
+}
+
+#[test]
+fn combined_tolerance_catches_downward_regression() {
+    assert!(compare(100.0, 95.0, tolerance(Some(10.0), Some(0.1))));
+    assert!(!compare(100.0, 50.0, tolerance(Some(100.0), Some(0.1))));
+}
+
+#[test]
+fn combined_tolerance_mode() {
+    let combined = |egress: &mut Egress| {
+        tolerance(Some(3.0), Some(0.03))(egress);
+        egress.tolerance_mode = ToleranceMode::Combined;
+    };
+
+    // |a - b| = 5 exceeds both atol and rtol * |b| on their own, but not their sum.
+    assert!(!compare(100.0, 105.0, tolerance(Some(3.0), Some(0.03))));
+    assert!(compare(100.0, 105.0, combined));
+    assert!(!compare(100.0, 107.0, combined));
+}
+
+#[test]
+fn per_artifact_tolerance() {
+    let dir = tempfile::tempdir().unwrap();
+
+    let mut egress = Egress::open(dir.path(), "tolerance").unwrap();
+    egress
+        .artifact("simulation")
+        .insert_json("energy", json!(1.0));
+    egress
+        .artifact("counts")
+        .insert_json("particles", json!(1000.0));
+    egress.close().unwrap().assert_unregressed();
+
+    let mut egress = Egress::open(dir.path(), "tolerance").unwrap();
+    egress.atol = Some(0.0);
+    egress.rtol = None;
+    let simulation = egress.artifact("simulation");
+    simulation.insert_json("energy", json!(1.001));
+    simulation.set_atol(Some(0.01));
+    egress
+        .artifact("counts")
+        .insert_json("particles", json!(1000.001));
+    let report = egress.close().unwrap();
+
+    assert!(!report.has_mismatch("simulation.energy"));
+    report.assert_mismatch("counts.particles");
+}
+
+#[test]
+fn large_u64_precision() {
+    let dir = tempfile::tempdir().unwrap();
+
+    let mut egress = Egress::open(dir.path(), "tolerance").unwrap();
+    egress.artifact("integer").insert_json("x", json!(u64::MAX));
+    egress.close().unwrap().assert_unregressed();
+
+    // Both of these round to the same `f64`.
+    let mut egress = Egress::open(dir.path(), "tolerance").unwrap();
+    egress
+        .artifact("integer")
+        .insert_json("x", json!(u64::MAX - 1));
+    egress.close().unwrap().assert_mismatch("integer.x");
+}
+
+#[test]
+fn strict_number_types() {
+    for &strict in &[false, true] {
+        let dir = tempfile::tempdir().unwrap();
+
+        let mut egress = Egress::open(dir.path(), "tolerance").unwrap();
+        egress.artifact("number").insert_json("x", json!(1));
+        egress.close().unwrap().assert_unregressed();
+
+        let mut egress = Egress::open(dir.path(), "tolerance").unwrap();
+        egress.strict_number_types = strict;
+        egress.artifact("number").insert_json("x", json!(1.0));
+        assert_eq!(egress.close().unwrap().has_mismatch("number.x"), strict);
+    }
+}
+
+#[test]
+fn path_tolerance() {
+    let dir = tempfile::tempdir().unwrap();
+
+    let reference = json!({ "temperature": 20.0, "pressure": 1.0, "runs": [{ "time": 1.0 }] });
+    let drifted = json!({ "temperature": 20.4, "pressure": 1.001, "runs": [{ "time": 1.2 }] });
+
+    let mut egress = Egress::open(dir.path(), "tolerance").unwrap();
+    egress.artifact("path").insert_json("result", reference);
+    egress.close().unwrap().assert_unregressed();
+
+    let mut egress = Egress::open(dir.path(), "tolerance").unwrap();
+    let artifact = egress.artifact("path");
+    artifact.insert_json("result", drifted);
+    artifact.set_tolerance("result.temperature", Some(0.5), None);
+    artifact.set_tolerance("result.runs[*]", None, Some(0.25));
+    let report = egress.close().unwrap();
+
+    assert_eq!(report.len(), 1, "{:?}", report.mismatches());
+    report.assert_mismatch("path.result.pressure");
+}
+
+#[test]
+fn integer_atol() {
+    let dir = tempfile::tempdir().unwrap();
+
+    let mut egress = Egress::open(dir.path(), "tolerance").unwrap();
+    egress
+        .artifact("timestamps")
+        .insert_json("run", json!({ "started": 1700000000, "count": 3 }));
+    egress.close().unwrap().assert_unregressed();
+
+    let close_with = |integer_atol| {
+        let mut egress = Egress::open(dir.path(), "tolerance").unwrap();
+        egress.atol = Some(2.0);
+        egress.integer_atol = integer_atol;
+        egress
+            .artifact("timestamps")
+            .insert_json("run", json!({ "started": 1700000001, "count": 6 }));
+        egress.close().unwrap()
+    };
+
+    let report = close_with(false);
+    report.assert_mismatch("timestamps.run.started");
+    report.assert_mismatch("timestamps.run.count");
+
+    let report = close_with(true);
+    assert!(!report.has_mismatch("timestamps.run.started"));
+    report.assert_mismatch("timestamps.run.count");
+}
+
+#[test]
+fn path_tolerance_recursive_wildcard() {
+    let dir = tempfile::tempdir().unwrap();
+
+    let reference = json!({ "a": { "loss": 1.0, "b": [{ "loss": 2.0 }] }, "accuracy": 0.5 });
+    let drifted = json!({ "a": { "loss": 1.05, "b": [{ "loss": 2.05 }] }, "accuracy": 0.55 });
+
+    let mut egress = Egress::open(dir.path(), "tolerance").unwrap();
+    egress.artifact("glob").insert_json("result", reference);
+    egress.close().unwrap().assert_unregressed();
+
+    let mut egress = Egress::open(dir.path(), "tolerance").unwrap();
+    let artifact = egress.artifact("glob");
+    artifact.insert_json("result", drifted);
+    artifact.set_tolerance("result.**.loss", Some(0.1), None);
+    let report = egress.close().unwrap();
+
+    assert_eq!(report.len(), 1, "{:?}", report.mismatches());
+    report.assert_mismatch("glob.result.accuracy");
+}
+
+#[test]
+fn ulps() {
+    let dir = tempfile::tempdir().unwrap();
+    let step = |f: f64, n: i64| f64::from_bits((f.to_bits() as i64 + n) as u64);
+    let tiny = f64::from_bits(1);
+
+    let mut egress = Egress::open(dir.path(), "tolerance").unwrap();
+    egress
+        .artifact("ulps")
+        .insert_json("values", json!([1.0, 1e300, tiny, 1e-300]));
+    egress.close().unwrap().assert_unregressed();
+
+    let close = |values: serde_json::Value| {
+        let mut egress = Egress::open(dir.path(), "tolerance").unwrap();
+        egress.ulps = Some(2);
+        egress.artifact("ulps").insert_json("values", values);
+        egress.close().unwrap()
+    };
+
+    // Within two representable floats, including across zero.
+    close(json!([
+        step(1.0, 2),
+        step(1e300, -2),
+        -tiny,
+        step(1e-300, 1)
+    ]))
+    .assert_unregressed();
+
+    let report = close(json!([
+        step(1.0, 3),
+        step(1e300, -1),
+        step(tiny, 3),
+        1e-300
+    ]));
+    assert_eq!(report.len(), 2, "{:?}", report.mismatches());
+    report.assert_mismatch("ulps.values[0]");
+    report.assert_mismatch("ulps.values[2]");
+}
+
+#[test]
+fn single_precision() {
+    let dir = tempfile::tempdir().unwrap();
+    let close = |value: serde_json::Value, single_precision: bool| {
+        let mut egress = Egress::open(dir.path(), "tolerance").unwrap();
+        let artifact = egress.artifact("f32");
+        artifact.insert_json("result", value);
+        if single_precision {
+            artifact.single_precision("result");
+        }
+        egress.close().unwrap()
+    };
+
+    let result = [0.1f32, 1.1f32, 3e-20f32];
+    close(json!(result), true).assert_unregressed();
+
+    // The same values read back from the reference, stored as `f64`s.
+    close(json!(result), true).assert_unregressed();
+
+    // A couple of `f32`s off, and the `f64`s the same computation would have produced.
+    let drifted = [
+        0.1f64,
+        f32::from_bits(1.1f32.to_bits() + 2).into(),
+        3e-20f64,
+    ];
+    close(json!(drifted), true).assert_unregressed();
+    let report = close(json!(drifted), false);
+    assert_eq!(report.len(), 3, "{}", report);
+
+    let far = [
+        0.1f64,
+        f32::from_bits(1.1f32.to_bits() + 5).into(),
+        3e-20f64,
+    ];
+    let report = close(json!(far), true);
+    assert_eq!(report.len(), 1, "{}", report);
+    report.assert_mismatch("f32.result[1]");
+}
+
+#[test]
+fn numeric_strings() {
+    let dir = tempfile::tempdir().unwrap();
+    let close = |amount: &str, numeric: bool| {
+        let mut egress = Egress::open(dir.path(), "tolerance").unwrap();
+        egress.atol = None;
+        egress.rtol = None;
+        let artifact = egress.artifact("strings");
+        artifact.insert_json("result", json!({ "amount": amount, "label": amount }));
+        artifact.set_tolerance("result.amount", Some(1e-6), None);
+        if numeric {
+            artifact.numeric_strings("result.amount");
+        }
+        egress.close().unwrap()
+    };
+
+    close("1.0", true).assert_unregressed();
+
+    let report = close("1.0000001", true);
+    assert_eq!(report.len(), 1, "{}", report);
+    report.assert_mismatch("strings.result.label");
+
+    let report = close("1.0000001", false);
+    assert_eq!(report.len(), 2, "{}", report);
+    report.assert_mismatch("strings.result.amount");
+
+    let report = close("1.001", true);
+    report.assert_mismatch("strings.result.amount");
+}
+
+/// An artifact holding `values` in an artifact nested under `nested`.
+fn nested(values: serde_json::Value) -> Artifact {
+    let mut inner = Artifact::new();
+    inner.insert_json("values", values);
+    let mut outer = Artifact::new();
+    outer.insert("nested", Entry::Artifact(inner));
+    outer
+}
+
+#[test]
+fn exact_subtree_in_nested_artifact() {
+    let reference = nested(json!({ "x": 1.0, "y": 1.0 }));
+    let mut drifted = nested(json!({ "x": 1.01, "y": 1.01 }));
+    assert!(drifted.diff(&reference, Some(0.1), None).is_empty());
+
+    drifted.exact_subtree("nested.values.x");
+    let mismatches = drifted.diff(&reference, Some(0.1), None);
+    assert_eq!(mismatches.len(), 1, "{:?}", mismatches);
+    assert_eq!(mismatches[0].path(), "nested.values.x");
+}
+
+#[test]
+fn set_tolerance_in_nested_artifact() {
+    let reference = nested(json!({ "x": 1.0, "y": 1.0 }));
+    let mut drifted = nested(json!({ "x": 1.5, "y": 1.01 }));
+
+    drifted.set_tolerance("nested.values.x", Some(1.0), None);
+    assert!(drifted.diff(&reference, Some(0.1), None).is_empty());
+
+    let mut drifted = nested(json!({ "x": 1.5, "y": 1.5 }));
+    drifted.set_tolerance("nested.values.x", Some(1.0), None);
+    let mismatches = drifted.diff(&reference, Some(0.1), None);
+    assert_eq!(mismatches.len(), 1, "{:?}", mismatches);
+    assert_eq!(mismatches[0].path(), "nested.values.y");
+}
+
+#[test]
+fn single_precision_in_nested_artifact() {
+    let drifted: f64 = f32::from_bits(1.1f32.to_bits() + 2).into();
+    let reference = nested(json!([1.1f32]));
+    let mut drifted = nested(json!([drifted]));
+    assert_eq!(drifted.diff(&reference, None, None).len(), 1);
+
+    drifted.single_precision("nested.values");
+    let mismatches = drifted.diff(&reference, None, None);
+    assert!(mismatches.is_empty(), "{:?}", mismatches);
+}
+
+#[test]
+fn numeric_strings_in_nested_artifact() {
+    let reference = nested(json!({ "amount": "1.0" }));
+    let mut drifted = nested(json!({ "amount": "1.0000001" }));
+    drifted.set_tolerance("nested.values.amount", Some(1e-6), None);
+    assert_eq!(drifted.diff(&reference, None, None).len(), 1);
+
+    drifted.numeric_strings("nested.values.amount");
+    let mismatches = drifted.diff(&reference, None, None);
+    assert!(mismatches.is_empty(), "{:?}", mismatches);
+}
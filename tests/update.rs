@@ -0,0 +1,30 @@
+use {egress::Egress, std::fs};
+
+#[test]
+fn update_overwrites_regressed_reference() {
+    let dir = tempfile::tempdir().unwrap();
+    let reference_path = dir.path().join("egress/artifacts/update/fruits.json");
+
+    let mut egress = Egress::open(dir.path(), "update").unwrap();
+    egress.artifact("fruits").insert_display("fruit", &"apple");
+    egress.close().unwrap().assert_unregressed();
+    let original = fs::read_to_string(&reference_path).unwrap();
+
+    std::env::set_var("EGRESS_UPDATE", "1");
+    let mut egress = Egress::open(dir.path(), "update").unwrap();
+    egress.artifact("fruits").insert_display("fruit", &"pear");
+    let report = egress.close().unwrap();
+    std::env::remove_var("EGRESS_UPDATE");
+
+    assert_eq!(report.summary().compared, 1);
+    assert_eq!(report.summary().regressed, 0);
+    report.assert_unregressed();
+    let updated = fs::read_to_string(&reference_path).unwrap();
+    assert_ne!(original, updated);
+    assert!(updated.contains("pear"));
+
+    // Without the environment variable, the new reference is what gets compared against.
+    let mut egress = Egress::open(dir.path(), "update").unwrap();
+    egress.artifact("fruits").insert_display("fruit", &"pear");
+    egress.close().unwrap().assert_unregressed();
+}